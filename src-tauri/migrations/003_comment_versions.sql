@@ -0,0 +1,4 @@
+-- Anchor comments to the version they were made on
+ALTER TABLE comments ADD COLUMN version_id INTEGER REFERENCES versions(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_comments_version_id ON comments(version_id);
@@ -0,0 +1,10 @@
+-- Track lineage so restores and forks can branch history
+ALTER TABLE versions ADD COLUMN parent_version_id INTEGER REFERENCES versions(id) ON DELETE SET NULL;
+
+-- Existing history is linear: each version's parent is the previous one of its document
+UPDATE versions SET parent_version_id = (
+  SELECT MAX(prev.id) FROM versions prev
+  WHERE prev.document_path = versions.document_path AND prev.id < versions.id
+);
+
+CREATE INDEX idx_versions_parent ON versions(parent_version_id);
@@ -0,0 +1,15 @@
+-- One row per AI request, for cost tracking and model comparisons
+CREATE TABLE IF NOT EXISTS ai_usage (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  timestamp INTEGER NOT NULL,
+  kind TEXT NOT NULL,
+  model TEXT NOT NULL,
+  input_tokens INTEGER NOT NULL DEFAULT 0,
+  output_tokens INTEGER NOT NULL DEFAULT 0,
+  cost_usd REAL NOT NULL DEFAULT 0,
+  first_token_ms INTEGER,
+  duration_ms INTEGER
+);
+
+CREATE INDEX idx_ai_usage_timestamp ON ai_usage(timestamp DESC);
+CREATE INDEX idx_ai_usage_model ON ai_usage(model);
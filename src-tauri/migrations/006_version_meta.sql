@@ -0,0 +1,10 @@
+-- Per-version key/value metadata (cached summaries, provenance, flags)
+CREATE TABLE IF NOT EXISTS version_meta (
+  version_id INTEGER NOT NULL REFERENCES versions(id) ON DELETE CASCADE,
+  key TEXT NOT NULL,
+  value TEXT NOT NULL,
+  updated_at INTEGER NOT NULL,
+  PRIMARY KEY (version_id, key)
+);
+
+CREATE INDEX idx_version_meta_key ON version_meta(key);
@@ -0,0 +1,9 @@
+-- Free-form tags for organizing version history
+CREATE TABLE IF NOT EXISTS version_tags (
+  version_id INTEGER NOT NULL REFERENCES versions(id) ON DELETE CASCADE,
+  tag TEXT NOT NULL,
+  created_at INTEGER NOT NULL,
+  PRIMARY KEY (version_id, tag)
+);
+
+CREATE INDEX idx_version_tags_tag ON version_tags(tag);
@@ -0,0 +1,4 @@
+-- Replies point at the comment they answer
+ALTER TABLE comments ADD COLUMN parent_id TEXT REFERENCES comments(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_comments_parent_id ON comments(parent_id);
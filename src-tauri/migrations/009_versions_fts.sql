@@ -0,0 +1,27 @@
+-- Full-text search over version content and labels, kept in sync by triggers
+CREATE VIRTUAL TABLE IF NOT EXISTS versions_fts USING fts5(
+  content,
+  checkpoint_name,
+  content = 'versions',
+  content_rowid = 'id'
+);
+
+CREATE TRIGGER versions_fts_insert AFTER INSERT ON versions BEGIN
+  INSERT INTO versions_fts (rowid, content, checkpoint_name)
+  VALUES (new.id, new.content, new.checkpoint_name);
+END;
+
+CREATE TRIGGER versions_fts_delete AFTER DELETE ON versions BEGIN
+  INSERT INTO versions_fts (versions_fts, rowid, content, checkpoint_name)
+  VALUES ('delete', old.id, old.content, old.checkpoint_name);
+END;
+
+CREATE TRIGGER versions_fts_update AFTER UPDATE OF content, checkpoint_name ON versions BEGIN
+  INSERT INTO versions_fts (versions_fts, rowid, content, checkpoint_name)
+  VALUES ('delete', old.id, old.content, old.checkpoint_name);
+  INSERT INTO versions_fts (rowid, content, checkpoint_name)
+  VALUES (new.id, new.content, new.checkpoint_name);
+END;
+
+-- Index existing history
+INSERT INTO versions_fts (versions_fts) VALUES ('rebuild');
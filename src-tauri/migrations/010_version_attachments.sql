@@ -0,0 +1,11 @@
+-- File references attached to a version. Only paths are stored, never file contents.
+CREATE TABLE IF NOT EXISTS version_attachments (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  version_id INTEGER NOT NULL REFERENCES versions(id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  path TEXT NOT NULL,
+  mime TEXT NOT NULL,
+  created_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_version_attachments_version_id ON version_attachments(version_id);
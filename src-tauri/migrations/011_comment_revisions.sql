@@ -0,0 +1,9 @@
+-- Prior bodies of edited comments, for an audit trail of review notes
+CREATE TABLE IF NOT EXISTS comment_revisions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  comment_id TEXT NOT NULL REFERENCES comments(id) ON DELETE CASCADE,
+  text TEXT NOT NULL,
+  edited_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_comment_revisions_comment_id ON comment_revisions(comment_id);
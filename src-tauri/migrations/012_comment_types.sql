@@ -0,0 +1,4 @@
+-- Review category for color-coding and filtering feedback
+ALTER TABLE comments ADD COLUMN comment_type TEXT NOT NULL DEFAULT 'note';
+
+CREATE INDEX idx_comments_type ON comments(comment_type);
@@ -0,0 +1,12 @@
+-- One embedding per version. `vector` is little-endian f32s; `content_hash` is the
+-- content it was computed from, so unchanged versions aren't re-embedded.
+CREATE TABLE IF NOT EXISTS version_embeddings (
+  version_id INTEGER PRIMARY KEY REFERENCES versions(id) ON DELETE CASCADE,
+  model TEXT NOT NULL,
+  content_hash TEXT NOT NULL,
+  dimensions INTEGER NOT NULL,
+  vector BLOB NOT NULL,
+  created_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_version_embeddings_model ON version_embeddings(model);
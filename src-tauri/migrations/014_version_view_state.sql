@@ -0,0 +1,7 @@
+-- Where the editor was in each version, so reopening it picks up in the same place
+CREATE TABLE IF NOT EXISTS version_view_state (
+  version_id INTEGER PRIMARY KEY REFERENCES versions(id) ON DELETE CASCADE,
+  cursor_offset INTEGER NOT NULL,
+  scroll_ratio REAL NOT NULL,
+  updated_at INTEGER NOT NULL
+);
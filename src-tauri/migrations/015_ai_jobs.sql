@@ -0,0 +1,13 @@
+-- Persistent queue of AI requests processed one at a time by a background worker.
+-- `job` is the request as JSON; status is pending, running, done, failed or cancelled.
+CREATE TABLE IF NOT EXISTS ai_jobs (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  job TEXT NOT NULL,
+  status TEXT NOT NULL DEFAULT 'pending',
+  result TEXT,
+  error TEXT,
+  created_at INTEGER NOT NULL,
+  updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_ai_jobs_status ON ai_jobs(status, id);
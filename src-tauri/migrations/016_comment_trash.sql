@@ -0,0 +1,5 @@
+-- Soft delete for comments. Trashing a thread stamps its replies with the same time,
+-- so restoring brings back exactly what was trashed together.
+ALTER TABLE comments ADD COLUMN deleted_at INTEGER;
+
+CREATE INDEX idx_comments_deleted_at ON comments(deleted_at);
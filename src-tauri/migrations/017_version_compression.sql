@@ -0,0 +1,4 @@
+-- Old versions can be gzipped into compressed_content, leaving content empty.
+-- The FTS update trigger drops their text from the search index.
+ALTER TABLE versions ADD COLUMN compressed BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE versions ADD COLUMN compressed_content BLOB;
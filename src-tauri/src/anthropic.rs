@@ -0,0 +1,435 @@
+//! Minimal Anthropic Messages API client shared by the AI commands.
+
+use eventsource_stream::Eventsource;
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::Value;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const API_VERSION: &str = "2023-06-01";
+
+/// Rough chars-per-token ratio for English prose, used for budgeting only
+pub(crate) const CHARS_PER_TOKEN: usize = 4;
+
+/// USD per million (input, output) tokens, matched by model-name family
+const PRICING: &[(&str, f64, f64)] = &[
+    ("opus", 15.0, 75.0),
+    ("sonnet", 3.0, 15.0),
+    ("haiku", 0.8, 4.0),
+];
+
+#[derive(Debug, Clone, Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct MessagesBody<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    messages: Vec<Message<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'a str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+/// A single-turn completion request
+#[derive(Debug, Clone)]
+pub struct CompletionRequest {
+    pub model: String,
+    pub prompt: String,
+    pub system: Option<String>,
+    pub max_tokens: u32,
+}
+
+impl CompletionRequest {
+    fn body(&self, stream: bool) -> MessagesBody<'_> {
+        MessagesBody {
+            model: &self.model,
+            max_tokens: self.max_tokens,
+            messages: vec![Message {
+                role: "user",
+                content: &self.prompt,
+            }],
+            system: self.system.as_deref(),
+            stream,
+        }
+    }
+}
+
+/// Full text of a completion plus the token usage the API reported
+#[derive(Debug, Clone, Default)]
+pub struct Completion {
+    pub text: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Result of a streamed completion. `completion.text` holds whatever arrived
+/// before the stream ended or was cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct StreamOutcome {
+    pub completion: Completion,
+    pub cancelled: bool,
+}
+
+/// How often an async wait checks a cancellation flag
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+/// Run `future` unless `cancelled` is set before it completes. `None` means it was
+/// cancelled; the future is dropped, releasing whatever it was waiting for.
+pub(crate) async fn unless_cancelled<F: Future>(
+    cancelled: &AtomicBool,
+    future: F,
+) -> Option<F::Output> {
+    let poll_flag = async {
+        while !cancelled.load(Ordering::SeqCst) {
+            tokio::time::sleep(CANCEL_POLL).await;
+        }
+    };
+    if cancelled.load(Ordering::SeqCst) {
+        return None;
+    }
+    let future = std::pin::pin!(future);
+    let poll_flag = std::pin::pin!(poll_flag);
+    match futures::future::select(future, poll_flag).await {
+        futures::future::Either::Left((output, _)) => Some(output),
+        futures::future::Either::Right(_) => None,
+    }
+}
+
+/// Rough token count of `text`, for progress before the API reports usage
+pub fn estimate_tokens(text: &str) -> u64 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u64
+}
+
+/// Trim `text` to roughly `max_tokens`, cutting on a line boundary where possible.
+/// Returns the text unchanged if it already fits.
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> String {
+    let max_chars = max_tokens * CHARS_PER_TOKEN;
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut = text
+        .char_indices()
+        .nth(max_chars)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+    let end = text[..cut].rfind('\n').unwrap_or(cut);
+    format!("{}\n[truncated]", &text[..end])
+}
+
+/// Estimated cost in USD. Unknown models are priced at zero.
+pub fn cost_usd(model: &str, input_tokens: u64, output_tokens: u64) -> f64 {
+    let model = model.to_lowercase();
+    PRICING
+        .iter()
+        .find(|(family, _, _)| model.contains(family))
+        .map(|(_, input, output)| {
+            (input_tokens as f64 * input + output_tokens as f64 * output) / 1_000_000.0
+        })
+        .unwrap_or(0.0)
+}
+
+/// Check a custom base URL: https, or http for localhost only.
+/// Returns it without a trailing slash.
+pub fn validate_base_url(url: &str) -> Result<String, String> {
+    let parsed = reqwest::Url::parse(url.trim())
+        .map_err(|e| format!("Validation: invalid base URL: {}", e))?;
+    let is_local = matches!(
+        parsed.host_str(),
+        Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+    );
+    match parsed.scheme() {
+        "https" => {}
+        "http" if is_local => {}
+        "http" => return Err("Validation: http is only allowed for localhost".to_string()),
+        scheme => return Err(format!("Validation: unsupported URL scheme '{}'", scheme)),
+    }
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+/// How long a request may wait: `idle` for the next bytes to arrive (reset by
+/// every streamed chunk), `overall` for the whole request
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    pub idle: Duration,
+    pub overall: Duration,
+}
+
+/// Fail with `Timeout` if `future` doesn't finish within `limit`
+async fn within<T>(
+    limit: Duration,
+    what: &str,
+    future: impl Future<Output = Result<T, String>>,
+) -> Result<T, String> {
+    tokio::time::timeout(limit, future)
+        .await
+        .unwrap_or_else(|_| Err(format!("Timeout: {} within {}s", what, limit.as_secs())))
+}
+
+/// Send a request, waiting at most `idle` for the response headers
+async fn send_within(
+    request: reqwest::RequestBuilder,
+    idle: Duration,
+) -> Result<reqwest::Response, String> {
+    within(idle, "no response", async {
+        request
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))
+    })
+    .await
+}
+
+/// A response's JSON body, waiting at most `idle` for it to arrive
+async fn json_within(response: reqwest::Response, idle: Duration) -> Result<Value, String> {
+    within(idle, "no response body", async {
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid response: {}", e))
+    })
+    .await
+}
+
+/// POST to the Messages API, turning non-2xx responses into `ApiError` messages
+async fn send(
+    base_url: &str,
+    idle: Duration,
+    api_key: &str,
+    body: &MessagesBody<'_>,
+) -> Result<reqwest::Response, String> {
+    let request = reqwest::Client::new()
+        .post(format!("{}/v1/messages", base_url))
+        .header("x-api-key", api_key)
+        .header("anthropic-version", API_VERSION)
+        .json(body);
+    let response = send_within(request, idle).await?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let json = json_within(response, idle).await.unwrap_or(Value::Null);
+    Err(api_error_message(status.as_u16(), &json))
+}
+
+/// Ids of the models available to this key, newest first
+pub async fn list_models(
+    base_url: &str,
+    timeouts: Timeouts,
+    api_key: &str,
+) -> Result<Vec<String>, String> {
+    let request = reqwest::Client::new()
+        .get(format!("{}/v1/models?limit=100", base_url))
+        .header("x-api-key", api_key)
+        .header("anthropic-version", API_VERSION)
+        .timeout(timeouts.overall);
+    let response = send_within(request, timeouts.idle).await?;
+    let status = response.status();
+    let json = match json_within(response, timeouts.idle).await {
+        Err(e) if e.starts_with("Timeout") => return Err(e),
+        json => json.unwrap_or(Value::Null),
+    };
+    if !status.is_success() {
+        return Err(api_error_message(status.as_u16(), &json));
+    }
+    Ok(json["data"]
+        .as_array()
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|m| m["id"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Embed `input` via `/v1/embeddings` on the configured base URL. The Anthropic API
+/// itself has no embeddings endpoint, so this needs a compatible gateway set with
+/// `ai_set_base_url`.
+pub async fn embed(
+    base_url: &str,
+    timeouts: Timeouts,
+    api_key: &str,
+    model: &str,
+    input: &str,
+) -> Result<Vec<f32>, String> {
+    let request = reqwest::Client::new()
+        .post(format!("{}/v1/embeddings", base_url))
+        .header("x-api-key", api_key)
+        .header("anthropic-version", API_VERSION)
+        .json(&serde_json::json!({ "model": model, "input": [input] }))
+        .timeout(timeouts.overall);
+    let response = send_within(request, timeouts.idle).await?;
+    let status = response.status();
+    let json = match json_within(response, timeouts.idle).await {
+        Err(e) if e.starts_with("Timeout") => return Err(e),
+        json => json.unwrap_or(Value::Null),
+    };
+    if !status.is_success() {
+        return Err(api_error_message(status.as_u16(), &json));
+    }
+    let vector: Vec<f32> = json["data"][0]["embedding"]
+        .as_array()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|v| v.as_f64().map(|v| v as f32))
+                .collect()
+        })
+        .unwrap_or_default();
+    if vector.is_empty() {
+        return Err("Invalid response: no embedding returned".to_string());
+    }
+    Ok(vector)
+}
+
+/// Send a non-streaming request to the Messages API
+pub async fn complete(
+    base_url: &str,
+    timeouts: Timeouts,
+    api_key: &str,
+    request: &CompletionRequest,
+) -> Result<Completion, String> {
+    let json = within(timeouts.overall, "request did not finish", async {
+        let response = send(base_url, timeouts.idle, api_key, &request.body(false)).await?;
+        json_within(response, timeouts.idle).await
+    })
+    .await?;
+
+    let text = json["content"]
+        .as_array()
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter_map(|block| block["text"].as_str())
+                .collect::<String>()
+        })
+        .unwrap_or_default();
+
+    Ok(Completion {
+        text,
+        input_tokens: json["usage"]["input_tokens"].as_u64().unwrap_or(0),
+        output_tokens: json["usage"]["output_tokens"].as_u64().unwrap_or(0),
+    })
+}
+
+/// Stream a completion over SSE, calling `on_text` for each text delta.
+/// Stops early (with `cancelled: true`) once `cancelled` is set, even while the
+/// connection is stalled, since waits are raced against the flag. Fails with
+/// `Timeout` if no event arrives within `timeouts.idle`, or the stream outlives
+/// `timeouts.overall`.
+pub async fn stream<F>(
+    base_url: &str,
+    timeouts: Timeouts,
+    api_key: &str,
+    request: &CompletionRequest,
+    cancelled: &AtomicBool,
+    mut on_text: F,
+) -> Result<StreamOutcome, String>
+where
+    F: FnMut(&str),
+{
+    let deadline = Instant::now() + timeouts.overall;
+    let mut outcome = StreamOutcome::default();
+    let body = request.body(true);
+    let Some(response) =
+        unless_cancelled(cancelled, send(base_url, timeouts.idle, api_key, &body)).await
+    else {
+        outcome.cancelled = true;
+        return Ok(outcome);
+    };
+    let mut events = response?.bytes_stream().eventsource();
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let wait = timeouts.idle.min(remaining);
+        let Some(next) =
+            unless_cancelled(cancelled, tokio::time::timeout(wait, events.next())).await
+        else {
+            outcome.cancelled = true;
+            break;
+        };
+        let event = match next {
+            Ok(Some(event)) => event,
+            Ok(None) => break,
+            Err(_) if wait < timeouts.idle => {
+                return Err(format!(
+                    "Timeout: stream did not finish within {}s",
+                    timeouts.overall.as_secs()
+                ))
+            }
+            Err(_) => {
+                return Err(format!(
+                    "Timeout: no data within {}s",
+                    timeouts.idle.as_secs()
+                ))
+            }
+        };
+        if cancelled.load(Ordering::SeqCst) {
+            outcome.cancelled = true;
+            break;
+        }
+        let event = event.map_err(|e| format!("Stream error: {}", e))?;
+        let Ok(data) = serde_json::from_str::<Value>(&event.data) else {
+            continue;
+        };
+
+        match event.event.as_str() {
+            "message_start" => {
+                let usage = &data["message"]["usage"];
+                outcome.completion.input_tokens = usage["input_tokens"].as_u64().unwrap_or(0);
+            }
+            "content_block_delta" => {
+                if let Some(text) = data["delta"]["text"].as_str() {
+                    outcome.completion.text.push_str(text);
+                    on_text(text);
+                }
+            }
+            "message_delta" => {
+                if let Some(tokens) = data["usage"]["output_tokens"].as_u64() {
+                    outcome.completion.output_tokens = tokens;
+                }
+            }
+            "error" => return Err(api_error_message(200, &data)),
+            _ => {}
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// Format an API error response as `ApiError: kind (status): message`
+fn api_error_message(status: u16, json: &Value) -> String {
+    let kind = json["error"]["type"].as_str().unwrap_or("api_error");
+    let message = json["error"]["message"].as_str().unwrap_or("unknown error");
+    format!("ApiError: {} ({}): {}", kind, status, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unless_cancelled_skips_work_once_flagged() {
+        let flag = AtomicBool::new(true);
+        let ran = std::cell::Cell::new(false);
+        let result = futures::executor::block_on(unless_cancelled(&flag, async { ran.set(true) }));
+        assert!(result.is_none());
+        assert!(!ran.get());
+    }
+
+    #[test]
+    fn unless_cancelled_returns_ready_output() {
+        let flag = AtomicBool::new(false);
+        let result = futures::executor::block_on(unless_cancelled(&flag, async { 7 }));
+        assert_eq!(result, Some(7));
+    }
+}
@@ -1,22 +1,199 @@
-use tauri::AppHandle;
-use tauri_plugin_keyring::KeyringExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::anthropic::{self, Completion, CompletionRequest, StreamOutcome};
+use crate::commands::ai_usage::{record_usage, UsageRecord};
+use crate::commands::cancel::CancelRegistry;
+use crate::commands::versions::{latest_version_id, load_version};
+use crate::rate_limit::{ConcurrencyConfig, ConcurrencyLimiter, RateLimitConfig, RateLimiter};
+use crate::{db, diff, settings};
 
 const SERVICE: &str = "com.serq.app";
 const KEY_NAME: &str = "anthropic-api-key";
+/// Keychain entry of each provider's API key
+const PROVIDER_KEYS: &[(&str, &str)] = &[("anthropic", KEY_NAME)];
+/// Throwaway entry written and deleted by `keyring_available`
+const PROBE_KEY_NAME: &str = "keychain-probe";
+
+/// Diff budget for label suggestions; labels don't need the whole change
+const LABEL_DIFF_TOKEN_BUDGET: usize = 2000;
+const LABEL_MAX_CHARS: usize = 80;
+
+pub(crate) const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Store key prefix for per-provider base URL overrides
+pub(crate) const BASE_URL_KEY_PREFIX: &str = "ai_base_url_";
+
+/// Providers that accept a base URL override
+pub(crate) const PROVIDERS: &[&str] = &["anthropic"];
+
+/// Store key: whether the frontend should use `ai_complete_stream` (default) or `ai_complete`
+pub(crate) const STREAMING_ENABLED_KEY: &str = "ai_streaming_enabled";
+
+/// Store keys and defaults for request timeouts, in seconds
+pub(crate) const REQUEST_TIMEOUT_KEY: &str = "ai_request_timeout_secs";
+pub(crate) const OVERALL_TIMEOUT_KEY: &str = "ai_overall_timeout_secs";
+pub(crate) const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 60;
+pub(crate) const DEFAULT_OVERALL_TIMEOUT_SECS: u64 = 600;
+
+/// Store keys for per-document system prompts (a map keyed by document path)
+/// and the default used for documents without one
+pub(crate) const DOCUMENT_PROMPTS_KEY: &str = "ai_document_system_prompts";
+pub(crate) const DEFAULT_SYSTEM_PROMPT_KEY: &str = "ai_default_system_prompt";
+/// Document content budget for `ai_complete_with_context`
+const CONTEXT_TOKEN_BUDGET: usize = 50_000;
+
+/// Store key and default for how often streams emit `ai-usage-tick`; 0 turns ticks off
+pub(crate) const USAGE_TICK_KEY: &str = "ai_usage_tick_ms";
+pub(crate) const DEFAULT_USAGE_TICK_MS: u64 = 250;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AiToken<'a> {
+    request_id: &'a str,
+    text: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AiDone<'a> {
+    request_id: &'a str,
+    text: &'a str,
+    cancelled: bool,
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AiUsageTick<'a> {
+    request_id: &'a str,
+    input_tokens: u64,
+    output_tokens: u64,
+    /// False only for the last tick, which carries the API's own counts
+    estimated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AiError<'a> {
+    request_id: &'a str,
+    kind: &'a str,
+    message: &'a str,
+}
+
+/// A keychain failure, classified so the UI can tell the user how to fix it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyringError {
+    /// No keychain service is running (typically Linux without a secret service)
+    NoBackend,
+    Locked,
+    AccessDenied,
+    NoEntry,
+    Other,
+}
+
+impl KeyringError {
+    /// Sort a platform error into a variant. Backends report most failures as
+    /// opaque platform errors, so those are matched on their message.
+    pub fn classify(error: &keyring::Error) -> Self {
+        let message = match error {
+            keyring::Error::NoEntry => return KeyringError::NoEntry,
+            keyring::Error::NoStorageAccess(e) | keyring::Error::PlatformFailure(e) => {
+                e.to_string().to_lowercase()
+            }
+            _ => return KeyringError::Other,
+        };
+        let has = |needles: &[&str]| needles.iter().any(|n| message.contains(n));
+        if has(&["locked"]) {
+            KeyringError::Locked
+        } else if has(&[
+            "serviceunknown",
+            "org.freedesktop.secrets",
+            "not provided by any .service",
+            "dbus",
+            "no such interface",
+        ]) {
+            KeyringError::NoBackend
+        } else if has(&[
+            "denied",
+            "not allowed",
+            "-25293",
+            "user canceled",
+            "unauthorized",
+        ]) {
+            KeyringError::AccessDenied
+        } else if matches!(error, keyring::Error::NoStorageAccess(_)) {
+            KeyringError::AccessDenied
+        } else {
+            KeyringError::Other
+        }
+    }
+
+    /// Error kind prefix for this variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            KeyringError::NoBackend => "KeyringNoBackend",
+            KeyringError::Locked => "KeyringLocked",
+            KeyringError::AccessDenied => "KeyringAccessDenied",
+            KeyringError::NoEntry => "KeyringNoEntry",
+            KeyringError::Other => "KeyringError",
+        }
+    }
+
+    /// What the user can do about it
+    pub fn guidance(&self) -> &'static str {
+        match self {
+            KeyringError::NoBackend => {
+                "No system keychain is available. On Linux, install and start a Secret \
+                 Service provider such as GNOME Keyring or KWallet, then restart SERQ."
+            }
+            KeyringError::Locked => {
+                "The system keychain is locked. Unlock it (for example by logging in \
+                 again) and retry."
+            }
+            KeyringError::AccessDenied => {
+                "SERQ was denied access to the system keychain. Allow access when \
+                 prompted, or grant it in your keychain settings."
+            }
+            KeyringError::NoEntry => "No API key is stored. Add one in Settings.",
+            KeyringError::Other => "The system keychain reported an unexpected error.",
+        }
+    }
+}
+
+/// `Kind: guidance (action: platform error)`, for returning from key commands
+fn keyring_error(action: &str, error: keyring::Error) -> String {
+    let classified = KeyringError::classify(&error);
+    format!(
+        "{}: {} ({}: {})",
+        classified.kind(),
+        classified.guidance(),
+        action,
+        error
+    )
+}
+
+fn key_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(SERVICE, KEY_NAME).map_err(|e| keyring_error("Failed to open keychain", e))
+}
 
 /// Store API key in system keychain (macOS Keychain)
 #[tauri::command]
-pub fn set_api_key(app: AppHandle, key: String) -> Result<(), String> {
-    app.keyring()
-        .set(SERVICE, KEY_NAME, &key)
-        .map_err(|e| format!("Failed to store API key: {}", e))
+pub fn set_api_key(key: String) -> Result<(), String> {
+    key_entry()?
+        .set_password(&key)
+        .map_err(|e| keyring_error("Failed to store API key", e))
 }
 
 /// Retrieve API key from keychain
 /// Returns None if no key is stored
 #[tauri::command]
-pub fn get_api_key(app: AppHandle) -> Result<Option<String>, String> {
-    match app.keyring().get(SERVICE, KEY_NAME) {
+pub fn get_api_key() -> Result<Option<String>, String> {
+    match key_entry()?.get_password() {
         Ok(key) => {
             // Treat empty string as "not set"
             if key.is_empty() {
@@ -25,17 +202,552 @@ pub fn get_api_key(app: AppHandle) -> Result<Option<String>, String> {
                 Ok(Some(key))
             }
         }
-        Err(tauri_plugin_keyring::Error::NoEntry) => Ok(None),
-        Err(e) => Err(format!("Failed to retrieve API key: {}", e)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(keyring_error("Failed to retrieve API key", e)),
     }
 }
 
 /// Check if API key exists without retrieving it
 #[tauri::command]
-pub fn has_api_key(app: AppHandle) -> Result<bool, String> {
-    match app.keyring().get(SERVICE, KEY_NAME) {
-        Ok(key) => Ok(!key.is_empty()),
-        Err(tauri_plugin_keyring::Error::NoEntry) => Ok(false),
-        Err(e) => Err(format!("Failed to check API key: {}", e)),
+pub fn has_api_key() -> Result<bool, String> {
+    Ok(get_api_key()?.is_some())
+}
+
+/// Probe the keychain with a set/get/delete round-trip on a throwaway entry.
+/// Returns false rather than erroring when the platform keychain doesn't work.
+#[tauri::command]
+pub fn keyring_available() -> Result<bool, String> {
+    let Ok(entry) = keyring::Entry::new(SERVICE, PROBE_KEY_NAME) else {
+        return Ok(false);
+    };
+    let probe = uuid::Uuid::new_v4().to_string();
+    if entry.set_password(&probe).is_err() {
+        return Ok(false);
+    }
+    let round_trip = entry.get_password().is_ok_and(|value| value == probe);
+    // Clean up even if the read failed
+    let deleted = entry.delete_credential().is_ok();
+    Ok(round_trip && deleted)
+}
+
+/// A provider with a stored key, and enough of the key to recognize it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyInfo {
+    pub provider: String,
+    /// e.g. `sk-...abcd`; short keys show only `...`
+    pub preview: String,
+}
+
+/// First three and last four characters of a key, or nothing for keys too short
+/// to hide anything
+fn mask_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() < 12 {
+        return "...".to_string();
+    }
+    let head: String = chars[..3].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", head, tail)
+}
+
+/// Every provider with a stored API key, with a masked preview. Never returns
+/// the keys themselves.
+#[tauri::command]
+pub fn list_all_keys() -> Result<Vec<KeyInfo>, String> {
+    let mut keys = Vec::new();
+    for (provider, name) in PROVIDER_KEYS {
+        let entry = keyring::Entry::new(SERVICE, name)
+            .map_err(|e| keyring_error("Failed to open keychain", e))?;
+        match entry.get_password() {
+            Ok(key) if !key.is_empty() => keys.push(KeyInfo {
+                provider: provider.to_string(),
+                preview: mask_key(&key),
+            }),
+            Ok(_) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(keyring_error("Failed to read API key", e)),
+        }
+    }
+    Ok(keys)
+}
+
+/// Delete every provider's API key from the keychain. Returns how many were removed.
+#[tauri::command]
+pub fn revoke_all_keys() -> Result<usize, String> {
+    let mut removed = 0;
+    for (_, name) in PROVIDER_KEYS {
+        let entry = keyring::Entry::new(SERVICE, name)
+            .map_err(|e| keyring_error("Failed to open keychain", e))?;
+        match entry.delete_credential() {
+            Ok(()) => removed += 1,
+            Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(keyring_error("Failed to delete API key", e)),
+        }
+    }
+    Ok(removed)
+}
+
+/// Get the stored key or fail with a `NoApiKey` error the UI can act on
+pub(crate) fn require_api_key() -> Result<String, String> {
+    get_api_key()?.ok_or_else(|| {
+        "NoApiKey: no Anthropic API key is stored. Add one in Settings to use AI features."
+            .to_string()
+    })
+}
+
+/// Suggest a short, commit-style label describing the change between two versions
+#[tauri::command]
+pub async fn ai_suggest_label(
+    app: AppHandle,
+    from_id: i64,
+    to_id: i64,
+    model: String,
+) -> Result<String, String> {
+    let api_key = require_api_key()?;
+
+    let diff_text = {
+        let conn = db::open(&app)?;
+        let from = load_version(&conn, from_id)?;
+        let to = load_version(&conn, to_id)?;
+        diff::unified_diff(&from.content, &to.content)
+    };
+    if diff_text.is_empty() {
+        return Err("NoChanges: the two versions have identical content".to_string());
+    }
+
+    let request = CompletionRequest {
+        model,
+        system: Some(
+            "You write version labels for a document editor. Reply with the label only."
+                .to_string(),
+        ),
+        prompt: format!(
+            "Summarize this document change as a short imperative label of at most eight words, \
+             like a commit subject line.\n\n{}",
+            anthropic::truncate_to_tokens(&diff_text, LABEL_DIFF_TOKEN_BUDGET)
+        ),
+        max_tokens: 64,
+    };
+    let completion = complete_with_usage(&app, &api_key, "suggest_label", &request).await?;
+
+    Ok(clean_label(&completion.text))
+}
+
+/// First line of the model's reply, without quotes or a trailing period, capped in length
+fn clean_label(text: &str) -> String {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("");
+    let line = line.trim_matches(|c: char| c == '"' || c == '\'' || c == '`');
+    let line = line.trim_end_matches('.');
+    line.chars().take(LABEL_MAX_CHARS).collect()
+}
+
+/// Model ids fetched from the API, kept for the session
+#[derive(Default)]
+pub struct ModelCache(pub Mutex<Option<Vec<String>>>);
+
+/// Fetch the model list and cache it
+pub(crate) async fn refresh_models(app: &AppHandle) -> Result<Vec<String>, String> {
+    let api_key = require_api_key()?;
+    let models = anthropic::list_models(&base_url(app), timeouts(app), &api_key).await?;
+    *app.state::<ModelCache>().0.lock().unwrap() = Some(models.clone());
+    Ok(models)
+}
+
+/// Available model ids, from the session cache unless `refresh` is set
+#[tauri::command]
+pub async fn ai_list_models(app: AppHandle, refresh: Option<bool>) -> Result<Vec<String>, String> {
+    let cached = app.state::<ModelCache>().0.lock().unwrap().clone();
+    if let Some(models) = cached.filter(|_| !refresh.unwrap_or(false)) {
+        return Ok(models);
+    }
+    refresh_models(&app).await
+}
+
+/// Anthropic base URL: the stored override, or the default
+pub(crate) fn base_url(app: &AppHandle) -> String {
+    settings::get::<String>(app, &format!("{}anthropic", BASE_URL_KEY_PREFIX))
+        .unwrap_or_else(|| anthropic::DEFAULT_BASE_URL.to_string())
+}
+
+/// Request timeouts from the store, or the defaults
+pub(crate) fn timeouts(app: &AppHandle) -> anthropic::Timeouts {
+    let idle = settings::get(app, REQUEST_TIMEOUT_KEY).unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS);
+    let overall = settings::get(app, OVERALL_TIMEOUT_KEY).unwrap_or(DEFAULT_OVERALL_TIMEOUT_SECS);
+    anthropic::Timeouts {
+        idle: Duration::from_secs(idle),
+        overall: Duration::from_secs(overall.max(idle)),
+    }
+}
+
+/// Abort AI requests that receive nothing for `secs` (streams reset the timer on
+/// every chunk) or that run longer than `overall_secs` in total. Timed-out
+/// requests fail with `Timeout`, which streaming commands report as `ai-error`.
+#[tauri::command]
+pub fn ai_set_request_timeout(
+    app: AppHandle,
+    secs: u64,
+    overall_secs: Option<u64>,
+) -> Result<(), String> {
+    if secs == 0 {
+        return Err("Validation: secs must be greater than 0".to_string());
+    }
+    if let Some(overall) = overall_secs {
+        if overall < secs {
+            return Err("Validation: overall_secs must be at least secs".to_string());
+        }
+        settings::set(&app, OVERALL_TIMEOUT_KEY, overall)?;
+    }
+    settings::set(&app, REQUEST_TIMEOUT_KEY, secs)
+}
+
+fn usage_tick_interval(app: &AppHandle) -> Option<Duration> {
+    let ms = settings::get(app, USAGE_TICK_KEY).unwrap_or(DEFAULT_USAGE_TICK_MS);
+    (ms > 0).then(|| Duration::from_millis(ms))
+}
+
+/// Set how often streaming emits `ai-usage-tick` with running token estimates.
+/// 0 turns the ticks off, including the final one.
+#[tauri::command]
+pub fn ai_set_usage_tick_interval(app: AppHandle, ms: u64) -> Result<(), String> {
+    settings::set(&app, USAGE_TICK_KEY, ms)
+}
+
+/// Route a provider through a proxy or compatible gateway. `None` restores the default.
+#[tauri::command]
+pub fn ai_set_base_url(
+    app: AppHandle,
+    provider: String,
+    url: Option<String>,
+) -> Result<(), String> {
+    if !PROVIDERS.contains(&provider.as_str()) {
+        return Err(format!("Validation: unknown provider '{}'", provider));
+    }
+    let key = format!("{}{}", BASE_URL_KEY_PREFIX, provider);
+    match url {
+        Some(url) => settings::set(&app, &key, anthropic::validate_base_url(&url)?),
+        None => settings::remove(&app, &key),
+    }
+}
+
+/// Record usage without failing the request that produced it
+fn log_usage(app: &AppHandle, usage: UsageRecord) {
+    if let Err(e) = record_usage(app, &usage) {
+        eprintln!("{}", e);
+    }
+}
+
+/// Non-streaming completion that records usage the same way the streaming path does
+pub(crate) async fn complete_with_usage(
+    app: &AppHandle,
+    api_key: &str,
+    kind: &'static str,
+    request: &CompletionRequest,
+) -> Result<Completion, String> {
+    let concurrency = app.state::<ConcurrencyLimiter>();
+    let _permit = concurrency.acquire().await?;
+    app.state::<RateLimiter>().acquire().await;
+    let started = Instant::now();
+    let completion = anthropic::complete(&base_url(app), timeouts(app), api_key, request).await?;
+    log_usage(
+        app,
+        UsageRecord {
+            kind,
+            model: request.model.clone(),
+            input_tokens: completion.input_tokens,
+            output_tokens: completion.output_tokens,
+            first_token_ms: None,
+            duration_ms: started.elapsed().as_millis() as u64,
+        },
+    );
+    Ok(completion)
+}
+
+/// Kind prefix of an error string (`Kind: message`), for the `ai-error` event
+pub(crate) fn error_kind(message: &str) -> &str {
+    message
+        .split_once(':')
+        .map(|(kind, _)| kind)
+        .filter(|kind| !kind.contains(' '))
+        .unwrap_or("Error")
+}
+
+/// Stream a completion with the standard events: `ai-token` per chunk, then `ai-done`
+/// or `ai-error`. Registers `request_id` for cancellation before waiting for a
+/// concurrency permit or rate-limit slot, so queued requests can be cancelled too;
+/// those end with `cancelled: true` without being sent. Records usage and latency.
+/// Running token estimates go out as `ai-usage-tick` at the configured interval,
+/// followed by one with the API's counts.
+pub(crate) async fn stream_with_events(
+    app: &AppHandle,
+    request_id: &str,
+    kind: &'static str,
+    request: CompletionRequest,
+) -> Result<StreamOutcome, String> {
+    let cancelled = app.state::<CancelRegistry>().register(request_id);
+    let result = async {
+        let api_key = require_api_key()?;
+        let cancelled_outcome = || -> Result<StreamOutcome, String> {
+            Ok(StreamOutcome {
+                cancelled: true,
+                ..Default::default()
+            })
+        };
+        let concurrency = app.state::<ConcurrencyLimiter>();
+        let Some(permit) = anthropic::unless_cancelled(&cancelled, concurrency.acquire()).await
+        else {
+            return cancelled_outcome();
+        };
+        let _permit = permit?;
+        let limiter = app.state::<RateLimiter>();
+        if anthropic::unless_cancelled(&cancelled, limiter.acquire())
+            .await
+            .is_none()
+        {
+            return cancelled_outcome();
+        }
+        let started = Instant::now();
+        let mut first_token_ms = None;
+        let tick_interval = usage_tick_interval(app);
+        let input_estimate = anthropic::estimate_tokens(&request.prompt)
+            + request
+                .system
+                .as_deref()
+                .map_or(0, anthropic::estimate_tokens);
+        let mut output_chars = 0;
+        let mut last_tick = started;
+
+        let base_url = base_url(app);
+        let timeouts = timeouts(app);
+        let outcome = anthropic::stream(
+            &base_url,
+            timeouts,
+            &api_key,
+            &request,
+            &cancelled,
+            |text| {
+                if first_token_ms.is_none() {
+                    first_token_ms = Some(started.elapsed().as_millis() as u64);
+                }
+                let _ = app.emit("ai-token", AiToken { request_id, text });
+                output_chars += text.chars().count();
+                if tick_interval.is_some_and(|interval| last_tick.elapsed() >= interval) {
+                    last_tick = Instant::now();
+                    let _ = app.emit(
+                        "ai-usage-tick",
+                        AiUsageTick {
+                            request_id,
+                            input_tokens: input_estimate,
+                            output_tokens: output_chars.div_ceil(anthropic::CHARS_PER_TOKEN) as u64,
+                            estimated: true,
+                        },
+                    );
+                }
+            },
+        )
+        .await;
+        let outcome = outcome?;
+        if tick_interval.is_some() {
+            let _ = app.emit(
+                "ai-usage-tick",
+                AiUsageTick {
+                    request_id,
+                    input_tokens: outcome.completion.input_tokens,
+                    output_tokens: outcome.completion.output_tokens,
+                    estimated: false,
+                },
+            );
+        }
+
+        log_usage(
+            app,
+            UsageRecord {
+                kind,
+                model: request.model.clone(),
+                input_tokens: outcome.completion.input_tokens,
+                output_tokens: outcome.completion.output_tokens,
+                first_token_ms,
+                duration_ms: started.elapsed().as_millis() as u64,
+            },
+        );
+        Ok::<_, String>(outcome)
+    }
+    .await;
+    app.state::<CancelRegistry>().finish(request_id);
+
+    match &result {
+        Ok(outcome) => {
+            let _ = app.emit(
+                "ai-done",
+                AiDone {
+                    request_id,
+                    text: &outcome.completion.text,
+                    cancelled: outcome.cancelled,
+                    input_tokens: outcome.completion.input_tokens,
+                    output_tokens: outcome.completion.output_tokens,
+                },
+            );
+        }
+        Err(message) => {
+            let _ = app.emit(
+                "ai-error",
+                AiError {
+                    request_id,
+                    kind: error_kind(message),
+                    message,
+                },
+            );
+        }
+    }
+    result
+}
+
+/// Stream a completion to the frontend as `ai-token` events.
+/// Cancel with `cancel_request(request_id)`.
+#[tauri::command]
+pub async fn ai_complete_stream(
+    app: AppHandle,
+    request_id: String,
+    prompt: String,
+    model: String,
+    system: Option<String>,
+) -> Result<(), String> {
+    let request = CompletionRequest {
+        model,
+        prompt,
+        system,
+        max_tokens: DEFAULT_MAX_TOKENS,
+    };
+    stream_with_events(&app, &request_id, "complete_stream", request).await?;
+    Ok(())
+}
+
+fn document_prompts(app: &AppHandle) -> HashMap<String, String> {
+    settings::get(app, DOCUMENT_PROMPTS_KEY).unwrap_or_default()
+}
+
+/// Set the system prompt AI requests about a document use. `None` or an empty
+/// prompt clears it, so the default applies again.
+#[tauri::command]
+pub fn set_document_system_prompt(
+    app: AppHandle,
+    document_path: String,
+    prompt: Option<String>,
+) -> Result<(), String> {
+    let mut prompts = document_prompts(&app);
+    match prompt.filter(|p| !p.trim().is_empty()) {
+        Some(prompt) => prompts.insert(document_path, prompt),
+        None => prompts.remove(&document_path),
+    };
+    settings::set(&app, DOCUMENT_PROMPTS_KEY, prompts)
+}
+
+/// The system prompt in effect for a document: its own, else the default, if any
+#[tauri::command]
+pub fn get_document_system_prompt(app: AppHandle, document_path: String) -> Option<String> {
+    document_prompts(&app)
+        .remove(&document_path)
+        .or_else(|| settings::get(&app, DEFAULT_SYSTEM_PROMPT_KEY))
+}
+
+/// Set the system prompt for documents without their own. `None` clears it.
+#[tauri::command]
+pub fn set_default_system_prompt(app: AppHandle, prompt: Option<String>) -> Result<(), String> {
+    match prompt.filter(|p| !p.trim().is_empty()) {
+        Some(prompt) => settings::set(&app, DEFAULT_SYSTEM_PROMPT_KEY, prompt),
+        None => settings::remove(&app, DEFAULT_SYSTEM_PROMPT_KEY),
+    }
+}
+
+/// Stream a completion about a document, like `ai_complete_stream`, with the
+/// document's latest content included ahead of `prompt` and its system prompt
+/// (or the default) applied
+#[tauri::command]
+pub async fn ai_complete_with_context(
+    app: AppHandle,
+    request_id: String,
+    document_path: String,
+    prompt: String,
+    model: String,
+) -> Result<(), String> {
+    let content = {
+        let conn = db::open(&app)?;
+        match latest_version_id(&conn, &document_path)? {
+            Some(id) => load_version(&conn, id)?.content,
+            None => return Err(format!("NotFound: {} has no versions", document_path)),
+        }
+    };
+    let request = CompletionRequest {
+        model,
+        system: get_document_system_prompt(app.clone(), document_path),
+        prompt: format!(
+            "<document>\n{}\n</document>\n\n{}",
+            anthropic::truncate_to_tokens(&content, CONTEXT_TOKEN_BUDGET),
+            prompt
+        ),
+        max_tokens: DEFAULT_MAX_TOKENS,
+    };
+    stream_with_events(&app, &request_id, "complete_with_context", request).await?;
+    Ok(())
+}
+
+/// Single-request completion returning the full text, for environments that proxy SSE poorly
+#[tauri::command]
+pub async fn ai_complete(app: AppHandle, prompt: String, model: String) -> Result<String, String> {
+    let api_key = require_api_key()?;
+    let request = CompletionRequest {
+        model,
+        prompt,
+        system: None,
+        max_tokens: DEFAULT_MAX_TOKENS,
+    };
+    let completion = complete_with_usage(&app, &api_key, "complete", &request).await?;
+    Ok(completion.text)
+}
+
+/// Choose between streaming and non-streaming completions
+#[tauri::command]
+pub fn set_ai_streaming(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::set(&app, STREAMING_ENABLED_KEY, enabled)
+}
+
+/// Whether streaming completions are enabled (default true)
+#[tauri::command]
+pub fn get_ai_streaming(app: AppHandle) -> bool {
+    settings::get(&app, STREAMING_ENABLED_KEY).unwrap_or(true)
+}
+
+/// Cap how many AI requests may start per minute across all commands
+#[tauri::command]
+pub fn ai_set_rate_limit(app: AppHandle, requests_per_minute: u32) -> Result<(), String> {
+    if requests_per_minute == 0 {
+        return Err("Validation: requests_per_minute must be greater than 0".to_string());
+    }
+    app.state::<RateLimiter>().set_config(RateLimitConfig {
+        requests_per_minute,
+    });
+    Ok(())
+}
+
+/// Cap how many AI requests may be in flight at once. With `queue_when_full`
+/// (the default) extra requests wait for a slot; otherwise they fail with `Busy`.
+#[tauri::command]
+pub fn ai_set_max_concurrency(
+    app: AppHandle,
+    n: u32,
+    queue_when_full: Option<bool>,
+) -> Result<(), String> {
+    if n == 0 {
+        return Err("Validation: max concurrency must be greater than 0".to_string());
     }
+    let limiter = app.state::<ConcurrencyLimiter>();
+    let queue_when_full = queue_when_full.unwrap_or(limiter.config().queue_when_full);
+    limiter.set_config(ConcurrencyConfig {
+        max_concurrent: n,
+        queue_when_full,
+    });
+    Ok(())
 }
@@ -0,0 +1,103 @@
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::anthropic::CompletionRequest;
+use crate::commands::ai::{stream_with_events, DEFAULT_MAX_TOKENS};
+use crate::commands::version_meta::{get_meta, set_meta};
+use crate::commands::versions::{create_version, load_version};
+use crate::db;
+
+const DRAFT_LABEL: &str = "AI draft";
+/// `version_meta` keys linking a draft to what generated it. The prompt is
+/// stored as a SHA-256 hash, never raw.
+const PROMPT_HASH_META: &str = "ai_prompt_hash";
+const MODEL_META: &str = "ai_model";
+const INPUT_TOKENS_META: &str = "ai_input_tokens";
+const OUTPUT_TOKENS_META: &str = "ai_output_tokens";
+
+/// What produced an AI-generated version
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    pub model: String,
+    pub prompt_hash: Option<String>,
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+}
+
+/// Stream a completion (as `ai-token` events) and save the result as a new version
+/// labeled "AI draft". A cancelled stream saves nothing unless `save_partial` is set.
+/// Returns the new version id.
+#[tauri::command]
+pub async fn ai_generate_version(
+    app: AppHandle,
+    request_id: String,
+    document_path: String,
+    prompt: String,
+    model: String,
+    save_partial: Option<bool>,
+) -> Result<i64, String> {
+    let request = CompletionRequest {
+        model: model.clone(),
+        prompt: prompt.clone(),
+        system: None,
+        max_tokens: DEFAULT_MAX_TOKENS,
+    };
+    let outcome = stream_with_events(&app, &request_id, "generate_version", request).await?;
+
+    let completion = outcome.completion;
+    let text = completion.text;
+    if outcome.cancelled && !(save_partial.unwrap_or(false) && !text.is_empty()) {
+        return Err("Cancelled: generation was cancelled, no version saved".to_string());
+    }
+
+    let version = create_version(
+        app.clone(),
+        document_path,
+        text,
+        Some(DRAFT_LABEL.to_string()),
+        None,
+        None,
+        None,
+    )?;
+    let conn = db::open(&app)?;
+    set_meta(&conn, version.id, MODEL_META, &model)?;
+    set_meta(
+        &conn,
+        version.id,
+        PROMPT_HASH_META,
+        &db::content_hash(&prompt),
+    )?;
+    set_meta(
+        &conn,
+        version.id,
+        INPUT_TOKENS_META,
+        &completion.input_tokens.to_string(),
+    )?;
+    set_meta(
+        &conn,
+        version.id,
+        OUTPUT_TOKENS_META,
+        &completion.output_tokens.to_string(),
+    )?;
+    Ok(version.id)
+}
+
+/// Provenance recorded by `ai_generate_version`, or None for human-authored versions
+#[tauri::command]
+pub fn get_version_provenance(app: AppHandle, id: i64) -> Result<Option<Provenance>, String> {
+    let conn = db::open(&app)?;
+    load_version(&conn, id)?;
+    let Some(model) = get_meta(&conn, id, MODEL_META)? else {
+        return Ok(None);
+    };
+    let tokens = |key: &str| -> Result<Option<u64>, String> {
+        Ok(get_meta(&conn, id, key)?.and_then(|value| value.parse().ok()))
+    };
+    Ok(Some(Provenance {
+        model,
+        prompt_hash: get_meta(&conn, id, PROMPT_HASH_META)?,
+        input_tokens: tokens(INPUT_TOKENS_META)?,
+        output_tokens: tokens(OUTPUT_TOKENS_META)?,
+    }))
+}
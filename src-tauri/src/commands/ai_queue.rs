@@ -0,0 +1,243 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+use crate::anthropic::CompletionRequest;
+use crate::commands::ai::{complete_with_usage, require_api_key, DEFAULT_MAX_TOKENS};
+use crate::commands::ai_summary::summarize;
+use crate::db;
+
+/// How long the worker waits before retrying after a database error
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// A queued AI request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum AiJob {
+    /// Summarize a version, as `ai_summarize_version` does
+    Summarize { version_id: i64, model: String },
+    /// A plain completion; the reply text is stored as the job result
+    Complete {
+        prompt: String,
+        model: String,
+        system: Option<String>,
+    },
+}
+
+/// Wakes the background worker when jobs are added
+#[derive(Default)]
+pub struct AiQueue {
+    wake: Notify,
+}
+
+/// Job counts by status, plus the job currently running
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStatus {
+    pub pending: usize,
+    pub running: Option<i64>,
+    pub done: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AiJobDone<'a> {
+    id: i64,
+    result: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AiJobFailed<'a> {
+    id: i64,
+    error: &'a str,
+}
+
+/// Claim the oldest pending job. Rows that no longer parse are marked failed.
+fn next_job(app: &AppHandle) -> Result<Option<(i64, AiJob)>, String> {
+    let conn = db::open(app)?;
+    loop {
+        let row: Option<(i64, String)> = conn
+            .query_row(
+                "SELECT id, job FROM ai_jobs WHERE status = 'pending' ORDER BY id LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let Some((id, json)) = row else {
+            return Ok(None);
+        };
+        match serde_json::from_str::<AiJob>(&json) {
+            Ok(job) => {
+                conn.execute(
+                    "UPDATE ai_jobs SET status = 'running', updated_at = ?1 WHERE id = ?2",
+                    rusqlite::params![db::now_ms(), id],
+                )
+                .map_err(|e| e.to_string())?;
+                return Ok(Some((id, job)));
+            }
+            Err(e) => finish_job(app, id, Err(format!("Validation: invalid job: {}", e)))?,
+        }
+    }
+}
+
+async fn run_job(app: &AppHandle, job: AiJob) -> Result<String, String> {
+    let api_key = require_api_key()?;
+    match job {
+        AiJob::Summarize { version_id, model } => {
+            let (summary, _) = summarize(app, &api_key, version_id, &model).await?;
+            Ok(summary)
+        }
+        AiJob::Complete {
+            prompt,
+            model,
+            system,
+        } => {
+            let request = CompletionRequest {
+                model,
+                prompt,
+                system,
+                max_tokens: DEFAULT_MAX_TOKENS,
+            };
+            let completion = complete_with_usage(app, &api_key, "queued_job", &request).await?;
+            Ok(completion.text)
+        }
+    }
+}
+
+/// Store a job's outcome and announce it, unless it was cancelled while running
+fn finish_job(app: &AppHandle, id: i64, outcome: Result<String, String>) -> Result<(), String> {
+    let conn = db::open(app)?;
+    let (status, result, error) = match &outcome {
+        Ok(result) => ("done", Some(result.as_str()), None),
+        Err(error) => ("failed", None, Some(error.as_str())),
+    };
+    let updated = conn
+        .execute(
+            "UPDATE ai_jobs SET status = ?1, result = ?2, error = ?3, updated_at = ?4
+             WHERE id = ?5 AND status IN ('pending', 'running')",
+            rusqlite::params![status, result, error, db::now_ms(), id],
+        )
+        .map_err(|e| format!("Failed to update job: {}", e))?;
+    if updated == 0 {
+        return Ok(());
+    }
+    let _ = match outcome {
+        Ok(result) => app.emit(
+            "ai-job-done",
+            AiJobDone {
+                id,
+                result: &result,
+            },
+        ),
+        Err(error) => app.emit("ai-job-failed", AiJobFailed { id, error: &error }),
+    };
+    Ok(())
+}
+
+/// Process queued jobs one at a time in the background, through the shared rate
+/// and concurrency limits. Jobs left running by a previous run start over.
+pub fn spawn_ai_worker(app: AppHandle) {
+    let reset = db::open(&app).and_then(|conn| {
+        conn.execute(
+            "UPDATE ai_jobs SET status = 'pending' WHERE status = 'running'",
+            [],
+        )
+        .map_err(|e| e.to_string())
+    });
+    if let Err(e) = reset {
+        eprintln!("AI queue reset failed: {}", e);
+    }
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match next_job(&app) {
+                Ok(Some((id, job))) => {
+                    let outcome = run_job(&app, job).await;
+                    if let Err(e) = finish_job(&app, id, outcome) {
+                        eprintln!("AI job {} failed to finish: {}", id, e);
+                    }
+                }
+                Ok(None) => app.state::<AiQueue>().wake.notified().await,
+                Err(e) => {
+                    eprintln!("AI queue unavailable: {}", e);
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+            }
+        }
+    });
+}
+
+/// Add a request to the persistent AI queue. Emits `ai-job-done` or `ai-job-failed`
+/// when it has been processed. Returns the job id.
+#[tauri::command]
+pub fn ai_enqueue(app: AppHandle, job: AiJob) -> Result<i64, String> {
+    let json = serde_json::to_string(&job).map_err(|e| e.to_string())?;
+    let now = db::now_ms();
+    let conn = db::open(&app)?;
+    conn.execute(
+        "INSERT INTO ai_jobs (job, status, created_at, updated_at) VALUES (?1, 'pending', ?2, ?2)",
+        rusqlite::params![json, now],
+    )
+    .map_err(|e| format!("Failed to enqueue job: {}", e))?;
+    let id = conn.last_insert_rowid();
+    app.state::<AiQueue>().wake.notify_one();
+    Ok(id)
+}
+
+/// Job counts by status and the id of the job in progress
+#[tauri::command]
+pub fn ai_queue_status(app: AppHandle) -> Result<QueueStatus, String> {
+    let conn = db::open(&app)?;
+    let mut status = QueueStatus::default();
+    let mut stmt = conn
+        .prepare("SELECT status, COUNT(*), MIN(id) FROM ai_jobs GROUP BY status")
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let name: String = row.get(0).map_err(|e| e.to_string())?;
+        let count = row.get::<_, i64>(1).map_err(|e| e.to_string())? as usize;
+        match name.as_str() {
+            "pending" => status.pending = count,
+            "running" => status.running = row.get(2).map_err(|e| e.to_string())?,
+            "done" => status.done = count,
+            "failed" => status.failed = count,
+            "cancelled" => status.cancelled = count,
+            _ => {}
+        }
+    }
+    Ok(status)
+}
+
+/// Cancel a pending or running job. A running request still completes, but its
+/// result is discarded. Returns false if the job had already finished.
+#[tauri::command]
+pub fn ai_cancel_job(app: AppHandle, id: i64) -> Result<bool, String> {
+    let conn = db::open(&app)?;
+    let exists: Option<i64> = conn
+        .query_row("SELECT id FROM ai_jobs WHERE id = ?1", [id], |row| {
+            row.get(0)
+        })
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if exists.is_none() {
+        return Err(format!("NotFound: AI job {} does not exist", id));
+    }
+    let updated = conn
+        .execute(
+            "UPDATE ai_jobs SET status = 'cancelled', updated_at = ?1
+             WHERE id = ?2 AND status IN ('pending', 'running')",
+            rusqlite::params![db::now_ms(), id],
+        )
+        .map_err(|e| format!("Failed to cancel job: {}", e))?;
+    Ok(updated > 0)
+}
@@ -0,0 +1,149 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::anthropic::{self, CompletionRequest};
+use crate::commands::ai::{complete_with_usage, require_api_key};
+use crate::commands::version_meta::{get_meta, set_meta};
+use crate::commands::versions::load_version;
+use crate::db;
+
+const SUMMARY_KEY: &str = "summary";
+const SUMMARY_CONTENT_TOKEN_BUDGET: usize = 6000;
+
+/// Cached summary stored as JSON under the `summary` metadata key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedSummary {
+    content_hash: String,
+    model: String,
+    text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SummaryReady<'a> {
+    version_id: i64,
+    summary: &'a str,
+    cached: bool,
+}
+
+/// A summary of identical content, from this version or any other
+fn cached_summary(
+    conn: &rusqlite::Connection,
+    content_hash: &str,
+) -> Result<Option<CachedSummary>, String> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM version_meta
+             WHERE key = ?1 AND json_extract(value, '$.contentHash') = ?2 LIMIT 1",
+            rusqlite::params![SUMMARY_KEY, content_hash],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(value.and_then(|v| serde_json::from_str(&v).ok()))
+}
+
+/// Summarize a version, reusing a cached summary when its content hash matches.
+/// Returns the summary and whether it came from the cache.
+pub(crate) async fn summarize(
+    app: &AppHandle,
+    api_key: &str,
+    id: i64,
+    model: &str,
+) -> Result<(String, bool), String> {
+    let (content, content_hash, cached) = {
+        let conn = db::open(app)?;
+        let version = load_version(&conn, id)?;
+        let cached = cached_summary(&conn, &version.content_hash)?;
+        (version.content, version.content_hash, cached)
+    };
+
+    if let Some(cached) = cached {
+        // Identical content was already summarized, possibly on another version
+        let conn = db::open(app)?;
+        if get_meta(&conn, id, SUMMARY_KEY)?.is_none() {
+            let json = serde_json::to_string(&cached).map_err(|e| e.to_string())?;
+            set_meta(&conn, id, SUMMARY_KEY, &json)?;
+        }
+        return Ok((cached.text, true));
+    }
+
+    let request = CompletionRequest {
+        model: model.to_string(),
+        system: Some("You summarize drafts for a version history list.".to_string()),
+        prompt: format!(
+            "Summarize this version of the document in one or two sentences.\n\n{}",
+            anthropic::truncate_to_tokens(&content, SUMMARY_CONTENT_TOKEN_BUDGET)
+        ),
+        max_tokens: 200,
+    };
+    let summary = complete_with_usage(app, api_key, "summarize_version", &request)
+        .await?
+        .text
+        .trim()
+        .to_string();
+
+    let cached = CachedSummary {
+        content_hash,
+        model: model.to_string(),
+        text: summary.clone(),
+    };
+    let json = serde_json::to_string(&cached).map_err(|e| e.to_string())?;
+    set_meta(&db::open(app)?, id, SUMMARY_KEY, &json)?;
+    Ok((summary, false))
+}
+
+/// Summarize one version, cached by content hash in `version_meta`
+#[tauri::command]
+pub async fn ai_summarize_version(
+    app: AppHandle,
+    id: i64,
+    model: String,
+) -> Result<String, String> {
+    let api_key = require_api_key()?;
+    let (summary, _) = summarize(&app, &api_key, id, &model).await?;
+    Ok(summary)
+}
+
+/// Summarize up to `limit` versions that have no cached summary, newest first.
+/// Emits `summary-ready` per version and returns how many were summarized.
+#[tauri::command]
+pub async fn ai_summarize_missing(
+    app: AppHandle,
+    model: String,
+    limit: i64,
+) -> Result<usize, String> {
+    let api_key = require_api_key()?;
+    let ids: Vec<i64> = {
+        let conn = db::open(&app)?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id FROM versions v WHERE NOT EXISTS (
+                   SELECT 1 FROM version_meta m WHERE m.version_id = v.id AND m.key = ?1
+                 ) ORDER BY id DESC LIMIT ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map(rusqlite::params![SUMMARY_KEY, limit], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        ids
+    };
+
+    for &id in &ids {
+        let (summary, cached) = summarize(&app, &api_key, id, &model).await?;
+        let _ = app.emit(
+            "summary-ready",
+            SummaryReady {
+                version_id: id,
+                summary: &summary,
+                cached,
+            },
+        );
+    }
+
+    Ok(ids.len())
+}
@@ -0,0 +1,167 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::anthropic::{self, CompletionRequest};
+use crate::commands::ai::{complete_with_usage, require_api_key};
+use crate::commands::tags::{normalize_tag, tag_versions_bulk, version_tags};
+use crate::commands::version_meta::set_meta;
+use crate::commands::versions::load_version;
+use crate::db;
+
+const SUGGESTED_TAGS_KEY: &str = "suggested_tags";
+const TAGS_CONTENT_TOKEN_BUDGET: usize = 6000;
+const MAX_SUGGESTED_TAGS: usize = 20;
+
+/// Cached suggestions stored as JSON under the `suggested_tags` metadata key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedTags {
+    content_hash: String,
+    model: String,
+    /// How many tags were asked for; a larger request isn't served from this cache
+    max_tags: usize,
+    tags: Vec<String>,
+}
+
+/// Suggestions for identical content, from this version or any other
+fn cached_tags(
+    conn: &rusqlite::Connection,
+    content_hash: &str,
+    model: &str,
+    max_tags: usize,
+) -> Result<Option<CachedTags>, String> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM version_meta
+             WHERE key = ?1 AND json_extract(value, '$.contentHash') = ?2
+               AND json_extract(value, '$.model') = ?3
+               AND json_extract(value, '$.maxTags') >= ?4
+             LIMIT 1",
+            rusqlite::params![SUGGESTED_TAGS_KEY, content_hash, model, max_tags as i64],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(value.and_then(|v| serde_json::from_str(&v).ok()))
+}
+
+/// Every tag in use, so the model can reuse the existing vocabulary
+fn known_tags(conn: &rusqlite::Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT tag FROM version_tags ORDER BY tag")
+        .map_err(|e| e.to_string())?;
+    let tags = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(tags)
+}
+
+/// One tag per line or comma, normalized, in order, without duplicates
+fn parse_tags(reply: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for raw in reply.split(['\n', ',']) {
+        let raw = raw.trim().trim_start_matches(['-', '*', '#']).trim();
+        if let Ok(tag) = normalize_tag(raw) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
+}
+
+/// Up to `max_tags` topical tags for a version, normalized and without the tags it
+/// already has. Nothing is applied; pass the accepted ones to
+/// `ai_apply_suggested_tags`. Cached by content hash, so re-running on unchanged
+/// content makes no request.
+#[tauri::command]
+pub async fn ai_suggest_tags(
+    app: AppHandle,
+    id: i64,
+    model: String,
+    max_tags: usize,
+) -> Result<Vec<String>, String> {
+    if max_tags == 0 || max_tags > MAX_SUGGESTED_TAGS {
+        return Err(format!(
+            "Validation: max_tags must be between 1 and {}",
+            MAX_SUGGESTED_TAGS
+        ));
+    }
+    let (content, content_hash, existing, known, cached) = {
+        let conn = db::open(&app)?;
+        let version = load_version(&conn, id)?;
+        let cached = cached_tags(&conn, &version.content_hash, &model, max_tags)?;
+        (
+            version.content,
+            version.content_hash,
+            version_tags(&conn, id)?,
+            known_tags(&conn)?,
+            cached,
+        )
+    };
+
+    let suggested = match cached {
+        Some(cached) => cached.tags,
+        None => {
+            let api_key = require_api_key()?;
+            let vocabulary = if known.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    "Prefer these existing tags where they fit: {}\n\n",
+                    known.join(", ")
+                )
+            };
+            let request = CompletionRequest {
+                model: model.clone(),
+                system: Some("You tag drafts in a version history by topic.".to_string()),
+                prompt: format!(
+                    "Suggest up to {} short topical tags for this document. \
+                     Reply with one tag per line and nothing else.\n\n{}{}",
+                    max_tags,
+                    vocabulary,
+                    anthropic::truncate_to_tokens(&content, TAGS_CONTENT_TOKEN_BUDGET)
+                ),
+                max_tokens: 200,
+            };
+            let reply = complete_with_usage(&app, &api_key, "suggest_tags", &request)
+                .await?
+                .text;
+            let tags: Vec<String> = parse_tags(&reply).into_iter().take(max_tags).collect();
+
+            let cached = CachedTags {
+                content_hash,
+                model,
+                max_tags,
+                tags: tags.clone(),
+            };
+            let json = serde_json::to_string(&cached).map_err(|e| e.to_string())?;
+            set_meta(&db::open(&app)?, id, SUGGESTED_TAGS_KEY, &json)?;
+            tags
+        }
+    };
+
+    Ok(suggested
+        .into_iter()
+        .filter(|tag| !existing.contains(tag))
+        .take(max_tags)
+        .collect())
+}
+
+/// Apply accepted suggestions to a version through `tag_versions_bulk`, one
+/// `tags-changed` event per new tag. Returns how many tags were added.
+#[tauri::command]
+pub fn ai_apply_suggested_tags(
+    app: AppHandle,
+    id: i64,
+    tags: Vec<String>,
+) -> Result<usize, String> {
+    let mut added = 0;
+    for tag in tags {
+        added += tag_versions_bulk(app.clone(), vec![id], tag)?;
+    }
+    Ok(added)
+}
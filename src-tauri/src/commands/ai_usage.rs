@@ -0,0 +1,271 @@
+use chrono::{Datelike, Local, TimeZone, Utc};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::anthropic;
+use crate::commands::workspace::allowed_path;
+use crate::stats::{self, LatencyStats};
+use crate::{db, settings};
+
+/// Fewest usable requests `ai_throughput_stats` will report on
+const MIN_THROUGHPUT_SAMPLES: usize = 3;
+
+/// Store key for the monthly spend that triggers `usage-alert`; unset means no alert
+pub(crate) const USAGE_ALERT_KEY: &str = "ai_usage_alert_usd";
+/// Store key for the month (`YYYY-MM`) the alert last fired in
+const USAGE_ALERT_MONTH_KEY: &str = "ai_usage_alert_month";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageAlert<'a> {
+    month: &'a str,
+    monthly_usd: f64,
+    spent_usd: f64,
+}
+
+/// One AI request's usage, as recorded into `ai_usage`
+#[derive(Debug, Clone)]
+pub struct UsageRecord {
+    pub kind: &'static str,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub first_token_ms: Option<u64>,
+    pub duration_ms: u64,
+}
+
+/// Insert a usage row. Callers treat failures as non-fatal.
+pub fn record_usage(app: &AppHandle, usage: &UsageRecord) -> Result<(), String> {
+    let conn = db::open(app)?;
+    conn.execute(
+        "INSERT INTO ai_usage
+           (timestamp, kind, model, input_tokens, output_tokens, cost_usd,
+            first_token_ms, duration_ms)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![
+            db::now_ms(),
+            usage.kind,
+            usage.model,
+            usage.input_tokens as i64,
+            usage.output_tokens as i64,
+            anthropic::cost_usd(&usage.model, usage.input_tokens, usage.output_tokens),
+            usage.first_token_ms.map(|ms| ms as i64),
+            usage.duration_ms as i64,
+        ],
+    )
+    .map_err(|e| format!("Failed to record AI usage: {}", e))?;
+    check_usage_alert(app, &conn)
+}
+
+/// Emit `usage-alert` the first time this month's spend reaches the alert threshold
+fn check_usage_alert(app: &AppHandle, conn: &rusqlite::Connection) -> Result<(), String> {
+    let Some(monthly_usd) = settings::get::<f64>(app, USAGE_ALERT_KEY) else {
+        return Ok(());
+    };
+    let now = Local::now();
+    let month = now.format("%Y-%m").to_string();
+    if settings::get::<String>(app, USAGE_ALERT_MONTH_KEY).as_deref() == Some(month.as_str()) {
+        return Ok(());
+    }
+    let month_start = Local
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .earliest()
+        .map_or(0, |t| t.timestamp_millis());
+    let spent_usd: f64 = conn
+        .query_row(
+            "SELECT COALESCE(SUM(cost_usd), 0) FROM ai_usage WHERE timestamp >= ?1",
+            [month_start],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if spent_usd < monthly_usd {
+        return Ok(());
+    }
+    settings::set(app, USAGE_ALERT_MONTH_KEY, &month)?;
+    let _ = app.emit(
+        "usage-alert",
+        UsageAlert {
+            month: &month,
+            monthly_usd,
+            spent_usd,
+        },
+    );
+    Ok(())
+}
+
+/// Emit `usage-alert` once a month, when month-to-date AI spend reaches
+/// `monthly_usd`. 0 turns the alert off. Changing it re-arms the alert for the
+/// current month.
+#[tauri::command]
+pub fn set_usage_alert(app: AppHandle, monthly_usd: f64) -> Result<(), String> {
+    if !monthly_usd.is_finite() || monthly_usd < 0.0 {
+        return Err("Validation: monthly_usd must be a non-negative number".to_string());
+    }
+    settings::remove(&app, USAGE_ALERT_MONTH_KEY)?;
+    if monthly_usd == 0.0 {
+        return settings::remove(&app, USAGE_ALERT_KEY);
+    }
+    settings::set(&app, USAGE_ALERT_KEY, monthly_usd)
+}
+
+/// The monthly spend alert threshold in USD, if one is set
+#[tauri::command]
+pub fn get_usage_alert(app: AppHandle) -> Option<f64> {
+    settings::get(&app, USAGE_ALERT_KEY)
+}
+
+/// Latency distributions for one model
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiLatencyStats {
+    pub model: String,
+    /// Time to first streamed token (streaming requests only)
+    pub first_token: LatencyStats,
+    /// Total request duration
+    pub total: LatencyStats,
+}
+
+/// p50/p95/average latency for a model, computed from the raw `ai_usage` rows
+#[tauri::command]
+pub fn ai_latency_stats(app: AppHandle, model: String) -> Result<AiLatencyStats, String> {
+    let conn = db::open(&app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT first_token_ms, duration_ms FROM ai_usage
+             WHERE model = ?1 AND duration_ms IS NOT NULL",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([&model], |row| {
+            Ok((row.get::<_, Option<i64>>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut first_token: Vec<u64> = rows
+        .iter()
+        .filter_map(|r| r.0)
+        .map(|ms| ms as u64)
+        .collect();
+    let mut total: Vec<u64> = rows.iter().map(|r| r.1 as u64).collect();
+
+    Ok(AiLatencyStats {
+        model,
+        first_token: stats::latency_stats(&mut first_token),
+        total: stats::latency_stats(&mut total),
+    })
+}
+
+/// Sustained output generation speed for one model
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThroughputStats {
+    pub model: String,
+    pub samples: usize,
+    pub average_tokens_per_second: f64,
+    pub p95_tokens_per_second: f64,
+}
+
+/// Output tokens per second for a model from `ai_usage`. Time to first token is
+/// excluded when recorded, so this measures generation rather than queueing.
+#[tauri::command]
+pub fn ai_throughput_stats(app: AppHandle, model: String) -> Result<ThroughputStats, String> {
+    let conn = db::open(&app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT output_tokens, duration_ms - COALESCE(first_token_ms, 0) FROM ai_usage
+             WHERE model = ?1 AND output_tokens > 0 AND duration_ms IS NOT NULL",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut rates: Vec<f64> = stmt
+        .query_map([&model], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|(_, ms)| *ms > 0)
+        .map(|(tokens, ms)| tokens as f64 * 1000.0 / ms as f64)
+        .collect();
+
+    if rates.len() < MIN_THROUGHPUT_SAMPLES {
+        return Err(format!(
+            "NotFound: not enough usage data for {} ({} of {} requests needed)",
+            model,
+            rates.len(),
+            MIN_THROUGHPUT_SAMPLES
+        ));
+    }
+    rates.sort_by(|a, b| a.total_cmp(b));
+    Ok(ThroughputStats {
+        samples: rates.len(),
+        average_tokens_per_second: rates.iter().sum::<f64>() / rates.len() as f64,
+        p95_tokens_per_second: stats::percentile(&rates, 95.0),
+        model,
+    })
+}
+
+/// Write `ai_usage` rows as CSV with a header, oldest first, optionally only rows
+/// after an ISO 8601 `since` time
+#[tauri::command]
+pub fn export_ai_usage_csv(
+    app: AppHandle,
+    path: String,
+    since: Option<String>,
+) -> Result<(), String> {
+    let since = since.as_deref().map(db::parse_timestamp).transpose()?;
+    let target = allowed_path(&app, &path)?;
+    let conn = db::open(&app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp, kind, model, input_tokens, output_tokens, cost_usd,
+                    first_token_ms, duration_ms
+             FROM ai_usage WHERE timestamp > ?1 ORDER BY timestamp, id",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+        .query([since.unwrap_or(i64::MIN)])
+        .map_err(|e| e.to_string())?;
+
+    let mut writer =
+        csv::Writer::from_path(&target).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    writer
+        .write_record([
+            "timestamp",
+            "kind",
+            "model",
+            "input_tokens",
+            "output_tokens",
+            "cost_usd",
+            "first_token_ms",
+            "duration_ms",
+        ])
+        .map_err(|e| e.to_string())?;
+
+    let optional = |value: Option<i64>| value.map(|v| v.to_string()).unwrap_or_default();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let timestamp: i64 = row.get(0).map_err(|e| e.to_string())?;
+        let iso = Utc
+            .timestamp_millis_opt(timestamp)
+            .single()
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default();
+        writer
+            .write_record([
+                iso,
+                row.get::<_, String>(1).map_err(|e| e.to_string())?,
+                row.get::<_, String>(2).map_err(|e| e.to_string())?,
+                row.get::<_, i64>(3).map_err(|e| e.to_string())?.to_string(),
+                row.get::<_, i64>(4).map_err(|e| e.to_string())?.to_string(),
+                format!("{:.6}", row.get::<_, f64>(5).map_err(|e| e.to_string())?),
+                optional(row.get(6).map_err(|e| e.to_string())?),
+                optional(row.get(7).map_err(|e| e.to_string())?),
+            ])
+            .map_err(|e| format!("Failed to write usage row: {}", e))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| format!("Failed to write {}: {}", path, e))
+}
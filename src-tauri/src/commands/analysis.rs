@@ -0,0 +1,212 @@
+use chrono::{Duration, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use tauri::AppHandle;
+
+use crate::commands::version_meta::{get_meta, set_meta};
+use crate::commands::versions::{content_from_row, load_version, CONTENT_COLUMNS};
+use crate::markdown::{self, TocEntry};
+use crate::readability::{self, ReadabilityScores};
+use crate::{db, diff, settings, text};
+
+const READABILITY_KEY: &str = "readability";
+const MAX_STATS_DAYS: i64 = 3650;
+/// Store key for the user's stopword list; unset means the built-in list
+pub(crate) const STOPWORDS_KEY: &str = "stopwords";
+
+/// Scores cached as JSON under the `readability` metadata key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedReadability {
+    content_hash: String,
+    scores: ReadabilityScores,
+}
+
+/// Flesch Reading Ease, Flesch–Kincaid grade and average lengths for a version.
+/// Cached by content hash in `version_meta`.
+#[tauri::command]
+pub fn readability(app: AppHandle, id: i64) -> Result<ReadabilityScores, String> {
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, id)?;
+
+    let cached = get_meta(&conn, id, READABILITY_KEY)?
+        .and_then(|json| serde_json::from_str::<CachedReadability>(&json).ok())
+        .filter(|cached| cached.content_hash == version.content_hash);
+    if let Some(cached) = cached {
+        return Ok(cached.scores);
+    }
+
+    let scores = readability::score(&version.content).ok_or_else(|| {
+        format!(
+            "Validation: version {} is too short to score (needs at least {} words)",
+            id,
+            readability::MIN_WORDS
+        )
+    })?;
+    let cached = CachedReadability {
+        content_hash: version.content_hash,
+        scores: scores.clone(),
+    };
+    let json = serde_json::to_string(&cached).map_err(|e| e.to_string())?;
+    set_meta(&conn, id, READABILITY_KEY, &json)?;
+    Ok(scores)
+}
+
+/// Writing activity for one local calendar day
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayStat {
+    /// `YYYY-MM-DD` in local time
+    pub date: String,
+    pub versions: usize,
+    pub words_added: usize,
+    pub words_removed: usize,
+}
+
+/// Per-day version counts and words added/removed over the last `days` days, oldest
+/// first. Days without saves are included with zeroes. Word changes come from diffing
+/// each version against the previous version of its document.
+#[tauri::command]
+pub fn writing_stats_by_day(app: AppHandle, days: i64) -> Result<Vec<DayStat>, String> {
+    if !(1..=MAX_STATS_DAYS).contains(&days) {
+        return Err(format!(
+            "Validation: days must be between 1 and {}",
+            MAX_STATS_DAYS
+        ));
+    }
+    let today = Local::now().date_naive();
+    let first_day = today - Duration::days(days - 1);
+    let since = Local
+        .from_local_datetime(&first_day.and_hms_opt(0, 0, 0).unwrap_or_default())
+        .earliest()
+        .map(|t| t.timestamp_millis())
+        .unwrap_or(0);
+
+    let mut by_day: BTreeMap<NaiveDate, DayStat> = (0..days)
+        .map(|i| {
+            let date = first_day + Duration::days(i);
+            let stat = DayStat {
+                date: date.format("%Y-%m-%d").to_string(),
+                ..DayStat::default()
+            };
+            (date, stat)
+        })
+        .collect();
+
+    let conn = db::open(&app)?;
+    {
+        let mut stmt = conn
+            .prepare(
+                "SELECT date(timestamp / 1000, 'unixepoch', 'localtime') AS day, COUNT(*)
+                 FROM versions WHERE timestamp >= ?1 GROUP BY day",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt.query([since]).map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let day: String = row.get(0).map_err(|e| e.to_string())?;
+            let count: i64 = row.get(1).map_err(|e| e.to_string())?;
+            if let Ok(date) = NaiveDate::parse_from_str(&day, "%Y-%m-%d") {
+                if let Some(stat) = by_day.get_mut(&date) {
+                    stat.versions = count as usize;
+                }
+            }
+        }
+    }
+
+    // Each document's versions in the window, preceded by its last version before it
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT document_path, {}, timestamp FROM versions
+             WHERE timestamp >= ?1 OR id IN (
+               SELECT MAX(id) FROM versions WHERE timestamp < ?1 GROUP BY document_path
+             )
+             ORDER BY document_path, id",
+            CONTENT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([since]).map_err(|e| e.to_string())?;
+    let mut previous: Option<(String, String)> = None;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let document_path: String = row.get(0).map_err(|e| e.to_string())?;
+        let content = content_from_row(row, 1).map_err(|e| e.to_string())?;
+        let timestamp: i64 = row.get(4).map_err(|e| e.to_string())?;
+
+        let old = match previous.take() {
+            Some((doc, old)) if doc == document_path => old,
+            _ => String::new(),
+        };
+        let date = Local
+            .timestamp_millis_opt(timestamp)
+            .single()
+            .map(|t| t.date_naive());
+        if let Some(stat) = date.and_then(|d| by_day.get_mut(&d)) {
+            let stats = diff::diff_stats(&old, &content);
+            stat.words_added += stats.words_added;
+            stat.words_removed += stats.words_removed;
+        }
+        previous = Some((document_path, content));
+    }
+
+    Ok(by_day.into_values().collect())
+}
+
+/// Outline of a version's markdown headings with byte offsets and unique anchors.
+/// Empty when the content has no headings.
+#[tauri::command]
+pub fn version_toc(app: AppHandle, id: i64) -> Result<Vec<TocEntry>, String> {
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, id)?;
+    Ok(markdown::toc(&version.content))
+}
+
+/// The user's stopwords, or the built-in list when none are set
+#[tauri::command]
+pub fn get_stopwords(app: AppHandle) -> Vec<String> {
+    settings::get(&app, STOPWORDS_KEY).unwrap_or_else(|| {
+        text::DEFAULT_STOPWORDS
+            .iter()
+            .map(|w| w.to_string())
+            .collect()
+    })
+}
+
+/// Replace the stopwords analysis leaves out. Entries are trimmed, lowercased and
+/// deduplicated; an empty list restores the built-in one.
+#[tauri::command]
+pub fn set_stopwords(app: AppHandle, words: Vec<String>) -> Result<(), String> {
+    let words: BTreeSet<String> = words
+        .iter()
+        .map(|w| w.trim().to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.is_empty() {
+        return settings::remove(&app, STOPWORDS_KEY);
+    }
+    settings::set(&app, STOPWORDS_KEY, words)
+}
+
+/// Most common words in a version, optionally without stopwords
+#[tauri::command]
+pub fn word_frequency(
+    app: AppHandle,
+    id: i64,
+    top_n: usize,
+    stopwords: bool,
+) -> Result<Vec<(String, usize)>, String> {
+    let skip: HashSet<String> = if stopwords {
+        get_stopwords(app.clone()).into_iter().collect()
+    } else {
+        HashSet::new()
+    };
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, id)?;
+    Ok(text::word_frequency(&version.content, top_n, &skip))
+}
+
+/// Whether a version's content is plausibly text, for features that assume it
+#[tauri::command]
+pub fn is_text_version(app: AppHandle, id: i64) -> Result<bool, String> {
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, id)?;
+    Ok(text::looks_like_text(version.content.as_bytes()))
+}
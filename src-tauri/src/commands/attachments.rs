@@ -0,0 +1,199 @@
+use serde::Serialize;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::commands::versions::load_version;
+use crate::commands::workspace::allowed_path;
+use crate::db;
+
+/// MIME types by lowercase extension; anything else is `application/octet-stream`
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("pdf", "application/pdf"),
+    ("json", "application/json"),
+    ("csv", "text/csv"),
+    ("txt", "text/plain"),
+    ("md", "text/markdown"),
+    ("html", "text/html"),
+    ("xml", "application/xml"),
+    ("zip", "application/zip"),
+];
+
+/// A file referenced by a version
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: i64,
+    pub version_id: i64,
+    pub name: String,
+    pub path: String,
+    pub mime: String,
+    pub created_at: i64,
+}
+
+fn mime_for(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    MIME_TYPES
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mime)| *mime)
+        .unwrap_or("application/octet-stream")
+}
+
+/// Attach a reference to an existing file inside the working directory. The file
+/// itself isn't copied; its canonical path is stored.
+#[tauri::command]
+pub fn add_attachment(app: AppHandle, version_id: i64, path: String) -> Result<Attachment, String> {
+    let file = std::fs::canonicalize(allowed_path(&app, &path)?)
+        .ok()
+        .filter(|file| file.is_file())
+        .ok_or_else(|| format!("NotFound: file {} does not exist", path))?;
+    let name = file
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.clone());
+    let mime = mime_for(&file);
+    let path = file.to_string_lossy().into_owned();
+
+    let conn = db::open(&app)?;
+    load_version(&conn, version_id)?;
+    let created_at = db::now_ms();
+    conn.execute(
+        "INSERT INTO version_attachments (version_id, name, path, mime, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![version_id, name, path, mime, created_at],
+    )
+    .map_err(|e| format!("Failed to add attachment: {}", e))?;
+
+    Ok(Attachment {
+        id: conn.last_insert_rowid(),
+        version_id,
+        name,
+        path,
+        mime: mime.to_string(),
+        created_at,
+    })
+}
+
+/// Attachments of a version, oldest first
+#[tauri::command]
+pub fn list_attachments(app: AppHandle, version_id: i64) -> Result<Vec<Attachment>, String> {
+    let conn = db::open(&app)?;
+    load_version(&conn, version_id)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, version_id, name, path, mime, created_at FROM version_attachments
+             WHERE version_id = ?1 ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let attachments = stmt
+        .query_map([version_id], |row| {
+            Ok(Attachment {
+                id: row.get(0)?,
+                version_id: row.get(1)?,
+                name: row.get(2)?,
+                path: row.get(3)?,
+                mime: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(attachments)
+}
+
+/// Remove an attachment reference. The file on disk is left alone.
+#[tauri::command]
+pub fn remove_attachment(app: AppHandle, id: i64) -> Result<(), String> {
+    let conn = db::open(&app)?;
+    let removed = conn
+        .execute("DELETE FROM version_attachments WHERE id = ?1", [id])
+        .map_err(|e| format!("Failed to remove attachment: {}", e))?;
+    if removed == 0 {
+        return Err(format!("NotFound: attachment {} does not exist", id));
+    }
+    Ok(())
+}
+
+/// Whether an attachment's file can still be used
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentStatus {
+    pub id: i64,
+    pub version_id: i64,
+    pub path: String,
+    /// `ok`, `missing`, `outside_working_directory` or `unreadable`
+    pub status: &'static str,
+    /// Why the file is outside the working directory or unreadable
+    pub detail: Option<String>,
+}
+
+fn check_attachment(app: &AppHandle, path: &str) -> (&'static str, Option<String>) {
+    if !Path::new(path).exists() {
+        return ("missing", None);
+    }
+    // Files outside the working directory can't be read by export either
+    let resolved = match allowed_path(app, path) {
+        Ok(resolved) => resolved,
+        Err(e) if e.starts_with("Forbidden:") => return ("outside_working_directory", Some(e)),
+        Err(e) => return ("unreadable", Some(e)),
+    };
+    match std::fs::File::open(resolved) {
+        Ok(_) => ("ok", None),
+        Err(e) => ("unreadable", Some(e.to_string())),
+    }
+}
+
+/// Check that attached files still exist and can be read, for one version or,
+/// when `version_id` is None, every attachment
+#[tauri::command]
+pub fn validate_attachments(
+    app: AppHandle,
+    version_id: Option<i64>,
+) -> Result<Vec<AttachmentStatus>, String> {
+    let conn = db::open(&app)?;
+    if let Some(id) = version_id {
+        load_version(&conn, id)?;
+    }
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, version_id, path FROM version_attachments
+             WHERE ?1 IS NULL OR version_id = ?1 ORDER BY version_id, id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([version_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, version_id, path)| {
+            let (status, detail) = check_attachment(&app, &path);
+            AttachmentStatus {
+                id,
+                version_id,
+                path,
+                status,
+                detail,
+            }
+        })
+        .collect())
+}
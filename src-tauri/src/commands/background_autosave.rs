@@ -0,0 +1,142 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::ai::error_kind;
+use crate::commands::versions::autosave;
+
+/// Error kinds that saving the same content again can't fix; that content is
+/// dropped until the frontend pushes something new
+const TERMINAL_ERROR_KINDS: &[&str] =
+    &["Validation", "SecretsDetected", "TooLarge", "QuotaExceeded"];
+
+/// Working content pushed by the frontend, saved by a backend timer so autosave
+/// keeps running while the window is hidden or busy
+#[derive(Default)]
+pub struct BackgroundAutosave {
+    /// Latest unsaved content per document path
+    working: Mutex<HashMap<String, String>>,
+    /// Stop flag and interval of the running timer, if any
+    running: Mutex<Option<(Arc<AtomicBool>, u64)>>,
+}
+
+impl BackgroundAutosave {
+    /// Tick interval of the running timer, or `None` when stopped
+    pub fn interval_secs(&self) -> Option<u64> {
+        self.running.lock().unwrap().as_ref().map(|(_, secs)| *secs)
+    }
+
+    /// Save every document with pending content. Each failure is reported as a
+    /// `background-autosave-failed` event. Content that failed for a transient
+    /// reason stays pending unless newer content arrived meanwhile.
+    fn save_pending(&self, app: &AppHandle) {
+        let pending = std::mem::take(&mut *self.working.lock().unwrap());
+        for (document_path, content) in pending {
+            match autosave(app.clone(), document_path.clone(), content.clone(), None) {
+                Ok(Some(version)) => {
+                    let _ = app.emit(
+                        "background-autosave",
+                        BackgroundSaved {
+                            document_path: &document_path,
+                            version_id: version.id,
+                        },
+                    );
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Background autosave failed for {}: {}", document_path, e);
+                    let kind = error_kind(&e);
+                    let _ = app.emit(
+                        "background-autosave-failed",
+                        BackgroundSaveFailed {
+                            document_path: &document_path,
+                            kind,
+                            message: &e,
+                        },
+                    );
+                    if TERMINAL_ERROR_KINDS.contains(&kind) {
+                        continue;
+                    }
+                    self.working
+                        .lock()
+                        .unwrap()
+                        .entry(document_path)
+                        .or_insert(content);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackgroundSaved<'a> {
+    document_path: &'a str,
+    version_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackgroundSaveFailed<'a> {
+    document_path: &'a str,
+    /// Error kind prefix, e.g. `SecretsDetected`
+    kind: &'a str,
+    message: &'a str,
+}
+
+/// Push the editor's current content for the background timer to save on its next tick
+#[tauri::command]
+pub fn update_working_content(
+    state: State<BackgroundAutosave>,
+    document_path: String,
+    text: String,
+) {
+    state.working.lock().unwrap().insert(document_path, text);
+}
+
+/// Start (or restart) the backend autosave timer. Each tick saves pushed content
+/// that differs from the latest version and emits `background-autosave`, or
+/// `background-autosave-failed` when a save fails.
+#[tauri::command]
+pub fn start_background_autosave(
+    app: AppHandle,
+    state: State<BackgroundAutosave>,
+    interval_secs: u64,
+) -> Result<(), String> {
+    if interval_secs == 0 {
+        return Err("Validation: interval_secs must be greater than 0".to_string());
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    if let Some((previous, _)) = state
+        .running
+        .lock()
+        .unwrap()
+        .replace((stop.clone(), interval_secs))
+    {
+        previous.store(true, Ordering::SeqCst);
+    }
+
+    std::thread::spawn(move || loop {
+        std::thread::sleep(Duration::from_secs(interval_secs));
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        app.state::<BackgroundAutosave>().save_pending(&app);
+    });
+    Ok(())
+}
+
+/// Stop the backend autosave timer. Returns false if it wasn't running.
+#[tauri::command]
+pub fn stop_background_autosave(state: State<BackgroundAutosave>) -> bool {
+    match state.running.lock().unwrap().take() {
+        Some((stop, _)) => {
+            stop.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
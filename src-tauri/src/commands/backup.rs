@@ -0,0 +1,161 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::{db, settings};
+
+/// Store key for the persisted backup schedule
+pub(crate) const AUTO_BACKUP_KEY: &str = "auto_backup";
+const BACKUP_PREFIX: &str = "serq-backup-";
+/// How often a sleeping backup timer checks whether it was stopped
+const STOP_POLL: Duration = Duration::from_secs(60);
+/// Longest backup interval accepted, one year
+const MAX_INTERVAL_HOURS: u64 = 24 * 365;
+
+/// Persisted backup schedule, resumed on startup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoBackupConfig {
+    pub dir: String,
+    pub interval_hours: u64,
+    pub keep: usize,
+}
+
+/// Stop flag of the running backup timer, if any
+#[derive(Default)]
+pub struct AutoBackup {
+    running: Mutex<Option<Arc<AtomicBool>>>,
+}
+
+impl AutoBackup {
+    /// Start a timer for `config`, stopping any previous one
+    fn start(&self, app: AppHandle, config: AutoBackupConfig) {
+        let stop = Arc::new(AtomicBool::new(false));
+        if let Some(previous) = self.running.lock().unwrap().replace(stop.clone()) {
+            previous.store(true, Ordering::SeqCst);
+        }
+
+        // Clamped too, in case a stored schedule predates the limit
+        let interval = Duration::from_secs(config.interval_hours.min(MAX_INTERVAL_HOURS) * 60 * 60);
+        std::thread::spawn(move || loop {
+            let started = Instant::now();
+            while started.elapsed() < interval {
+                std::thread::sleep(STOP_POLL.min(interval.saturating_sub(started.elapsed())));
+                if stop.load(Ordering::SeqCst) {
+                    return;
+                }
+            }
+            match create_backup(&app, &config) {
+                Ok(path) => {
+                    let _ = app.emit(
+                        "backup-created",
+                        BackupCreated {
+                            path: path.to_string_lossy().into_owned(),
+                        },
+                    );
+                }
+                Err(e) => eprintln!("Automatic backup failed: {}", e),
+            }
+        });
+    }
+
+    fn stop(&self) -> bool {
+        match self.running.lock().unwrap().take() {
+            Some(stop) => {
+                stop.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupCreated {
+    path: String,
+}
+
+/// Snapshot the database into `config.dir` with a timestamped name, then delete all
+/// but the newest `config.keep` backups. `VACUUM INTO` includes anything still in the
+/// WAL, so the backup is a single self-contained file without sidecars.
+fn create_backup(app: &AppHandle, config: &AutoBackupConfig) -> Result<PathBuf, String> {
+    let dir = Path::new(&config.dir);
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let path = dir.join(format!(
+        "{}{}.db",
+        BACKUP_PREFIX,
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    db::open(app)?
+        .execute("VACUUM INTO ?1", [path.to_string_lossy()])
+        .map_err(|e| format!("Failed to back up database: {}", e))?;
+
+    // Timestamped names sort chronologically
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(BACKUP_PREFIX) && n.ends_with(".db"))
+        })
+        .collect();
+    backups.sort();
+    let excess = backups.len().saturating_sub(config.keep.max(1));
+    for old in &backups[..excess] {
+        if let Err(e) = std::fs::remove_file(old) {
+            eprintln!("Failed to remove old backup {}: {}", old.display(), e);
+        }
+    }
+    Ok(path)
+}
+
+/// Resume the persisted backup schedule. Called from `setup`.
+pub fn resume_auto_backup(app: &AppHandle) {
+    if let Some(config) = settings::get::<AutoBackupConfig>(app, AUTO_BACKUP_KEY) {
+        app.state::<AutoBackup>().start(app.clone(), config);
+    }
+}
+
+/// Back up the database into `dir` every `interval_hours`, keeping the newest `keep`
+/// copies. The schedule is saved and resumes on the next launch.
+#[tauri::command]
+pub fn enable_auto_backup(
+    app: AppHandle,
+    state: State<AutoBackup>,
+    dir: String,
+    interval_hours: u64,
+    keep: usize,
+) -> Result<(), String> {
+    if interval_hours == 0 || keep == 0 {
+        return Err("Validation: interval_hours and keep must be greater than 0".to_string());
+    }
+    if interval_hours > MAX_INTERVAL_HOURS {
+        return Err(format!(
+            "Validation: interval_hours must be at most {}",
+            MAX_INTERVAL_HOURS
+        ));
+    }
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create backup directory {}: {}", dir, e))?;
+    let config = AutoBackupConfig {
+        dir,
+        interval_hours,
+        keep,
+    };
+    settings::set(&app, AUTO_BACKUP_KEY, &config)?;
+    state.start(app, config);
+    Ok(())
+}
+
+/// Stop automatic backups and forget the schedule. Returns false if none was running.
+#[tauri::command]
+pub fn disable_auto_backup(app: AppHandle, state: State<AutoBackup>) -> Result<bool, String> {
+    settings::remove(&app, AUTO_BACKUP_KEY)?;
+    Ok(state.stop())
+}
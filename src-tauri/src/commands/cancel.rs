@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+/// Cancellation flags for long-running background work, keyed by a frontend-chosen request id
+#[derive(Default)]
+pub struct CancelRegistry {
+    tokens: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl CancelRegistry {
+    /// Register a request and get the flag the worker should poll
+    pub fn register(&self, request_id: &str) -> Arc<AtomicBool> {
+        let token = Arc::new(AtomicBool::new(false));
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(request_id.to_string(), token.clone());
+        token
+    }
+
+    /// Flag a request as cancelled. Returns false if no such request is running.
+    pub fn cancel(&self, request_id: &str) -> bool {
+        match self.tokens.lock().unwrap().get(request_id) {
+            Some(token) => {
+                token.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop a request's flag once its worker has finished
+    pub fn finish(&self, request_id: &str) {
+        self.tokens.lock().unwrap().remove(request_id);
+    }
+
+    /// Ids of requests still running, sorted
+    #[cfg(debug_assertions)]
+    pub fn active_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.tokens.lock().unwrap().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Whether the registry lock is currently held (or poisoned)
+    #[cfg(debug_assertions)]
+    pub fn is_locked(&self) -> bool {
+        self.tokens.try_lock().is_err()
+    }
+}
+
+/// Cancel a running background request by id
+#[tauri::command]
+pub fn cancel_request(registry: State<CancelRegistry>, request_id: String) -> bool {
+    registry.cancel(&request_id)
+}
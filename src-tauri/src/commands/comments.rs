@@ -0,0 +1,1204 @@
+use rusqlite::{OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use tauri::{AppHandle, Emitter};
+
+use crate::commands::versions::load_version;
+use crate::commands::workspace::allowed_path;
+use crate::review_markdown::{self, ReviewEntry, ReviewWriter};
+use crate::{db, settings, text};
+
+/// Store key and default for the longest comment body, in characters
+pub(crate) const MAX_COMMENT_LENGTH_KEY: &str = "max_comment_length";
+pub(crate) const DEFAULT_MAX_COMMENT_LENGTH: usize = 10_000;
+/// Store key for whether bulk operations emit one batched event (the default)
+/// or one event per comment
+pub(crate) const EVENT_BATCHING_KEY: &str = "event_batching";
+/// Comments written between `comments-export-progress` events
+const EXPORT_PROGRESS_INTERVAL: usize = 500;
+
+/// Allowed `comment_type` values; the first is the default
+pub const COMMENT_TYPES: &[&str] = &["note", "typo", "suggestion", "question"];
+
+/// Columns selected for a full `Comment`, in the order `comment_from_row` expects
+pub(crate) const COMMENT_COLUMNS: &str = "id, document_path, version_id, parent_id, text, \
+     created_at, resolved_at, position_from, position_to, comment_type, deleted_at";
+
+/// A stored comment
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Comment {
+    pub id: String,
+    pub document_path: String,
+    pub version_id: Option<i64>,
+    pub parent_id: Option<String>,
+    pub text: String,
+    pub created_at: i64,
+    pub resolved_at: Option<i64>,
+    pub position_from: i64,
+    pub position_to: i64,
+    pub comment_type: String,
+    /// Set while the comment is in the trash
+    pub deleted_at: Option<i64>,
+}
+
+pub(crate) fn comment_from_row(row: &Row) -> rusqlite::Result<Comment> {
+    Ok(Comment {
+        id: row.get(0)?,
+        document_path: row.get(1)?,
+        version_id: row.get(2)?,
+        parent_id: row.get(3)?,
+        text: row.get(4)?,
+        created_at: row.get(5)?,
+        resolved_at: row.get(6)?,
+        position_from: row.get(7)?,
+        position_to: row.get(8)?,
+        comment_type: row.get(9)?,
+        deleted_at: row.get(10)?,
+    })
+}
+
+/// Load a comment by id, failing with `NotFound` if it doesn't exist
+pub fn load_comment(conn: &rusqlite::Connection, id: &str) -> Result<Comment, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM comments WHERE id = ?1", COMMENT_COLUMNS),
+        [id],
+        comment_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load comment: {}", e))?
+    .ok_or_else(|| format!("NotFound: comment {} does not exist", id))
+}
+
+/// Reject comment types outside `COMMENT_TYPES`
+fn validate_comment_type(comment_type: &str) -> Result<(), String> {
+    if COMMENT_TYPES.contains(&comment_type) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Validation: unknown comment type '{}' (expected one of: {})",
+            comment_type,
+            COMMENT_TYPES.join(", ")
+        ))
+    }
+}
+
+fn max_comment_length(app: &AppHandle) -> usize {
+    settings::get(app, MAX_COMMENT_LENGTH_KEY).unwrap_or(DEFAULT_MAX_COMMENT_LENGTH)
+}
+
+/// Set the longest comment body accepted, in characters
+#[tauri::command]
+pub fn set_max_comment_length(app: AppHandle, n: usize) -> Result<(), String> {
+    if n == 0 {
+        return Err("Validation: max_comment_length must be greater than 0".to_string());
+    }
+    settings::set(&app, MAX_COMMENT_LENGTH_KEY, n)
+}
+
+/// Strip HTML tags from a comment body, trim it and enforce the length limit.
+/// Other characters are kept as typed, and sanitizing a stored body again changes
+/// nothing, so exports and edits round-trip. Errors name the rule that failed.
+fn sanitize_comment_body(body: &str, max_length: usize) -> Result<String, String> {
+    let stripped = text::strip_tags(body);
+    let body = stripped.trim();
+    if body.is_empty() {
+        return Err("Validation: non_empty: comment body is empty".to_string());
+    }
+    let length = body.chars().count();
+    if length > max_length {
+        return Err(format!(
+            "Validation: max_length: comment body is {} characters, limit is {}",
+            length, max_length
+        ));
+    }
+    Ok(body.to_string())
+}
+
+/// Choose between one `comments-batch-changed` event per bulk operation (enabled)
+/// and one `comment-created`/`comment-updated` event per affected comment.
+/// `resolve_all_comments` always emits `comments-resolved`; when enabled it also
+/// emits `comments-batch-changed`, and when disabled it sends nothing per comment.
+#[tauri::command]
+pub fn set_event_batching(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::set(&app, EVENT_BATCHING_KEY, enabled)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CommentsBatchChanged<'a> {
+    /// The per-comment event this batch stands in for
+    event: &'a str,
+    ids: &'a [String],
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CommentChanged<'a> {
+    id: &'a str,
+}
+
+/// Announce a bulk change, batched or per comment depending on `set_event_batching`
+fn emit_comment_changes(app: &AppHandle, event: &str, ids: &[String]) -> Result<(), String> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    if settings::get(app, EVENT_BATCHING_KEY).unwrap_or(true) {
+        return app
+            .emit(
+                "comments-batch-changed",
+                CommentsBatchChanged { event, ids },
+            )
+            .map_err(|e| e.to_string());
+    }
+    for id in ids {
+        app.emit(event, CommentChanged { id })
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CommentUpdated<'a> {
+    id: &'a str,
+    comment_type: &'a str,
+}
+
+/// Comments anchored to a version that no longer exists, or replying to a missing comment
+const ORPHANED_COMMENTS_WHERE: &str = "(version_id IS NOT NULL
+        AND NOT EXISTS (SELECT 1 FROM versions v WHERE v.id = comments.version_id))
+     OR (parent_id IS NOT NULL
+        AND NOT EXISTS (SELECT 1 FROM comments p WHERE p.id = comments.parent_id))";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CommentsResolved {
+    version_id: i64,
+    count: usize,
+}
+
+/// Resolve every open comment on a version in one UPDATE.
+/// Emits a single `comments-resolved` event, plus `comments-batch-changed` with the
+/// resolved ids when event batching is on. Never emits one event per comment.
+#[tauri::command]
+pub fn resolve_all_comments(app: AppHandle, version_id: i64) -> Result<usize, String> {
+    let mut conn = db::open(&app)?;
+    load_version(&conn, version_id)?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut stmt = tx
+        .prepare(
+            "SELECT id FROM comments
+             WHERE version_id = ?1 AND resolved_at IS NULL AND deleted_at IS NULL",
+        )
+        .map_err(|e| e.to_string())?;
+    let ids: Vec<String> = stmt
+        .query_map([version_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    drop(stmt);
+    tx.execute(
+        "UPDATE comments SET resolved_at = ?1
+         WHERE version_id = ?2 AND resolved_at IS NULL AND deleted_at IS NULL",
+        rusqlite::params![db::now_ms(), version_id],
+    )
+    .map_err(|e| format!("Failed to resolve comments: {}", e))?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    app.emit(
+        "comments-resolved",
+        CommentsResolved {
+            version_id,
+            count: ids.len(),
+        },
+    )
+    .map_err(|e| e.to_string())?;
+    if !ids.is_empty() && settings::get(&app, EVENT_BATCHING_KEY).unwrap_or(true) {
+        app.emit(
+            "comments-batch-changed",
+            CommentsBatchChanged {
+                event: "comment-updated",
+                ids: &ids,
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(ids.len())
+}
+
+/// A comment anchored to a character range of a version
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchoredCommentInput {
+    pub text: String,
+    pub position_from: i64,
+    pub position_to: i64,
+}
+
+/// Create many anchored comments on a version in one transaction, e.g. when importing
+/// review notes. Returns the new ids in input order; nothing is inserted if any fails.
+/// Emits `comment-created` for the new comments, batched by default.
+#[tauri::command]
+pub fn create_comments_bulk(
+    app: AppHandle,
+    version_id: i64,
+    comments: Vec<AnchoredCommentInput>,
+) -> Result<Vec<String>, String> {
+    let max_length = max_comment_length(&app);
+    let mut conn = db::open(&app)?;
+    let version = load_version(&conn, version_id)?;
+    let length = version.content.chars().count() as i64;
+
+    let mut bodies = Vec::with_capacity(comments.len());
+    for (i, comment) in comments.iter().enumerate() {
+        bodies.push(
+            sanitize_comment_body(&comment.text, max_length)
+                .map_err(|e| format!("{} (comment {})", e, i))?,
+        );
+        if comment.position_from < 0
+            || comment.position_from > comment.position_to
+            || comment.position_to > length
+        {
+            return Err(format!(
+                "Validation: comment {} range {}..{} is outside the version (length {})",
+                i, comment.position_from, comment.position_to, length
+            ));
+        }
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let created_at = db::now_ms();
+    let mut ids = Vec::with_capacity(comments.len());
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT INTO comments
+                 (id, document_path, text, created_at, position_from, position_to, version_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )
+            .map_err(|e| e.to_string())?;
+        for (comment, body) in comments.iter().zip(&bodies) {
+            let id = uuid::Uuid::new_v4().to_string();
+            stmt.execute(rusqlite::params![
+                id,
+                version.document_path,
+                body,
+                created_at,
+                comment.position_from,
+                comment.position_to,
+                version_id
+            ])
+            .map_err(|e| format!("Failed to create comment: {}", e))?;
+            ids.push(id);
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    emit_comment_changes(&app, "comment-created", &ids)?;
+    Ok(ids)
+}
+
+/// A comment in a flattened thread listing
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentRow {
+    #[serde(flatten)]
+    pub comment: Comment,
+    /// 0 for top-level comments
+    pub depth: usize,
+    pub has_children: bool,
+}
+
+/// One page of a version's comment threads in pre-order (each comment followed by
+/// its replies, siblings oldest first), for virtualized lists
+#[tauri::command]
+pub fn list_comments_flat(
+    app: AppHandle,
+    version_id: i64,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<CommentRow>, String> {
+    if limit < 0 || offset < 0 {
+        return Err("Validation: limit and offset must not be negative".to_string());
+    }
+    let conn = db::open(&app)?;
+    load_version(&conn, version_id)?;
+
+    // Sibling order key: zero-padded creation time, then id for ties
+    let mut stmt = conn
+        .prepare(&format!(
+            "WITH RECURSIVE tree(node_id, depth, path) AS (
+                 SELECT id, 0, printf('%020d', created_at) || id
+                 FROM comments
+                 WHERE version_id = ?1 AND parent_id IS NULL AND deleted_at IS NULL
+                 UNION ALL
+                 SELECT c.id, t.depth + 1, t.path || '/' || printf('%020d', c.created_at) || c.id
+                 FROM comments c JOIN tree t ON c.parent_id = t.node_id
+                 WHERE c.deleted_at IS NULL
+             )
+             SELECT {}, tree.depth,
+                    EXISTS (SELECT 1 FROM comments k
+                            WHERE k.parent_id = comments.id AND k.deleted_at IS NULL)
+             FROM tree JOIN comments ON comments.id = tree.node_id
+             ORDER BY tree.path LIMIT ?2 OFFSET ?3",
+            COMMENT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params![version_id, limit, offset], |row| {
+            Ok(CommentRow {
+                comment: comment_from_row(row)?,
+                depth: row.get::<_, i64>(11)? as usize,
+                has_children: row.get(12)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+/// A comment and all replies below it
+const THREAD_CTE: &str = "WITH RECURSIVE thread(id) AS (
+         SELECT ?1
+         UNION ALL
+         SELECT c.id FROM comments c JOIN thread t ON c.parent_id = t.id
+     )";
+
+/// Move a comment and its replies to the trash. They stay in the database but are
+/// left out of listings and counts until restored. Returns how many were trashed.
+#[tauri::command]
+pub fn trash_comment(app: AppHandle, id: String) -> Result<usize, String> {
+    let mut conn = db::open(&app)?;
+    let comment = load_comment(&conn, &id)?;
+    if comment.deleted_at.is_some() {
+        return Err(format!("Conflict: comment {} is already in the trash", id));
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    // Replies trashed earlier keep their own time, so restoring this thread skips them
+    let trashed = tx
+        .execute(
+            &format!(
+                "{} UPDATE comments SET deleted_at = ?2
+                 WHERE id IN (SELECT id FROM thread) AND deleted_at IS NULL",
+                THREAD_CTE
+            ),
+            rusqlite::params![id, db::now_ms()],
+        )
+        .map_err(|e| format!("Failed to trash comment: {}", e))?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(trashed)
+}
+
+/// Bring a trashed comment back along with the replies trashed with it.
+/// Returns how many were restored.
+#[tauri::command]
+pub fn restore_comment(app: AppHandle, id: String) -> Result<usize, String> {
+    let mut conn = db::open(&app)?;
+    let comment = load_comment(&conn, &id)?;
+    let Some(deleted_at) = comment.deleted_at else {
+        return Err(format!("Conflict: comment {} is not in the trash", id));
+    };
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let restored = tx
+        .execute(
+            &format!(
+                "{} UPDATE comments SET deleted_at = NULL
+                 WHERE id IN (SELECT id FROM thread) AND deleted_at = ?2",
+                THREAD_CTE
+            ),
+            rusqlite::params![id, deleted_at],
+        )
+        .map_err(|e| format!("Failed to restore comment: {}", e))?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(restored)
+}
+
+/// Trashed comments on a version, most recently trashed first
+#[tauri::command]
+pub fn list_trashed_comments(app: AppHandle, version_id: i64) -> Result<Vec<Comment>, String> {
+    let conn = db::open(&app)?;
+    load_version(&conn, version_id)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM comments WHERE version_id = ?1 AND deleted_at IS NOT NULL
+             ORDER BY deleted_at DESC, created_at, id",
+            COMMENT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let comments = stmt
+        .query_map([version_id], comment_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(comments)
+}
+
+/// Comment totals for one version
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentCounts {
+    pub total: usize,
+    pub unresolved: usize,
+}
+
+/// Comment counts for many versions in one grouped query, for badges in version
+/// lists. Versions without comments are left out. Trashed comments aren't counted.
+#[tauri::command]
+pub fn comment_counts_for_versions(
+    app: AppHandle,
+    version_ids: Vec<i64>,
+) -> Result<HashMap<i64, CommentCounts>, String> {
+    let mut counts = HashMap::new();
+    if version_ids.is_empty() {
+        return Ok(counts);
+    }
+    let conn = db::open(&app)?;
+    let placeholders = vec!["?"; version_ids.len()].join(", ");
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT version_id, COUNT(*), COUNT(*) FILTER (WHERE resolved_at IS NULL)
+             FROM comments WHERE deleted_at IS NULL AND version_id IN ({})
+             GROUP BY version_id",
+            placeholders
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+        .query(rusqlite::params_from_iter(&version_ids))
+        .map_err(|e| e.to_string())?;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let version_id: i64 = row.get(0).map_err(|e| e.to_string())?;
+        let total: i64 = row.get(1).map_err(|e| e.to_string())?;
+        let unresolved: i64 = row.get(2).map_err(|e| e.to_string())?;
+        counts.insert(
+            version_id,
+            CommentCounts {
+                total: total as usize,
+                unresolved: unresolved as usize,
+            },
+        );
+    }
+    Ok(counts)
+}
+
+/// Comment body for comparisons: lowercase, whitespace runs collapsed
+fn comparable_body(body: &str) -> String {
+    body.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Groups of comments on a version whose bodies match once whitespace and case
+/// are ignored. Each group is oldest first; groups are ordered by their oldest.
+#[tauri::command]
+pub fn find_duplicate_comments(
+    app: AppHandle,
+    version_id: i64,
+) -> Result<Vec<Vec<String>>, String> {
+    let conn = db::open(&app)?;
+    load_version(&conn, version_id)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, text FROM comments
+             WHERE version_id = ?1 AND deleted_at IS NULL ORDER BY created_at, id",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([version_id]).map_err(|e| e.to_string())?;
+
+    let mut clusters: Vec<Vec<String>> = Vec::new();
+    let mut by_key: HashMap<String, usize> = HashMap::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let id: String = row.get(0).map_err(|e| e.to_string())?;
+        let body: String = row.get(1).map_err(|e| e.to_string())?;
+        let index = *by_key.entry(comparable_body(&body)).or_insert_with(|| {
+            clusters.push(Vec::new());
+            clusters.len() - 1
+        });
+        clusters[index].push(id);
+    }
+    clusters.retain(|ids| ids.len() > 1);
+    Ok(clusters)
+}
+
+/// Parent, grandparent and so on of a comment, nearest first
+fn ancestor_ids(conn: &rusqlite::Connection, id: &str) -> Result<Vec<String>, String> {
+    let mut ancestors: Vec<String> = Vec::new();
+    let mut current = id.to_string();
+    loop {
+        let parent: Option<String> = conn
+            .query_row(
+                "SELECT parent_id FROM comments WHERE id = ?1",
+                [&current],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .flatten();
+        // Stop at the root, or where a corrupt chain loops back on itself
+        match parent {
+            Some(parent) if parent != id && !ancestors.contains(&parent) => {
+                ancestors.push(parent.clone());
+                current = parent;
+            }
+            _ => return Ok(ancestors),
+        }
+    }
+}
+
+/// Collapse duplicates into `keep_id`: replies to the removed comments move to
+/// it, then the removed comments are deleted. All must be on the same version.
+/// Returns how many were removed.
+#[tauri::command]
+pub fn merge_duplicate_comments(
+    app: AppHandle,
+    keep_id: String,
+    remove_ids: Vec<String>,
+) -> Result<usize, String> {
+    let mut conn = db::open(&app)?;
+    let keep = load_comment(&conn, &keep_id)?;
+    // Moving replies onto a descendant of a removed comment would close a cycle
+    if ancestor_ids(&conn, &keep_id)?
+        .iter()
+        .any(|ancestor| remove_ids.contains(ancestor))
+    {
+        return Err("Validation: keep_id is in the thread of a comment being removed".to_string());
+    }
+    for id in &remove_ids {
+        if *id == keep_id {
+            return Err("Validation: keep_id is also in remove_ids".to_string());
+        }
+        if load_comment(&conn, id)?.version_id != keep.version_id {
+            return Err(format!(
+                "Validation: comment {} is on a different version than {}",
+                id, keep_id
+            ));
+        }
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut removed = 0;
+    for id in &remove_ids {
+        tx.execute(
+            "UPDATE comments SET parent_id = ?1 WHERE parent_id = ?2",
+            rusqlite::params![keep_id, id],
+        )
+        .map_err(|e| format!("Failed to move replies: {}", e))?;
+        removed += tx
+            .execute("DELETE FROM comments WHERE id = ?1", [id])
+            .map_err(|e| format!("Failed to delete comment: {}", e))?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(removed)
+}
+
+/// A comment found on both versions whose resolution differs
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentChange {
+    pub a: Comment,
+    pub b: Comment,
+}
+
+/// How the comments on version B differ from those on version A
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentTreeDiff {
+    /// Only on B
+    pub added: Vec<Comment>,
+    /// Only on A
+    pub removed: Vec<Comment>,
+    /// On both, resolved on one and open on the other
+    pub changed: Vec<CommentChange>,
+}
+
+/// A version's live comments, oldest first, each keyed by its comparable body
+/// prefixed with those of its ancestors, so replies match only within the
+/// same thread
+fn keyed_comments(
+    conn: &rusqlite::Connection,
+    version_id: i64,
+) -> Result<Vec<(String, Comment)>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM comments WHERE version_id = ?1 AND deleted_at IS NULL
+             ORDER BY created_at, id",
+            COMMENT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let comments = stmt
+        .query_map([version_id], comment_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let by_id: HashMap<&str, &Comment> = comments.iter().map(|c| (c.id.as_str(), c)).collect();
+    let mut keyed = Vec::with_capacity(comments.len());
+    for comment in &comments {
+        let mut path = vec![comparable_body(&comment.text)];
+        let mut seen = std::collections::HashSet::from([comment.id.as_str()]);
+        let mut parent = comment.parent_id.as_deref();
+        while let Some(p) = parent.and_then(|id| by_id.get(id)) {
+            if !seen.insert(p.id.as_str()) {
+                break;
+            }
+            path.push(comparable_body(&p.text));
+            parent = p.parent_id.as_deref();
+        }
+        path.reverse();
+        // Unit separator, which comment text won't realistically contain
+        keyed.push((path.join("\u{1f}"), comment.clone()));
+    }
+    Ok(keyed)
+}
+
+/// Compare the comment threads of two versions, e.g. across a fork. Comments
+/// match when their bodies (ignoring case and whitespace) and those of their
+/// ancestors agree; matched pairs whose resolution differs are reported as changed.
+#[tauri::command]
+pub fn diff_comment_trees(
+    app: AppHandle,
+    version_a: i64,
+    version_b: i64,
+) -> Result<CommentTreeDiff, String> {
+    let conn = db::open(&app)?;
+    load_version(&conn, version_a)?;
+    load_version(&conn, version_b)?;
+
+    let mut unmatched_a: HashMap<String, std::collections::VecDeque<Comment>> = HashMap::new();
+    let mut order_a = Vec::new();
+    for (key, comment) in keyed_comments(&conn, version_a)? {
+        order_a.push(comment.id.clone());
+        unmatched_a.entry(key).or_default().push_back(comment);
+    }
+
+    let mut diff = CommentTreeDiff {
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+    for (key, b) in keyed_comments(&conn, version_b)? {
+        match unmatched_a
+            .get_mut(&key)
+            .and_then(|queue| queue.pop_front())
+        {
+            Some(a) if a.resolved_at.is_some() != b.resolved_at.is_some() => {
+                diff.changed.push(CommentChange { a, b })
+            }
+            Some(_) => {}
+            None => diff.added.push(b),
+        }
+    }
+
+    let mut removed: HashMap<String, Comment> = unmatched_a
+        .into_values()
+        .flatten()
+        .map(|c| (c.id.clone(), c))
+        .collect();
+    diff.removed = order_a.iter().filter_map(|id| removed.remove(id)).collect();
+    Ok(diff)
+}
+
+/// Promote replies whose parent comment no longer exists to top-level comments,
+/// within one version. Returns how many were fixed.
+#[tauri::command]
+pub fn reparent_orphans(app: AppHandle, version_id: i64) -> Result<usize, String> {
+    let mut conn = db::open(&app)?;
+    load_version(&conn, version_id)?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let fixed = tx
+        .execute(
+            "UPDATE comments SET parent_id = NULL
+             WHERE version_id = ?1 AND parent_id IS NOT NULL
+               AND NOT EXISTS (SELECT 1 FROM comments p WHERE p.id = comments.parent_id)",
+            [version_id],
+        )
+        .map_err(|e| format!("Failed to reparent comments: {}", e))?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(fixed)
+}
+
+/// Ids of comments whose version or parent comment is missing
+#[tauri::command]
+pub fn find_orphaned_comments(app: AppHandle) -> Result<Vec<String>, String> {
+    let conn = db::open(&app)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id FROM comments WHERE {} ORDER BY created_at",
+            ORPHANED_COMMENTS_WHERE
+        ))
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(ids)
+}
+
+/// Delete orphaned comments in one transaction. Returns how many were removed,
+/// including replies removed along with them.
+#[tauri::command]
+pub fn purge_orphaned_comments(app: AppHandle) -> Result<usize, String> {
+    let mut conn = db::open(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let count_comments = |tx: &rusqlite::Transaction| -> Result<usize, String> {
+        tx.query_row("SELECT COUNT(*) FROM comments", [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .map(|n| n as usize)
+        .map_err(|e| e.to_string())
+    };
+    let before = count_comments(&tx)?;
+    tx.execute(
+        &format!("DELETE FROM comments WHERE {}", ORPHANED_COMMENTS_WHERE),
+        [],
+    )
+    .map_err(|e| format!("Failed to purge comments: {}", e))?;
+    let removed = before - count_comments(&tx)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(removed)
+}
+
+/// A comment body as it was before an edit replaced it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentRevision {
+    pub text: String,
+    /// When this body was replaced
+    pub edited_at: i64,
+}
+
+/// Change a comment's body, keeping the previous body in `comment_revisions`
+#[tauri::command]
+pub fn edit_comment(app: AppHandle, id: String, new_body: String) -> Result<(), String> {
+    let new_body = sanitize_comment_body(&new_body, max_comment_length(&app))?;
+    let mut conn = db::open(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let current: String = tx
+        .query_row("SELECT text FROM comments WHERE id = ?1", [&id], |row| {
+            row.get(0)
+        })
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("NotFound: comment {} does not exist", id))?;
+    if current == new_body {
+        return Err("NoChanges: comment body is unchanged".to_string());
+    }
+
+    tx.execute(
+        "INSERT INTO comment_revisions (comment_id, text, edited_at) VALUES (?1, ?2, ?3)",
+        rusqlite::params![id, current, db::now_ms()],
+    )
+    .map_err(|e| format!("Failed to record revision: {}", e))?;
+    tx.execute(
+        "UPDATE comments SET text = ?1 WHERE id = ?2",
+        rusqlite::params![new_body, id],
+    )
+    .map_err(|e| format!("Failed to edit comment: {}", e))?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Prior bodies of a comment, oldest first. Empty if it was never edited.
+#[tauri::command]
+pub fn get_comment_history(app: AppHandle, id: String) -> Result<Vec<CommentRevision>, String> {
+    let conn = db::open(&app)?;
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM comments WHERE id = ?1)",
+            [&id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !exists {
+        return Err(format!("NotFound: comment {} does not exist", id));
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT text, edited_at FROM comment_revisions WHERE comment_id = ?1
+             ORDER BY edited_at, id",
+        )
+        .map_err(|e| e.to_string())?;
+    let revisions = stmt
+        .query_map([&id], |row| {
+            Ok(CommentRevision {
+                text: row.get(0)?,
+                edited_at: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(revisions)
+}
+
+/// Create a comment on a version's character range, optionally as a reply and with
+/// a review type (default `note`)
+#[tauri::command]
+pub fn create_comment(
+    app: AppHandle,
+    version_id: i64,
+    text: String,
+    position_from: i64,
+    position_to: i64,
+    parent_id: Option<String>,
+    comment_type: Option<String>,
+) -> Result<Comment, String> {
+    let comment_type = comment_type.unwrap_or_else(|| COMMENT_TYPES[0].to_string());
+    validate_comment_type(&comment_type)?;
+    let text = sanitize_comment_body(&text, max_comment_length(&app))?;
+
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, version_id)?;
+    let length = version.content.chars().count() as i64;
+    if position_from < 0 || position_from > position_to || position_to > length {
+        return Err(format!(
+            "Validation: range {}..{} is outside the version (length {})",
+            position_from, position_to, length
+        ));
+    }
+    if let Some(parent_id) = &parent_id {
+        load_comment(&conn, parent_id)?;
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO comments
+         (id, document_path, text, created_at, position_from, position_to, version_id,
+          parent_id, comment_type)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        rusqlite::params![
+            id,
+            version.document_path,
+            text,
+            db::now_ms(),
+            position_from,
+            position_to,
+            version_id,
+            parent_id,
+            comment_type
+        ],
+    )
+    .map_err(|e| format!("Failed to create comment: {}", e))?;
+    load_comment(&conn, &id)
+}
+
+/// Change a comment's review type. Emits `comment-updated`.
+#[tauri::command]
+pub fn set_comment_type(app: AppHandle, id: String, comment_type: String) -> Result<(), String> {
+    validate_comment_type(&comment_type)?;
+    let conn = db::open(&app)?;
+    let updated = conn
+        .execute(
+            "UPDATE comments SET comment_type = ?1 WHERE id = ?2",
+            rusqlite::params![comment_type, id],
+        )
+        .map_err(|e| format!("Failed to update comment: {}", e))?;
+    if updated == 0 {
+        return Err(format!("NotFound: comment {} does not exist", id));
+    }
+
+    app.emit(
+        "comment-updated",
+        CommentUpdated {
+            id: &id,
+            comment_type: &comment_type,
+        },
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// All comments of one review type, oldest first
+#[tauri::command]
+pub fn list_comments_by_type(app: AppHandle, comment_type: String) -> Result<Vec<Comment>, String> {
+    validate_comment_type(&comment_type)?;
+    let conn = db::open(&app)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM comments WHERE comment_type = ?1 AND deleted_at IS NULL
+             ORDER BY created_at, id",
+            COMMENT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let comments = stmt
+        .query_map([&comment_type], comment_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(comments)
+}
+
+/// Comments of a version as review entries, threads in creation order with
+/// replies depth-first under their parents
+fn review_entries(
+    conn: &rusqlite::Connection,
+    version_id: i64,
+) -> Result<Vec<ReviewEntry>, String> {
+    let mut entries = Vec::new();
+    walk_review(conn, version_id, |entry, _| {
+        entries.push(entry);
+        Ok(())
+    })?;
+    Ok(entries)
+}
+
+/// Visit a version's comments in review order with the total count, walking the
+/// threads with an explicit stack so deep reply chains can't overflow. Returns
+/// how many were visited.
+fn walk_review(
+    conn: &rusqlite::Connection,
+    version_id: i64,
+    mut visit: impl FnMut(ReviewEntry, usize) -> Result<(), String>,
+) -> Result<usize, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM comments WHERE version_id = ?1 AND deleted_at IS NULL
+             ORDER BY created_at, id",
+            COMMENT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let comments = stmt
+        .query_map([version_id], comment_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let ids: std::collections::HashSet<&str> = comments.iter().map(|c| c.id.as_str()).collect();
+    let mut children: HashMap<&str, Vec<&Comment>> = Default::default();
+    let mut roots = Vec::new();
+    for comment in &comments {
+        match comment.parent_id.as_deref().filter(|p| ids.contains(p)) {
+            Some(parent) => children.entry(parent).or_default().push(comment),
+            None => roots.push(comment),
+        }
+    }
+
+    let mut stack: Vec<(&Comment, usize)> = roots.into_iter().rev().map(|c| (c, 0)).collect();
+    while let Some((comment, depth)) = stack.pop() {
+        let entry = ReviewEntry {
+            depth,
+            comment_type: comment.comment_type.clone(),
+            position_from: comment.position_from,
+            position_to: comment.position_to,
+            text: comment.text.clone(),
+        };
+        visit(entry, comments.len())?;
+        if let Some(replies) = children.get(comment.id.as_str()) {
+            stack.extend(replies.iter().rev().map(|c| (*c, depth + 1)));
+        }
+    }
+    Ok(comments.len())
+}
+
+/// Write a version's comment threads as a markdown review file. Returns how many
+/// comments were written.
+#[tauri::command]
+pub fn export_comments_markdown(
+    app: AppHandle,
+    version_id: i64,
+    path: String,
+) -> Result<usize, String> {
+    let target = allowed_path(&app, &path)?;
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, version_id)?;
+    let entries = review_entries(&conn, version_id)?;
+    let title = format!(
+        "Review of {} (version {})",
+        version.document_path, version.id
+    );
+    std::fs::write(&target, review_markdown::render(&title, &entries))
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(entries.len())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CommentsExportProgress<'a> {
+    path: &'a str,
+    written: usize,
+    total: usize,
+}
+
+/// Same output as `export_comments_markdown`, written to the file entry by entry
+/// on a blocking thread instead of being built in memory. Emits
+/// `comments-export-progress` every 500 comments and once at the end. Returns how
+/// many comments were written.
+#[tauri::command]
+pub async fn export_comments_markdown_stream(
+    app: AppHandle,
+    version_id: i64,
+    path: String,
+) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || stream_review(&app, version_id, &path))
+        .await
+        .map_err(|e| format!("Export failed: {}", e))?
+}
+
+fn stream_review(app: &AppHandle, version_id: i64, path: &str) -> Result<usize, String> {
+    let target = allowed_path(app, path)?;
+    let conn = db::open(app)?;
+    let version = load_version(&conn, version_id)?;
+    let title = format!(
+        "Review of {} (version {})",
+        version.document_path, version.id
+    );
+    let write_error = |e: std::io::Error| format!("Failed to write {}: {}", path, e);
+    let file = std::fs::File::create(&target).map_err(write_error)?;
+    let mut writer =
+        ReviewWriter::new(std::io::BufWriter::new(file), &title).map_err(write_error)?;
+
+    let mut written = 0;
+    let total = walk_review(&conn, version_id, |entry, total| {
+        writer.write(&entry).map_err(write_error)?;
+        written += 1;
+        if written % EXPORT_PROGRESS_INTERVAL == 0 {
+            let _ = app.emit(
+                "comments-export-progress",
+                CommentsExportProgress {
+                    path,
+                    written,
+                    total,
+                },
+            );
+        }
+        Ok(())
+    })?;
+    writer.into_inner().flush().map_err(write_error)?;
+    let _ = app.emit(
+        "comments-export-progress",
+        CommentsExportProgress {
+            path,
+            written: total,
+            total,
+        },
+    );
+    Ok(total)
+}
+
+/// Comments created from a review file, and entries skipped as malformed
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentImportSummary {
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+/// Import a markdown review file (as written by `export_comments_markdown`) as
+/// threaded comments on a version. Malformed entries, or ranges outside the
+/// version, are skipped and counted instead of failing the import.
+#[tauri::command]
+pub fn import_comments_markdown(
+    app: AppHandle,
+    version_id: i64,
+    path: String,
+) -> Result<CommentImportSummary, String> {
+    let source = allowed_path(&app, &path)?;
+    let max_length = max_comment_length(&app);
+    let markdown =
+        std::fs::read_to_string(&source).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let review = review_markdown::parse(&markdown, COMMENT_TYPES);
+
+    let mut conn = db::open(&app)?;
+    let version = load_version(&conn, version_id)?;
+    let length = version.content.chars().count() as i64;
+    let mut imported = 0;
+    let mut skipped = review.skipped;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let created_at = db::now_ms();
+    // Ids of the current thread's ancestors, indexed by depth
+    let mut ancestors: Vec<String> = Vec::new();
+    for entry in &review.entries {
+        let in_range = 0 <= entry.position_from
+            && entry.position_from <= entry.position_to
+            && entry.position_to <= length;
+        let body = sanitize_comment_body(&entry.text, max_length)
+            .ok()
+            .filter(|_| in_range && entry.depth <= ancestors.len());
+        let Some(body) = body else {
+            // Replies of a skipped comment go with it
+            ancestors.truncate(entry.depth);
+            skipped += 1;
+            continue;
+        };
+        ancestors.truncate(entry.depth);
+        let id = uuid::Uuid::new_v4().to_string();
+        tx.execute(
+            "INSERT INTO comments
+             (id, document_path, text, created_at, position_from, position_to, version_id,
+              parent_id, comment_type)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                id,
+                version.document_path,
+                body,
+                created_at,
+                entry.position_from,
+                entry.position_to,
+                version_id,
+                ancestors.last(),
+                entry.comment_type
+            ],
+        )
+        .map_err(|e| format!("Failed to create comment: {}", e))?;
+        ancestors.push(id);
+        imported += 1;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(CommentImportSummary { imported, skipped })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_keeps_markup_characters_as_typed() {
+        let body = sanitize_comment_body("  a < b && c > d  ", 100).unwrap();
+        assert_eq!(body, "a < b && c > d");
+        // Sanitizing again, as an import or edit does, changes nothing
+        assert_eq!(sanitize_comment_body(&body, 100).unwrap(), body);
+    }
+
+    #[test]
+    fn sanitize_never_stores_markup() {
+        let body = sanitize_comment_body("<b>x</b>", 100).unwrap();
+        assert_eq!(body, "x");
+        assert_eq!(sanitize_comment_body(&body, 100).unwrap(), body);
+        let body = sanitize_comment_body("<scr<b>ipt>alert(1)</script> hi", 100).unwrap();
+        assert!(!body.contains('<'), "{}", body);
+        assert!(sanitize_comment_body("<img src=x onerror=alert(1)>", 100).is_err());
+    }
+
+    #[test]
+    fn ancestors_cover_the_whole_chain() {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        db::apply_migrations(&mut conn).unwrap();
+        for (id, parent) in [("r", None), ("p", Some("r")), ("k", Some("p"))] {
+            conn.execute(
+                "INSERT INTO comments (id, document_path, text, created_at, position_from,
+                                       position_to, parent_id)
+                 VALUES (?1, 'doc.md', 'body', 0, 0, 0, ?2)",
+                rusqlite::params![id, parent],
+            )
+            .unwrap();
+        }
+        assert_eq!(ancestor_ids(&conn, "k").unwrap(), vec!["p", "r"]);
+        assert!(ancestor_ids(&conn, "r").unwrap().is_empty());
+    }
+
+    #[test]
+    fn sanitize_limits_raw_length() {
+        assert!(sanitize_comment_body(&"<".repeat(10), 10).is_ok());
+        assert!(sanitize_comment_body(&"<".repeat(11), 10).is_err());
+        assert!(sanitize_comment_body("   ", 10).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, State};
+
+use crate::commands::background_autosave::BackgroundAutosave;
+use crate::commands::debug_bridge::{self, DebugBridgeState, RotationConfig};
+use crate::commands::{
+    ai, ai_usage, analysis, backup, comments, embeddings, export, history, versions, workspace,
+};
+use crate::rate_limit::{ConcurrencyConfig, ConcurrencyLimiter, RateLimitConfig, RateLimiter};
+use crate::{anthropic, secrets, settings, text};
+
+/// One setting as it is currently in effect
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: Value,
+    /// `default`, `store` (user override), `runtime` (set this session, not
+    /// persisted), `env` or `keychain`
+    pub source: &'static str,
+}
+
+/// Every configurable setting with its resolved value
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfig {
+    pub entries: Vec<ConfigEntry>,
+}
+
+fn entry(key: &str, value: Value, source: &'static str) -> ConfigEntry {
+    ConfigEntry {
+        key: key.to_string(),
+        value,
+        source,
+    }
+}
+
+/// Scheme and host of a URL, with everything after masked. Webhook URLs carry
+/// their token in the path or query, in forms no secret pattern recognizes.
+fn mask_url(url: &str) -> String {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return secrets::REDACTED.to_string();
+    };
+    let origin = parsed.origin().ascii_serialization();
+    if parsed.path() == "/" && parsed.query().is_none() {
+        origin
+    } else {
+        format!("{}/{}", origin, secrets::REDACTED)
+    }
+}
+
+/// A store setting, or its default when unset
+fn stored(app: &AppHandle, key: &str, default: Value) -> ConfigEntry {
+    match settings::get::<Value>(app, key) {
+        Some(value) => entry(key, value, "store"),
+        None => entry(key, default, "default"),
+    }
+}
+
+/// An in-memory setting, reported as `default` while it still equals the default
+fn runtime(key: &str, value: Value, default: Value) -> ConfigEntry {
+    let source = if value == default {
+        "default"
+    } else {
+        "runtime"
+    };
+    entry(key, value, source)
+}
+
+/// Replace likely secrets inside string values
+fn redact(value: Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(secrets::redact(&s)),
+        Value::Array(items) => Value::Array(items.into_iter().map(redact).collect()),
+        Value::Object(map) => Value::Object(map.into_iter().map(|(k, v)| (k, redact(v))).collect()),
+        other => other,
+    }
+}
+
+/// The resolved value of every backend setting and where it came from: store
+/// defaults, user overrides, session-only changes, environment and keychain.
+/// Secrets are redacted; the API key is only reported as present or not.
+#[tauri::command]
+pub fn effective_config(
+    app: AppHandle,
+    limiter: State<RateLimiter>,
+    concurrency: State<ConcurrencyLimiter>,
+    bridge: State<DebugBridgeState>,
+    background: State<BackgroundAutosave>,
+) -> Result<EffectiveConfig, String> {
+    let mut entries = vec![
+        stored(
+            &app,
+            versions::MAX_VERSION_BYTES_KEY,
+            json!(versions::DEFAULT_MAX_VERSION_BYTES),
+        ),
+        stored(&app, versions::NORMALIZE_LINE_ENDINGS_KEY, json!(true)),
+        stored(
+            &app,
+            versions::AUTO_LABEL_THRESHOLD_KEY,
+            json!(versions::DEFAULT_AUTO_LABEL_THRESHOLD),
+        ),
+        stored(&app, versions::DOCUMENT_TEMPLATE_KEY, Value::Null),
+        stored(&app, versions::BLOCK_SECRETS_KEY, json!(false)),
+        stored(&app, versions::DEFAULT_DURABILITY_KEY, json!("relaxed")),
+        stored(&app, versions::STORAGE_QUOTA_KEY, Value::Null),
+        stored(
+            &app,
+            history::DIFF_SIZE_THRESHOLD_KEY,
+            json!(history::DEFAULT_DIFF_SIZE_THRESHOLD),
+        ),
+        stored(
+            &app,
+            comments::MAX_COMMENT_LENGTH_KEY,
+            json!(comments::DEFAULT_MAX_COMMENT_LENGTH),
+        ),
+        stored(&app, comments::EVENT_BATCHING_KEY, json!(true)),
+        stored(
+            &app,
+            export::MAX_IMPORT_BYTES_KEY,
+            json!(export::DEFAULT_MAX_IMPORT_BYTES),
+        ),
+        stored(
+            &app,
+            analysis::STOPWORDS_KEY,
+            json!(text::DEFAULT_STOPWORDS),
+        ),
+        stored(&app, workspace::WORKING_DIRECTORY_KEY, Value::Null),
+        stored(&app, backup::AUTO_BACKUP_KEY, Value::Null),
+        stored(&app, ai::STREAMING_ENABLED_KEY, json!(true)),
+        stored(
+            &app,
+            ai::REQUEST_TIMEOUT_KEY,
+            json!(ai::DEFAULT_REQUEST_TIMEOUT_SECS),
+        ),
+        stored(
+            &app,
+            ai::OVERALL_TIMEOUT_KEY,
+            json!(ai::DEFAULT_OVERALL_TIMEOUT_SECS),
+        ),
+        stored(&app, ai::USAGE_TICK_KEY, json!(ai::DEFAULT_USAGE_TICK_MS)),
+        stored(&app, ai_usage::USAGE_ALERT_KEY, Value::Null),
+        stored(&app, embeddings::EMBEDDING_MODEL_KEY, Value::Null),
+        stored(&app, ai::DEFAULT_SYSTEM_PROMPT_KEY, Value::Null),
+        stored(&app, ai::DOCUMENT_PROMPTS_KEY, json!({})),
+    ];
+    for provider in ai::PROVIDERS {
+        entries.push(stored(
+            &app,
+            &format!("{}{}", ai::BASE_URL_KEY_PREFIX, provider),
+            json!(anthropic::DEFAULT_BASE_URL),
+        ));
+    }
+
+    entries.push(runtime(
+        "ai_rate_limit",
+        json!(limiter.config()),
+        json!(RateLimitConfig::default()),
+    ));
+    entries.push(runtime(
+        "ai_concurrency",
+        json!(concurrency.config()),
+        json!(ConcurrencyConfig::default()),
+    ));
+    entries.push(runtime(
+        "background_autosave_interval_secs",
+        json!(background.interval_secs()),
+        Value::Null,
+    ));
+    let rotation = *bridge.rotation.lock().unwrap();
+    let default_rotation = RotationConfig::default();
+    entries.push(runtime(
+        "debug_log_rotation",
+        json!({ "maxBytes": rotation.max_bytes, "keepBytes": rotation.keep_bytes }),
+        json!({ "maxBytes": default_rotation.max_bytes, "keepBytes": default_rotation.keep_bytes }),
+    ));
+    entries.push(runtime(
+        "debug_log_flush_interval_ms",
+        json!(bridge.flush_interval_ms.load(Ordering::SeqCst)),
+        json!(debug_bridge::DEFAULT_FLUSH_INTERVAL_MS),
+    ));
+    entries.push(runtime(
+        "debug_bridge_enabled",
+        json!(bridge.enabled.load(Ordering::SeqCst)),
+        json!(true),
+    ));
+    let webhook = bridge
+        .webhook
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|w| json!({ "url": mask_url(&w.url), "minLevel": w.filter.min_level }));
+    entries.push(runtime("debug_log_webhook", json!(webhook), Value::Null));
+    // The log lives under $HOME
+    entries.push(entry(
+        "debug_log_path",
+        debug_bridge::log_path().map_or(Value::Null, Value::String),
+        "env",
+    ));
+    // A keychain failure is worth seeing here rather than failing the whole report
+    let api_key = match ai::has_api_key() {
+        Ok(true) => json!(secrets::REDACTED),
+        Ok(false) => Value::Null,
+        Err(e) => json!(e),
+    };
+    entries.push(entry("anthropic_api_key", api_key, "keychain"));
+
+    for entry in &mut entries {
+        entry.value = redact(std::mem::take(&mut entry.value));
+    }
+    Ok(EffectiveConfig { entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_url_hides_path_and_query_tokens() {
+        assert_eq!(
+            mask_url("https://hooks.slack.com/services/T000/B000/abcdef"),
+            "https://hooks.slack.com/[REDACTED]"
+        );
+        assert_eq!(
+            mask_url("https://logs.example.com:8443/ingest?token=abc"),
+            "https://logs.example.com:8443/[REDACTED]"
+        );
+        assert_eq!(
+            mask_url("https://logs.example.com"),
+            "https://logs.example.com"
+        );
+        assert_eq!(mask_url("not a url"), "[REDACTED]");
+    }
+}
@@ -0,0 +1,1061 @@
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::mpsc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands::versions::{content_from_row, CONTENT_COLUMNS};
+use crate::commands::workspace::allowed_path;
+use crate::db;
+
+/// Current values of the PRAGMAs SERQ manages, as seen by Rust-side connections
+#[tauri::command]
+pub fn db_get_pragmas(app: AppHandle) -> Result<HashMap<String, String>, String> {
+    let conn = db::open(&app)?;
+    let mut pragmas = HashMap::new();
+
+    for name in db::REPORTED_PRAGMAS {
+        let value = conn
+            .query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read PRAGMA {}: {}", name, e))?;
+        pragmas.insert(name.to_string(), db::value_to_string(value));
+    }
+
+    Ok(pragmas)
+}
+
+/// A bundled migration and whether the sql plugin has applied it to serq.db
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+    pub installed_on: Option<String>,
+}
+
+/// The sql plugin records applied migrations in sqlx's tracking table
+const MIGRATIONS_TABLE: &str = "_sqlx_migrations";
+
+/// Every bundled migration with its applied state, read from the plugin's tracking table
+#[tauri::command]
+pub fn list_migrations(app: AppHandle) -> Result<Vec<MigrationStatus>, String> {
+    let conn = db::open(&app)?;
+    let applied = applied_migrations(&conn)?;
+
+    Ok(db::migrations()
+        .into_iter()
+        .map(|migration| {
+            let installed_on = applied.get(&migration.version).cloned();
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                applied: installed_on.is_some(),
+                installed_on,
+            }
+        })
+        .collect())
+}
+
+/// Successfully applied migration versions mapped to their install time.
+/// Empty if the plugin hasn't created its tracking table yet.
+fn applied_migrations(conn: &Connection) -> Result<HashMap<i64, String>, String> {
+    let has_table: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            [MIGRATIONS_TABLE],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !has_table {
+        return Ok(HashMap::new());
+    }
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT version, CAST(installed_on AS TEXT) FROM {} WHERE success = 1",
+            MIGRATIONS_TABLE
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+/// Regenerate the full-text index from the versions table, e.g. after an
+/// import that bypassed the sync triggers
+#[tauri::command]
+pub fn fts_rebuild(app: AppHandle) -> Result<(), String> {
+    let conn = db::open(&app)?;
+    conn.execute(
+        "INSERT INTO versions_fts (versions_fts) VALUES ('rebuild')",
+        [],
+    )
+    .map_err(|e| format!("Failed to rebuild search index: {}", e))?;
+    Ok(())
+}
+
+/// Whether the full-text index matches the versions table.
+/// Returns false (not an error) when the index has drifted.
+#[tauri::command]
+pub fn fts_check(app: AppHandle) -> Result<bool, String> {
+    let conn = db::open(&app)?;
+    // rank = 1 also compares the index against the content table
+    match conn.execute(
+        "INSERT INTO versions_fts (versions_fts, rank) VALUES ('integrity-check', 1)",
+        [],
+    ) {
+        Ok(_) => Ok(true),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::DatabaseCorrupt =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(format!("Failed to check search index: {}", e)),
+    }
+}
+
+/// How the full-text index compares with the versions table
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FtsStatus {
+    pub version_rows: i64,
+    pub indexed_rows: i64,
+    /// Versions with no index entry, so invisible to search
+    pub missing: i64,
+    /// Index entries whose version is gone
+    pub orphaned: i64,
+    pub stale: bool,
+    /// What to do about it, when stale
+    pub suggestion: Option<String>,
+}
+
+/// Row counts of the versions table and the full-text index, flagging a stale
+/// index. Cheaper than `fts_check`, which verifies every token.
+#[tauri::command]
+pub fn fts_status(app: AppHandle) -> Result<FtsStatus, String> {
+    let conn = db::open(&app)?;
+    let count = |sql: &str| -> Result<i64, String> {
+        conn.query_row(sql, [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read search index status: {}", e))
+    };
+    // The index is external-content, so counting versions_fts would count versions;
+    // the docsize shadow table has one row per indexed version
+    let version_rows = count("SELECT COUNT(*) FROM versions")?;
+    let indexed_rows = count("SELECT COUNT(*) FROM versions_fts_docsize")?;
+    let missing = count(
+        "SELECT COUNT(*) FROM versions v
+         WHERE NOT EXISTS (SELECT 1 FROM versions_fts_docsize d WHERE d.id = v.id)",
+    )?;
+    let orphaned = count(
+        "SELECT COUNT(*) FROM versions_fts_docsize d
+         WHERE NOT EXISTS (SELECT 1 FROM versions v WHERE v.id = d.id)",
+    )?;
+    let stale = missing > 0 || orphaned > 0;
+    Ok(FtsStatus {
+        version_rows,
+        indexed_rows,
+        missing,
+        orphaned,
+        stale,
+        suggestion: stale
+            .then(|| "Search results may be incomplete; run fts_rebuild to reindex".to_string()),
+    })
+}
+
+/// Outcome of applying pending migrations to a copy of serq.db
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunReport {
+    pub success: bool,
+    /// Pending migration versions that applied cleanly, in order
+    pub applied: Vec<i64>,
+    pub failed_version: Option<i64>,
+    pub failed_statement: Option<String>,
+    pub error: Option<String>,
+    pub integrity_ok: bool,
+    pub foreign_key_violations: usize,
+}
+
+/// Apply pending migrations to a snapshot of serq.db in the temp dir, then run
+/// integrity and foreign-key checks. The live database is only read.
+#[tauri::command]
+pub fn migration_dry_run(app: AppHandle) -> Result<DryRunReport, String> {
+    let copy_path = std::env::temp_dir().join(format!(
+        "serq-migration-dry-run-{}.db",
+        uuid::Uuid::new_v4()
+    ));
+    // Consistent snapshot, including anything still in the WAL
+    {
+        let conn = db::open(&app)?;
+        conn.execute("VACUUM INTO ?1", [copy_path.to_string_lossy()])
+            .map_err(|e| format!("Failed to copy database: {}", e))?;
+    }
+
+    let report = dry_run_on(&copy_path);
+    let _ = std::fs::remove_file(&copy_path);
+    report
+}
+
+fn dry_run_on(path: &std::path::Path) -> Result<DryRunReport, String> {
+    let conn = Connection::open(path).map_err(|e| format!("Failed to open copy: {}", e))?;
+    db::apply_connection_pragmas(&conn)?;
+    apply_and_check(&conn)
+}
+
+/// Apply migrations `conn` hasn't recorded statement by statement, stopping at the
+/// first failure, then run integrity and foreign-key checks
+fn apply_and_check(conn: &Connection) -> Result<DryRunReport, String> {
+    let applied = applied_migrations(conn)?;
+    let mut report = DryRunReport::default();
+
+    'migrations: for migration in db::migrations() {
+        if applied.contains_key(&migration.version) {
+            continue;
+        }
+        for statement in db::split_statements(migration.sql) {
+            if let Err(e) = conn.execute_batch(&statement) {
+                report.failed_version = Some(migration.version);
+                report.failed_statement = Some(statement);
+                report.error = Some(e.to_string());
+                break 'migrations;
+            }
+        }
+        report.applied.push(migration.version);
+    }
+
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    report.integrity_ok = integrity == "ok";
+
+    let mut stmt = conn
+        .prepare("PRAGMA foreign_key_check")
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    while rows.next().map_err(|e| e.to_string())?.is_some() {
+        report.foreign_key_violations += 1;
+    }
+
+    report.success =
+        report.error.is_none() && report.integrity_ok && report.foreign_key_violations == 0;
+    Ok(report)
+}
+
+/// Apply every bundled migration in order to a fresh in-memory database and run
+/// integrity checks, to confirm the migrations work on this platform's SQLite.
+/// Never touches the live database. Fails with the first broken migration and
+/// statement, or the check that didn't pass.
+#[tauri::command]
+pub fn verify_migrations_from_scratch() -> Result<(), String> {
+    let conn = Connection::open_in_memory()
+        .map_err(|e| format!("Failed to open in-memory database: {}", e))?;
+    db::apply_connection_pragmas(&conn)?;
+    let report = apply_and_check(&conn)?;
+
+    if let (Some(version), Some(statement), Some(error)) = (
+        report.failed_version,
+        &report.failed_statement,
+        &report.error,
+    ) {
+        return Err(format!(
+            "Migration {} failed: {}\nStatement: {}",
+            version, error, statement
+        ));
+    }
+    if !report.integrity_ok {
+        return Err("Conflict: integrity check failed after migrating".to_string());
+    }
+    if report.foreign_key_violations > 0 {
+        return Err(format!(
+            "Conflict: {} foreign key violations after migrating",
+            report.foreign_key_violations
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DatabaseSwitched {
+    path: String,
+    migrations_applied: Vec<i64>,
+}
+
+/// Point Rust commands at another database file, creating it if missing and
+/// bringing it up to the current schema. Emits `database-switched` so the UI can
+/// reload its own connection (`sqlite:<path>`) and data.
+#[tauri::command]
+pub fn switch_database(app: AppHandle, path: String) -> Result<(), String> {
+    let target = std::path::PathBuf::from(&path);
+    // An empty file is a valid, fresh database
+    let is_empty = std::fs::metadata(&target).is_ok_and(|m| m.len() == 0);
+    if target.exists() && !is_empty {
+        if !db::is_sqlite_file(&target)? {
+            return Err(format!("Validation: {} is not a SQLite database", path));
+        }
+    } else if let Some(dir) = target.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+
+    let mut conn =
+        Connection::open(&target).map_err(|e| format!("Failed to open database: {}", e))?;
+    db::apply_connection_pragmas(&conn)?;
+    conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))
+        .map_err(|e| format!("Failed to enable WAL: {}", e))?;
+    let migrations_applied = db::apply_migrations(&mut conn)?;
+    drop(conn);
+
+    // Commands already running finish on the old file; new ones open the new one
+    let canonical = std::fs::canonicalize(&target).map_err(|e| e.to_string())?;
+    *app.state::<db::ActiveDatabase>().0.lock().unwrap() = Some(canonical.clone());
+
+    app.emit(
+        "database-switched",
+        DatabaseSwitched {
+            path: canonical.to_string_lossy().into_owned(),
+            migrations_applied,
+        },
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Row and time caps for `run_readonly_query`
+const QUERY_MAX_ROWS: usize = 1000;
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Columns and rows of an ad-hoc query
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+    /// True when more rows matched than `QUERY_MAX_ROWS`
+    pub truncated: bool,
+}
+
+fn json_value(value: rusqlite::types::ValueRef) -> serde_json::Value {
+    use rusqlite::types::ValueRef;
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => f.into(),
+        ValueRef::Text(s) => String::from_utf8_lossy(s).into_owned().into(),
+        ValueRef::Blob(b) => format!("<{} bytes>", b.len()).into(),
+    }
+}
+
+/// Prepare `sql` if it is a single read-only SELECT; anything else fails with
+/// `Forbidden`
+fn prepare_select<'c>(conn: &'c Connection, sql: &str) -> Result<rusqlite::Statement<'c>, String> {
+    let keyword = sql
+        .trim_start()
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or("")
+        .to_ascii_uppercase();
+    if keyword != "SELECT" && keyword != "WITH" {
+        return Err("Forbidden: only SELECT queries are allowed".to_string());
+    }
+    let stmt = conn.prepare(sql).map_err(|e| match e {
+        rusqlite::Error::MultipleStatement => {
+            "Forbidden: only a single statement is allowed".to_string()
+        }
+        e => format!("Validation: invalid query: {}", e),
+    })?;
+    if !stmt.readonly() {
+        return Err("Forbidden: the query would modify the database".to_string());
+    }
+    Ok(stmt)
+}
+
+/// Run one SELECT against a read-only connection, for diagnostics. Anything else
+/// (writes, PRAGMAs, several statements) is rejected with `Forbidden`. Returns at
+/// most 1000 rows and gives up after 5 seconds.
+#[tauri::command]
+pub fn run_readonly_query(app: AppHandle, sql: String) -> Result<QueryResult, String> {
+    let conn = db::open_readonly(&app)?;
+    let mut stmt = prepare_select(&conn, &sql)?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+
+    // Interrupt the query if it outlives the timeout; finishing first drops `done`
+    let (done, finished) = mpsc::channel::<()>();
+    let interrupt = conn.get_interrupt_handle();
+    std::thread::spawn(move || {
+        if finished.recv_timeout(QUERY_TIMEOUT) == Err(mpsc::RecvTimeoutError::Timeout) {
+            interrupt.interrupt();
+        }
+    });
+
+    let mut rows = Vec::new();
+    let mut truncated = false;
+    let mut cursor = stmt.query([]).map_err(|e| e.to_string())?;
+    while let Some(row) = cursor.next().map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(err, _)
+            if err.code == rusqlite::ErrorCode::OperationInterrupted =>
+        {
+            format!("Cancelled: query exceeded {}s", QUERY_TIMEOUT.as_secs())
+        }
+        e => format!("Query failed: {}", e),
+    })? {
+        if rows.len() == QUERY_MAX_ROWS {
+            truncated = true;
+            break;
+        }
+        let values = (0..columns.len())
+            .map(|i| row.get_ref(i).map(json_value))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        rows.push(values);
+    }
+    drop(done);
+
+    Ok(QueryResult {
+        columns,
+        rows,
+        truncated,
+    })
+}
+
+/// A column as reported by `PRAGMA table_info`: type, not null, default, pk position
+type ColumnInfo = (String, bool, Option<String>, i64);
+
+/// Schema objects keyed by (type, name), with their SQL whitespace-normalized.
+/// SQLite's own tables and the migrations bookkeeping table are left out.
+fn schema_objects(conn: &Connection) -> Result<BTreeMap<(String, String), String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT type, name, COALESCE(sql, '') FROM sqlite_master
+             WHERE name NOT LIKE 'sqlite_%' AND name != '_sqlx_migrations'",
+        )
+        .map_err(|e| e.to_string())?;
+    let objects = stmt
+        .query_map([], |row| {
+            let sql: String = row.get(2)?;
+            Ok((
+                (row.get(0)?, row.get(1)?),
+                sql.split_whitespace().collect::<Vec<_>>().join(" "),
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<BTreeMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(objects)
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<BTreeMap<String, ColumnInfo>, String> {
+    let mut stmt = conn
+        .prepare("SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?1)")
+        .map_err(|e| e.to_string())?;
+    let columns = stmt
+        .query_map([table], |row| {
+            Ok((
+                row.get(0)?,
+                (row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?),
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<BTreeMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(columns)
+}
+
+/// Differences between the schema the migrations produce and the live database:
+/// missing or extra tables, indexes and triggers, column differences, and objects
+/// whose definitions don't match. Empty when the schemas agree. Read-only.
+#[tauri::command]
+pub fn schema_drift_report(app: AppHandle) -> Result<Vec<String>, String> {
+    let mut expected_conn = Connection::open_in_memory().map_err(|e| e.to_string())?;
+    db::apply_migrations(&mut expected_conn)?;
+    let live_conn = db::open_readonly(&app)?;
+
+    let expected = schema_objects(&expected_conn)?;
+    let live = schema_objects(&live_conn)?;
+    let mut report = Vec::new();
+
+    for ((kind, name), expected_sql) in &expected {
+        let Some(live_sql) = live.get(&(kind.clone(), name.clone())) else {
+            report.push(format!("Missing {} {}", kind, name));
+            continue;
+        };
+        if kind != "table" {
+            if live_sql != expected_sql {
+                report.push(format!("{} {} has a different definition", kind, name));
+            }
+            continue;
+        }
+
+        let expected_columns = table_columns(&expected_conn, name)?;
+        let live_columns = table_columns(&live_conn, name)?;
+        let mut column_drift = false;
+        for (column, info) in &expected_columns {
+            match live_columns.get(column) {
+                None => report.push(format!("Missing column {}.{}", name, column)),
+                Some(live_info) if live_info != info => {
+                    report.push(format!(
+                        "Column {}.{} differs: expected {}, found {}",
+                        name,
+                        column,
+                        describe_column(info),
+                        describe_column(live_info)
+                    ));
+                }
+                Some(_) => continue,
+            }
+            column_drift = true;
+        }
+        for column in live_columns.keys() {
+            if !expected_columns.contains_key(column) {
+                report.push(format!("Extra column {}.{}", name, column));
+                column_drift = true;
+            }
+        }
+        // Constraints only show up in the CREATE statement
+        if !column_drift && live_sql != expected_sql {
+            report.push(format!("table {} has a different definition", name));
+        }
+    }
+    for (kind, name) in live.keys() {
+        if !expected.contains_key(&(kind.clone(), name.clone())) {
+            report.push(format!("Extra {} {}", kind, name));
+        }
+    }
+    Ok(report)
+}
+
+fn describe_column((column_type, not_null, default, pk): &ColumnInfo) -> String {
+    let mut description = if column_type.is_empty() {
+        "untyped".to_string()
+    } else {
+        column_type.clone()
+    };
+    if *not_null {
+        description.push_str(" NOT NULL");
+    }
+    if let Some(default) = default {
+        description.push_str(&format!(" DEFAULT {}", default));
+    }
+    if *pk > 0 {
+        description.push_str(" PRIMARY KEY");
+    }
+    description
+}
+
+/// Timestamp columns `normalize_timestamps` checks, as (table, column)
+const TIMESTAMP_COLUMNS: &[(&str, &str)] = &[
+    ("versions", "timestamp"),
+    ("comments", "created_at"),
+    ("comments", "resolved_at"),
+    ("comments", "deleted_at"),
+];
+/// Integer timestamps below this are taken to be epoch seconds (before 1973 as ms)
+const EPOCH_SECONDS_CUTOFF: i64 = 100_000_000_000;
+
+/// What `normalize_timestamps` found
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizeReport {
+    /// Non-null timestamp values checked
+    pub inspected: usize,
+    /// Values in a legacy format, rewritten unless this was a dry run
+    pub fixed: usize,
+    /// Legacy values that couldn't be parsed and were left as they are
+    pub unparseable: usize,
+    pub dry_run: bool,
+}
+
+/// Epoch milliseconds for a timestamp stored in a legacy format, or `None` when
+/// it is already canonical. `Some(Err)` means it couldn't be parsed.
+fn legacy_timestamp(value: rusqlite::types::ValueRef) -> Option<Result<i64, ()>> {
+    use rusqlite::types::ValueRef;
+    let from_number = |n: f64| {
+        if n.abs() < EPOCH_SECONDS_CUTOFF as f64 {
+            (n * 1000.0).round() as i64
+        } else {
+            n.round() as i64
+        }
+    };
+    match value {
+        ValueRef::Integer(i) if i != 0 && i.abs() < EPOCH_SECONDS_CUTOFF => Some(Ok(i * 1000)),
+        ValueRef::Integer(_) | ValueRef::Null => None,
+        ValueRef::Real(f) if f.is_finite() => Some(Ok(from_number(f))),
+        ValueRef::Real(_) | ValueRef::Blob(_) => Some(Err(())),
+        ValueRef::Text(bytes) => {
+            let text = String::from_utf8_lossy(bytes);
+            let text = text.trim();
+            let parsed = db::parse_timestamp(text).ok().or_else(|| {
+                // SQLite's CURRENT_TIMESTAMP format, which is UTC
+                ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+                    .iter()
+                    .find_map(|format| chrono::NaiveDateTime::parse_from_str(text, format).ok())
+                    .map(|t| t.and_utc().timestamp_millis())
+            });
+            let parsed = parsed.or_else(|| {
+                text.parse::<f64>()
+                    .ok()
+                    .filter(|n| n.is_finite())
+                    .map(from_number)
+            });
+            Some(parsed.ok_or(()))
+        }
+    }
+}
+
+/// Find timestamps stored in legacy formats (ISO 8601 or SQLite date text, epoch
+/// seconds, floats) in `versions` and `comments` and, unless `dry_run`, rewrite them
+/// as the integer epoch milliseconds range queries compare against. Runs in one
+/// transaction.
+#[tauri::command]
+pub fn normalize_timestamps(app: AppHandle, dry_run: bool) -> Result<NormalizeReport, String> {
+    let mut conn = db::open(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut report = NormalizeReport {
+        dry_run,
+        ..Default::default()
+    };
+    for (table, column) in TIMESTAMP_COLUMNS {
+        let mut fixes: Vec<(i64, i64)> = Vec::new();
+        {
+            let mut stmt = tx
+                .prepare(&format!(
+                    "SELECT rowid, {column} FROM {table} WHERE {column} IS NOT NULL"
+                ))
+                .map_err(|e| e.to_string())?;
+            let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+            while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+                report.inspected += 1;
+                let value = row.get_ref(1).map_err(|e| e.to_string())?;
+                match legacy_timestamp(value) {
+                    None => {}
+                    Some(Ok(ms)) => fixes.push((row.get(0).map_err(|e| e.to_string())?, ms)),
+                    Some(Err(())) => report.unparseable += 1,
+                }
+            }
+        }
+        report.fixed += fixes.len();
+        if dry_run {
+            continue;
+        }
+        let mut update = tx
+            .prepare(&format!(
+                "UPDATE {table} SET {column} = ?1 WHERE rowid = ?2"
+            ))
+            .map_err(|e| e.to_string())?;
+        for (rowid, ms) in fixes {
+            update
+                .execute(rusqlite::params![ms, rowid])
+                .map_err(|e| format!("Failed to update {}.{}: {}", table, column, e))?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+/// Write a new database at `path` holding only `version_ids` and their comments,
+/// with the full schema and migration history, for attaching to bug reports.
+/// Each `replacements` key is replaced by its value in version content, labels and
+/// comment text, to scrub private details. Parents that weren't exported are
+/// cleared. Fails with `Conflict` if the file already exists.
+#[tauri::command]
+pub fn export_repro_db(
+    app: AppHandle,
+    path: String,
+    mut version_ids: Vec<i64>,
+    replacements: Option<HashMap<String, String>>,
+) -> Result<(), String> {
+    version_ids.sort_unstable();
+    version_ids.dedup();
+    if version_ids.is_empty() {
+        return Err("Validation: no versions to export".to_string());
+    }
+    let target = allowed_path(&app, &path)?;
+    if target.exists() {
+        return Err(format!("Conflict: {} already exists", path));
+    }
+    {
+        let mut repro =
+            Connection::open(&target).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        db::apply_migrations(&mut repro)?;
+    }
+    let result = copy_into_repro(
+        &app,
+        &target,
+        &version_ids,
+        &replacements.unwrap_or_default(),
+    );
+    if result.is_err() {
+        let _ = std::fs::remove_file(&target);
+    }
+    result
+}
+
+fn copy_into_repro(
+    app: &AppHandle,
+    target: &std::path::Path,
+    version_ids: &[i64],
+    replacements: &HashMap<String, String>,
+) -> Result<(), String> {
+    let mut conn = db::open(app)?;
+    // Selected versions may reference parents and threads that aren't copied
+    conn.execute_batch("PRAGMA foreign_keys = OFF")
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS repro",
+        [target.to_string_lossy().as_ref()],
+    )
+    .map_err(|e| format!("Failed to attach repro database: {}", e))?;
+
+    let placeholders = vec!["?"; version_ids.len()].join(", ");
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let copied = tx
+        .execute(
+            &format!(
+                "INSERT INTO repro.versions SELECT * FROM main.versions WHERE id IN ({})",
+                placeholders
+            ),
+            rusqlite::params_from_iter(version_ids),
+        )
+        .map_err(|e| format!("Failed to copy versions: {}", e))?;
+    if copied < version_ids.len() {
+        return Err("NotFound: some of the versions do not exist".to_string());
+    }
+    tx.execute(
+        &format!(
+            "INSERT INTO repro.comments SELECT * FROM main.comments WHERE version_id IN ({})",
+            placeholders
+        ),
+        rusqlite::params_from_iter(version_ids),
+    )
+    .map_err(|e| format!("Failed to copy comments: {}", e))?;
+    tx.execute_batch(
+        "UPDATE repro.versions SET parent_version_id = NULL
+         WHERE parent_version_id NOT IN (SELECT id FROM repro.versions);
+         UPDATE repro.comments SET parent_id = NULL
+         WHERE parent_id NOT IN (SELECT id FROM repro.comments);",
+    )
+    .map_err(|e| format!("Failed to clear dangling references: {}", e))?;
+    // Compressed text would otherwise escape the replacements
+    decompress_versions(&tx, "repro")?;
+
+    for (from, to) in replacements.iter().filter(|(from, _)| !from.is_empty()) {
+        tx.execute(
+            "UPDATE repro.versions SET content = replace(content, ?1, ?2),
+                 checkpoint_name = replace(checkpoint_name, ?1, ?2)",
+            rusqlite::params![from, to],
+        )
+        .and_then(|_| {
+            tx.execute(
+                "UPDATE repro.comments SET text = replace(text, ?1, ?2)",
+                rusqlite::params![from, to],
+            )
+        })
+        .map_err(|e| format!("Failed to scrub content: {}", e))?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    conn.execute_batch("DETACH DATABASE repro")
+        .map_err(|e| e.to_string())
+}
+
+/// Write a new database at `path` with every version and comment, renumbered to
+/// contiguous ids: versions 1..n by timestamp, comments "1".."n" by creation time, with
+/// parent and version references remapped to match. Content is stored
+/// uncompressed. Tags, metadata and other per-version tables aren't included.
+/// The live database is left untouched, and nothing is kept unless the remapped
+/// copy passes a foreign key check. Fails with `Conflict` if the file exists.
+#[tauri::command]
+pub fn export_resequenced(app: AppHandle, path: String) -> Result<(), String> {
+    let target = allowed_path(&app, &path)?;
+    if target.exists() {
+        return Err(format!("Conflict: {} already exists", path));
+    }
+    {
+        let mut export =
+            Connection::open(&target).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        db::apply_migrations(&mut export)?;
+    }
+    let result = copy_resequenced(&app, &target);
+    if result.is_err() {
+        let _ = std::fs::remove_file(&target);
+    }
+    result
+}
+
+fn copy_resequenced(app: &AppHandle, target: &std::path::Path) -> Result<(), String> {
+    let mut conn = db::open(app)?;
+    // Ids are rewritten in place, so references are briefly inconsistent
+    conn.execute_batch("PRAGMA foreign_keys = OFF")
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS export",
+        [target.to_string_lossy().as_ref()],
+    )
+    .map_err(|e| format!("Failed to attach export database: {}", e))?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute_batch(
+        "CREATE TEMP TABLE version_map (old_id INTEGER PRIMARY KEY, new_id INTEGER NOT NULL);
+         INSERT INTO version_map SELECT id, ROW_NUMBER() OVER (ORDER BY timestamp, id) FROM main.versions;
+         CREATE TEMP TABLE comment_map (old_id TEXT PRIMARY KEY, new_id TEXT NOT NULL);
+         INSERT INTO comment_map
+           SELECT id, CAST(ROW_NUMBER() OVER (ORDER BY created_at, id) AS TEXT)
+           FROM main.comments;
+         INSERT INTO export.versions SELECT * FROM main.versions;
+         INSERT INTO export.comments SELECT * FROM main.comments;",
+    )
+    .map_err(|e| format!("Failed to copy history: {}", e))?;
+
+    // References first, while ids still hold their old values. New ids go through
+    // a negated or prefixed form so no row collides with one not yet renumbered.
+    tx.execute_batch(
+        "UPDATE export.versions SET parent_version_id =
+           (SELECT new_id FROM version_map WHERE old_id = parent_version_id);
+         UPDATE export.comments SET
+           version_id = (SELECT new_id FROM version_map WHERE old_id = version_id),
+           parent_id = (SELECT new_id FROM comment_map WHERE old_id = parent_id);
+         UPDATE export.versions SET id = -(SELECT new_id FROM version_map WHERE old_id = id);
+         UPDATE export.versions SET id = -id;
+         UPDATE export.comments SET id = '#' || (SELECT new_id FROM comment_map WHERE old_id = id);
+         UPDATE export.comments SET id = substr(id, 2);
+         UPDATE export.sqlite_sequence SET seq = (SELECT COUNT(*) FROM export.versions)
+         WHERE name = 'versions';
+         DROP TABLE version_map;
+         DROP TABLE comment_map;",
+    )
+    .map_err(|e| format!("Failed to renumber ids: {}", e))?;
+    decompress_versions(&tx, "export")?;
+    // The FTS rowids still point at the old ids
+    tx.execute_batch("INSERT INTO export.versions_fts (versions_fts) VALUES ('rebuild')")
+        .map_err(|e| format!("Failed to index export: {}", e))?;
+
+    let violations: i64 = tx
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM pragma_foreign_key_check('versions', 'export'))
+                  + (SELECT COUNT(*) FROM pragma_foreign_key_check('comments', 'export'))",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to check export integrity: {}", e))?;
+    if violations > 0 {
+        return Err(format!(
+            "Conflict: the renumbered export has {} broken references",
+            violations
+        ));
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    conn.execute_batch("DETACH DATABASE export")
+        .map_err(|e| e.to_string())
+}
+
+/// Store every compressed version in `schema` as plain text again
+fn decompress_versions(conn: &Connection, schema: &str) -> Result<(), String> {
+    let compressed: Vec<(i64, String)> = {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT id, {} FROM {}.versions WHERE compressed != 0",
+                CONTENT_COLUMNS, schema
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, content_from_row(row, 1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decompress versions: {}", e))?;
+        rows
+    };
+    for (id, content) in compressed {
+        conn.execute(
+            &format!(
+                "UPDATE {}.versions SET content = ?1, compressed = 0, compressed_content = NULL
+                 WHERE id = ?2",
+                schema
+            ),
+            rusqlite::params![content, id],
+        )
+        .map_err(|e| format!("Failed to decompress version {}: {}", id, e))?;
+    }
+    Ok(())
+}
+
+/// One step of a query plan
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainRow {
+    pub id: i64,
+    pub parent: i64,
+    /// SQLite's own description, e.g. `SEARCH versions USING INDEX ...`
+    pub detail: String,
+    /// Table the step reads, for SCAN and SEARCH steps
+    pub table: Option<String>,
+    /// Index the step uses; `PRIMARY KEY` for rowid lookups
+    pub index: Option<String>,
+    /// The index alone answers the query, without reading the table
+    pub covering: bool,
+    /// Every row of `table` is read
+    pub full_scan: bool,
+    /// Plain-language reading of `detail`
+    pub summary: String,
+}
+
+/// Pull the table and index out of a plan step's detail
+fn parse_plan_detail(id: i64, parent: i64, detail: String) -> ExplainRow {
+    let mut words = detail.split_whitespace();
+    let op = words.next().unwrap_or("");
+    let table = if matches!(op, "SCAN" | "SEARCH") {
+        words.next().map(str::to_string)
+    } else {
+        None
+    };
+    let covering = detail.contains("USING COVERING INDEX");
+    let index = if let Some(rest) = detail
+        .split_once("USING COVERING INDEX ")
+        .or_else(|| detail.split_once("USING INDEX "))
+        .map(|(_, rest)| rest)
+    {
+        rest.split_whitespace().next().map(str::to_string)
+    } else if detail.contains("PRIMARY KEY") {
+        Some("PRIMARY KEY".to_string())
+    } else {
+        None
+    };
+    let full_scan = op == "SCAN" && index.is_none();
+    let summary = match (&table, &index) {
+        (Some(table), None) if full_scan => format!("Full scan of {}", table),
+        (Some(table), Some(index)) if index == "PRIMARY KEY" => {
+            format!("Looks up {} by primary key", table)
+        }
+        (Some(table), Some(index)) if covering => {
+            format!("Reads {} entirely from covering index {}", table, index)
+        }
+        (Some(table), Some(index)) => format!("Uses index {} on {}", index, table),
+        _ => detail.clone(),
+    };
+    ExplainRow {
+        id,
+        parent,
+        detail,
+        table,
+        index,
+        covering,
+        full_scan,
+        summary,
+    }
+}
+
+/// The `EXPLAIN QUERY PLAN` of a SELECT, with index use spelled out, for finding
+/// out why a query is slow. Rejects anything `run_readonly_query` would.
+#[tauri::command]
+pub fn explain_query(app: AppHandle, sql: String) -> Result<Vec<ExplainRow>, String> {
+    let conn = db::open_readonly(&app)?;
+    prepare_select(&conn, &sql)?;
+    let mut stmt = conn
+        .prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+        .map_err(|e| format!("Validation: invalid query: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(parse_plan_detail(row.get(0)?, row.get(1)?, row.get(3)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to explain query: {}", e))?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::types::ValueRef;
+
+    #[test]
+    fn canonical_timestamps_are_left_alone() {
+        assert_eq!(legacy_timestamp(ValueRef::Integer(1_714_564_800_000)), None);
+        assert_eq!(legacy_timestamp(ValueRef::Integer(0)), None);
+        assert_eq!(legacy_timestamp(ValueRef::Null), None);
+    }
+
+    #[test]
+    fn epoch_seconds_become_milliseconds() {
+        assert_eq!(
+            legacy_timestamp(ValueRef::Integer(1_714_564_800)),
+            Some(Ok(1_714_564_800_000))
+        );
+        assert_eq!(
+            legacy_timestamp(ValueRef::Real(1_714_564_800.5)),
+            Some(Ok(1_714_564_800_500))
+        );
+        assert_eq!(
+            legacy_timestamp(ValueRef::Real(1_714_564_800_000.0)),
+            Some(Ok(1_714_564_800_000))
+        );
+    }
+
+    #[test]
+    fn text_timestamps_are_parsed() {
+        let expected = Some(Ok(1_714_564_800_000));
+        assert_eq!(
+            legacy_timestamp(ValueRef::Text(b"2024-05-01T12:00:00Z")),
+            expected
+        );
+        assert_eq!(
+            legacy_timestamp(ValueRef::Text(b"2024-05-01 12:00:00")),
+            expected
+        );
+        assert_eq!(legacy_timestamp(ValueRef::Text(b" 1714564800 ")), expected);
+        assert_eq!(
+            legacy_timestamp(ValueRef::Text(b"yesterday")),
+            Some(Err(()))
+        );
+    }
+
+    #[test]
+    fn unusable_values_are_unparseable() {
+        assert_eq!(legacy_timestamp(ValueRef::Blob(&[1, 2])), Some(Err(())));
+        assert_eq!(legacy_timestamp(ValueRef::Real(f64::NAN)), Some(Err(())));
+    }
+
+    #[test]
+    fn plan_detail_names_index_and_table() {
+        let row = parse_plan_detail(
+            2,
+            0,
+            "SEARCH versions USING INDEX idx_versions_document_path (document_path=?)".into(),
+        );
+        assert_eq!(row.table.as_deref(), Some("versions"));
+        assert_eq!(row.index.as_deref(), Some("idx_versions_document_path"));
+        assert!(!row.covering && !row.full_scan);
+        assert_eq!(
+            row.summary,
+            "Uses index idx_versions_document_path on versions"
+        );
+    }
+
+    #[test]
+    fn plan_detail_recognizes_rowid_covering_and_full_scans() {
+        let row = parse_plan_detail(
+            2,
+            0,
+            "SEARCH versions USING INTEGER PRIMARY KEY (rowid=?)".into(),
+        );
+        assert_eq!(row.index.as_deref(), Some("PRIMARY KEY"));
+        assert_eq!(row.summary, "Looks up versions by primary key");
+
+        let row = parse_plan_detail(
+            2,
+            0,
+            "SCAN comments USING COVERING INDEX idx_comments_version".into(),
+        );
+        assert!(row.covering && !row.full_scan);
+        assert_eq!(row.index.as_deref(), Some("idx_comments_version"));
+
+        let row = parse_plan_detail(2, 0, "SCAN versions".into());
+        assert!(row.full_scan);
+        assert_eq!(row.summary, "Full scan of versions");
+
+        let row = parse_plan_detail(3, 0, "USE TEMP B-TREE FOR ORDER BY".into());
+        assert_eq!(row.table, None);
+        assert_eq!(row.summary, "USE TEMP B-TREE FOR ORDER BY");
+    }
+}
@@ -1,12 +1,344 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tokio::sync::Notify;
+
+use crate::secrets;
+
+/// Default rotation: once the log passes 5MB, keep the last 1MB
+const DEFAULT_MAX_BYTES: usize = 5 * 1024 * 1024;
+const DEFAULT_KEEP_BYTES: usize = 1024 * 1024;
+
+/// Buffered lines are written every interval, or sooner once the buffer passes this size
+pub(crate) const DEFAULT_FLUSH_INTERVAL_MS: u64 = 500;
+const FLUSH_BUFFER_BYTES: usize = 64 * 1024;
+
+/// Webhook sends wait this long after the first queued entry so bursts go out together
+const WEBHOOK_DEBOUNCE: Duration = Duration::from_secs(2);
+const WEBHOOK_BATCH_SIZE: usize = 100;
+/// Oldest entries are dropped past this, so an unreachable endpoint can't grow memory
+const WEBHOOK_QUEUE_LIMIT: usize = 5000;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Automatic rotation thresholds applied after each write
+#[derive(Debug, Clone, Copy)]
+pub struct RotationConfig {
+    pub max_bytes: usize,
+    pub keep_bytes: usize,
+}
+
+impl Default for RotationConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_BYTES,
+            keep_bytes: DEFAULT_KEEP_BYTES,
+        }
+    }
+}
+
+/// Which log entries a live-log subscriber receives. Empty fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Lowest level passed through, by `level_rank`
+    pub min_level: Option<String>,
+    /// Entries pass if their source contains any of these
+    pub sources: Vec<String>,
+}
+
+/// Severity order of frontend log levels; unknown levels rank with `log`
+fn level_rank(level: &str) -> u8 {
+    match level.to_ascii_lowercase().as_str() {
+        "debug" | "trace" => 0,
+        "warn" | "warning" => 2,
+        "error" => 3,
+        _ => 1,
+    }
+}
+
+impl LogFilter {
+    pub fn matches(&self, level: &str, source: Option<&str>) -> bool {
+        let level_ok = match self.min_level.as_deref() {
+            Some(min) => level_rank(level) >= level_rank(min),
+            None => true,
+        };
+        let source_ok = self.sources.is_empty()
+            || source.is_some_and(|src| self.sources.iter().any(|s| src.contains(s.as_str())));
+        level_ok && source_ok
+    }
+}
+
+/// Where log entries are forwarded, and which ones
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub filter: LogFilter,
+}
+
+/// One forwarded log entry; `line` is the formatted, redacted file line
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookEntry {
+    pub level: String,
+    pub timestamp: String,
+    pub source: Option<String>,
+    pub line: String,
+}
+
+/// Runtime state of the debug bridge
+pub struct DebugBridgeState {
+    /// Global kill switch; when false, log entries are dropped on arrival
+    pub enabled: AtomicBool,
+    pub rotation: Mutex<RotationConfig>,
+    /// Live-log subscriptions by (window label, subscription id). A window gets each
+    /// `debug-log-line` once if any of its subscriptions match.
+    pub subscriptions: Mutex<HashMap<(String, u64), LogFilter>>,
+    next_subscription_id: AtomicU64,
+    /// Formatted lines waiting for the next flush
+    pub pending: Mutex<String>,
+    pub flush_interval_ms: AtomicU64,
+    pub webhook: Mutex<Option<WebhookConfig>>,
+    /// Entries waiting for the webhook sender
+    pub webhook_queue: Mutex<VecDeque<WebhookEntry>>,
+    webhook_wake: Notify,
+}
+
+impl Default for DebugBridgeState {
+    fn default() -> Self {
+        Self {
+            enabled: AtomicBool::new(true),
+            rotation: Mutex::default(),
+            subscriptions: Mutex::default(),
+            next_subscription_id: AtomicU64::new(1),
+            pending: Mutex::default(),
+            flush_interval_ms: AtomicU64::new(DEFAULT_FLUSH_INTERVAL_MS),
+            webhook: Mutex::default(),
+            webhook_queue: Mutex::default(),
+            webhook_wake: Notify::new(),
+        }
+    }
+}
+
+impl DebugBridgeState {
+    /// Drop all of a window's live-log subscriptions. Called when the window is destroyed.
+    pub fn remove_window(&self, label: &str) {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .retain(|(window, _), _| window != label);
+    }
+
+    /// Add a subscription for `label` and return its id
+    fn subscribe(&self, label: &str, filter: LogFilter) -> u64 {
+        let id = self.next_subscription_id.fetch_add(1, Ordering::Relaxed);
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .insert((label.to_string(), id), filter);
+        id
+    }
+
+    /// Labels of windows with at least one subscription, sorted
+    pub fn subscribed_windows(&self) -> Vec<String> {
+        let mut windows: Vec<String> = self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .keys()
+            .map(|(window, _)| window.clone())
+            .collect();
+        windows.sort();
+        windows.dedup();
+        windows
+    }
+
+    /// Append buffered lines to the log file and rotate if needed.
+    /// Holds the buffer lock while writing so concurrent flushes keep line order.
+    pub fn flush(&self) -> Result<(), String> {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let log_path = log_path()?;
+
+        // Append to log file (create if doesn't exist)
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .map_err(|e| format!("Failed to open log file: {}", e))?;
+        file.write_all(pending.as_bytes())
+            .map_err(|e| format!("Failed to write to log file: {}", e))?;
+        pending.clear();
+
+        // Also rotate if file gets too large
+        let rotation = *self.rotation.lock().unwrap();
+        let metadata = std::fs::metadata(&log_path).map_err(|e| e.to_string())?;
+        if metadata.len() > rotation.max_bytes as u64 {
+            trim_log(&log_path, rotation.keep_bytes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Flush buffered log lines on a background thread every flush interval
+pub fn spawn_flusher(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let state = app.state::<DebugBridgeState>();
+        let interval = state.flush_interval_ms.load(Ordering::Relaxed);
+        std::thread::sleep(Duration::from_millis(interval));
+        if let Err(e) = state.flush() {
+            eprintln!("Debug log flush failed: {}", e);
+        }
+    });
+}
+
+/// POST queued log entries to the webhook as JSON arrays. Waits for the first entry,
+/// then `WEBHOOK_DEBOUNCE`, then sends everything queued in batches. Failed batches
+/// are dropped rather than retried so a dead endpoint can't back up logging.
+pub fn spawn_webhook_sender(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let client = reqwest::Client::new();
+        loop {
+            let state = app.state::<DebugBridgeState>();
+            state.webhook_wake.notified().await;
+            tokio::time::sleep(WEBHOOK_DEBOUNCE).await;
+            let batch: Vec<WebhookEntry> =
+                std::mem::take(&mut *state.webhook_queue.lock().unwrap()).into();
+            let url = state
+                .webhook
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|w| w.url.clone());
+            let Some(url) = url else {
+                continue;
+            };
+            for chunk in batch.chunks(WEBHOOK_BATCH_SIZE) {
+                let sent = client
+                    .post(&url)
+                    .timeout(WEBHOOK_TIMEOUT)
+                    .json(chunk)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status());
+                if let Err(e) = sent {
+                    eprintln!("Debug log webhook failed: {}", e);
+                }
+            }
+        }
+    });
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionStatus {
+    pub windows: Vec<String>,
+}
+
+/// What `debug_bridge_compact(keep_bytes)` would remove
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationPreview {
+    pub current_bytes: usize,
+    pub bytes_removed: usize,
+    pub lines_removed: usize,
+    /// Timestamp of the first entry that would remain, from its `[TIMESTAMP]` prefix
+    pub oldest_retained_timestamp: Option<String>,
+}
+
+pub(crate) fn log_path() -> Result<String, String> {
+    let home = std::env::var("HOME").map_err(|e| e.to_string())?;
+    Ok(format!("{}/.serq-debug.log", home))
+}
+
+/// Log file contents, or empty if it doesn't exist yet
+fn read_log(log_path: &str) -> Result<Vec<u8>, String> {
+    match std::fs::read(log_path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Offset trimming would keep from: roughly the last `keep_bytes`, on a line boundary
+fn trim_start(content: &[u8], keep_bytes: usize) -> usize {
+    if content.len() <= keep_bytes {
+        return 0;
+    }
+    let keep_from = content.len() - keep_bytes;
+    // Find the next newline after the cut point for clean truncation
+    content[keep_from..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map(|i| keep_from + i + 1)
+        .unwrap_or(keep_from)
+}
+
+/// Trim the log to roughly the last `keep_bytes`, starting on a line boundary.
+/// Returns the number of bytes removed.
+fn trim_log(log_path: &str, keep_bytes: usize) -> Result<usize, String> {
+    let content = read_log(log_path)?;
+    let start = trim_start(&content, keep_bytes);
+    if start > 0 {
+        std::fs::write(log_path, &content[start..]).map_err(|e| e.to_string())?;
+    }
+    Ok(start)
+}
+
+/// Append backend panics, with a backtrace, to the debug log, then run the previous
+/// hook so the panic is reported and unwinds as before. Written straight to the file
+/// rather than the buffer, since a panic can take the flusher down with it.
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("(non-string panic payload)");
+        // Same ISO 8601 form the frontend sends for its entries
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let mut line = format!("[{}] PANIC: {}", timestamp, message);
+        if let Some(location) = info.location() {
+            line.push_str(&format!("\n  at {}", location));
+        }
+        let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+        for frame in backtrace.lines() {
+            line.push_str(&format!("\n  {}", frame));
+        }
+        line.push('\n');
+        let written = log_path().and_then(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(secrets::redact(&line).as_bytes()))
+                .map_err(|e| e.to_string())
+        });
+        if let Err(e) = written {
+            eprintln!("Failed to log panic: {}", e);
+        }
+        previous(info);
+    }));
+}
 
 /// Receives log entries from the frontend debug bridge and writes them to a file.
 /// The file lives at ~/.serq-debug.log so Claude Code can read it with a simple `cat` or `tail -f`.
 #[tauri::command]
-pub fn debug_bridge_log(entry: String) -> Result<(), String> {
-    let home = std::env::var("HOME").map_err(|e| e.to_string())?;
-    let log_path = format!("{}/.serq-debug.log", home);
+pub fn debug_bridge_log(
+    app: AppHandle,
+    state: State<DebugBridgeState>,
+    entry: String,
+) -> Result<(), String> {
+    if !state.enabled.load(Ordering::Relaxed) {
+        return Ok(());
+    }
 
     // Parse the JSON entry to format it nicely
     let parsed: serde_json::Value = serde_json::from_str(&entry).map_err(|e| e.to_string())?;
@@ -30,25 +362,51 @@ pub fn debug_bridge_log(entry: String) -> Result<(), String> {
         }
     }
     line.push('\n');
+    // Never write pasted API keys or tokens to disk
+    let line = secrets::redact(&line);
 
-    // Append to log file (create if doesn't exist)
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_path)
-        .map_err(|e| format!("Failed to open log file: {}", e))?;
+    let mut labels: Vec<String> = state
+        .subscriptions
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, filter)| filter.matches(level, source))
+        .map(|((label, _), _)| label.clone())
+        .collect();
+    labels.sort();
+    labels.dedup();
+    for label in labels {
+        let _ = app.emit_to(label.as_str(), "debug-log-line", &line);
+    }
 
-    file.write_all(line.as_bytes())
-        .map_err(|e| format!("Failed to write to log file: {}", e))?;
+    let forward = state
+        .webhook
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|w| w.filter.matches(level, source));
+    if forward {
+        let mut queue = state.webhook_queue.lock().unwrap();
+        if queue.len() >= WEBHOOK_QUEUE_LIMIT {
+            queue.pop_front();
+        }
+        queue.push_back(WebhookEntry {
+            level: level.to_string(),
+            timestamp: timestamp.to_string(),
+            source: source.map(str::to_string),
+            line: line.clone(),
+        });
+        state.webhook_wake.notify_one();
+    }
 
-    // Also rotate if file gets too large (>5MB) - truncate to last 1MB
-    let metadata = std::fs::metadata(&log_path).map_err(|e| e.to_string())?;
-    if metadata.len() > 5 * 1024 * 1024 {
-        let content = std::fs::read_to_string(&log_path).map_err(|e| e.to_string())?;
-        let keep_from = content.len().saturating_sub(1024 * 1024);
-        // Find the next newline after the cut point for clean truncation
-        let start = content[keep_from..].find('\n').map(|i| keep_from + i + 1).unwrap_or(keep_from);
-        std::fs::write(&log_path, &content[start..]).map_err(|e| e.to_string())?;
+    // Buffered; the flusher thread writes it out unless a burst fills the buffer first
+    let buffered = {
+        let mut pending = state.pending.lock().unwrap();
+        pending.push_str(&line);
+        pending.len()
+    };
+    if buffered > FLUSH_BUFFER_BYTES {
+        state.flush()?;
     }
 
     Ok(())
@@ -56,9 +414,190 @@ pub fn debug_bridge_log(entry: String) -> Result<(), String> {
 
 /// Clear the debug log file - callable from frontend or CLI
 #[tauri::command]
-pub fn debug_bridge_clear() -> Result<(), String> {
-    let home = std::env::var("HOME").map_err(|e| e.to_string())?;
-    let log_path = format!("{}/.serq-debug.log", home);
+pub fn debug_bridge_clear(state: State<DebugBridgeState>) -> Result<(), String> {
+    state.pending.lock().unwrap().clear();
+    let log_path = log_path()?;
     std::fs::write(&log_path, "").map_err(|e| e.to_string())?;
     Ok(())
 }
+
+/// Trim the log now to roughly the last `keep_bytes`. Returns bytes removed.
+#[tauri::command]
+pub fn debug_bridge_compact(
+    state: State<DebugBridgeState>,
+    keep_bytes: usize,
+) -> Result<usize, String> {
+    state.flush()?;
+    trim_log(&log_path()?, keep_bytes)
+}
+
+/// Configure automatic rotation: once the log exceeds `max_bytes`, keep the last `keep_bytes`
+#[tauri::command]
+pub fn debug_bridge_set_rotation(
+    state: State<DebugBridgeState>,
+    max_bytes: usize,
+    keep_bytes: usize,
+) -> Result<(), String> {
+    if keep_bytes == 0 || keep_bytes >= max_bytes {
+        return Err(
+            "Validation: keep_bytes must be greater than 0 and less than max_bytes".to_string(),
+        );
+    }
+    *state.rotation.lock().unwrap() = RotationConfig {
+        max_bytes,
+        keep_bytes,
+    };
+    Ok(())
+}
+
+/// Also POST each log entry at or above `min_level` to `url`, which must be https.
+/// Entries are queued and sent in batches in the background. `None` stops forwarding.
+#[tauri::command]
+pub fn debug_bridge_set_webhook(
+    state: State<DebugBridgeState>,
+    url: Option<String>,
+    min_level: Option<String>,
+) -> Result<(), String> {
+    let Some(url) = url else {
+        *state.webhook.lock().unwrap() = None;
+        state.webhook_queue.lock().unwrap().clear();
+        return Ok(());
+    };
+    let parsed = reqwest::Url::parse(url.trim())
+        .map_err(|e| format!("Validation: invalid webhook URL: {}", e))?;
+    if parsed.scheme() != "https" {
+        return Err("Validation: webhook URL must use https".to_string());
+    }
+    *state.webhook.lock().unwrap() = Some(WebhookConfig {
+        url: parsed.to_string(),
+        filter: LogFilter {
+            min_level,
+            sources: Vec::new(),
+        },
+    });
+    Ok(())
+}
+
+/// Start receiving each log line as a `debug-log-line` event in the calling window.
+/// Returns the subscription id to pass to `debug_bridge_unsubscribe`.
+#[tauri::command]
+pub fn debug_bridge_subscribe(window: Window, state: State<DebugBridgeState>) -> u64 {
+    state.subscribe(window.label(), LogFilter::default())
+}
+
+/// Like `debug_bridge_subscribe`, but only entries at or above `min_level` whose
+/// source contains one of `sources` are sent. Adds to the window's other
+/// subscriptions rather than replacing them.
+#[tauri::command]
+pub fn debug_bridge_subscribe_filtered(
+    window: Window,
+    state: State<DebugBridgeState>,
+    min_level: Option<String>,
+    sources: Option<Vec<String>>,
+) -> u64 {
+    let filter = LogFilter {
+        min_level,
+        sources: sources
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect(),
+    };
+    state.subscribe(window.label(), filter)
+}
+
+/// End one of the calling window's live-log subscriptions
+#[tauri::command]
+pub fn debug_bridge_unsubscribe(window: Window, state: State<DebugBridgeState>, id: u64) {
+    state
+        .subscriptions
+        .lock()
+        .unwrap()
+        .remove(&(window.label().to_string(), id));
+}
+
+/// Windows currently subscribed to live-log events
+#[tauri::command]
+pub fn debug_bridge_subscription_status(state: State<DebugBridgeState>) -> SubscriptionStatus {
+    SubscriptionStatus {
+        windows: state.subscribed_windows(),
+    }
+}
+
+/// Turn debug logging on or off globally without touching the frontend
+#[tauri::command]
+pub fn debug_bridge_set_enabled(state: State<DebugBridgeState>, enabled: bool) {
+    state.enabled.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether the debug bridge is currently writing log entries
+#[tauri::command]
+pub fn debug_bridge_is_enabled(state: State<DebugBridgeState>) -> bool {
+    state.enabled.load(Ordering::Relaxed)
+}
+
+/// Set how often buffered log lines are written to disk
+#[tauri::command]
+pub fn debug_bridge_set_flush_interval(
+    state: State<DebugBridgeState>,
+    ms: u64,
+) -> Result<(), String> {
+    if ms == 0 {
+        return Err("Validation: flush interval must be greater than 0".to_string());
+    }
+    state.flush_interval_ms.store(ms, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Preview `debug_bridge_compact(keep_bytes)` without touching the file.
+/// Lines still buffered for the next flush aren't counted.
+#[tauri::command]
+pub fn debug_bridge_rotation_preview(keep_bytes: usize) -> Result<RotationPreview, String> {
+    let content = read_log(&log_path()?)?;
+    let start = trim_start(&content, keep_bytes);
+    let retained = String::from_utf8_lossy(&content[start..]);
+
+    // Entries start with `[TIMESTAMP] LEVEL:`; continuation lines are indented
+    let oldest_retained_timestamp = retained
+        .lines()
+        .filter_map(|line| line.strip_prefix('['))
+        .find_map(|rest| rest.split_once(']'))
+        .map(|(timestamp, _)| timestamp.to_string());
+
+    Ok(RotationPreview {
+        current_bytes: content.len(),
+        bytes_removed: start,
+        lines_removed: content[..start].iter().filter(|&&b| b == b'\n').count(),
+        oldest_retained_timestamp,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filtered_subscriptions_stack_per_window() {
+        let state = DebugBridgeState::default();
+        let errors = LogFilter {
+            min_level: Some("error".to_string()),
+            sources: Vec::new(),
+        };
+        let first = state.subscribe("main", errors.clone());
+        let second = state.subscribe("main", errors);
+        let other = state.subscribe("settings", LogFilter::default());
+        assert_ne!(first, second);
+        assert_eq!(state.subscriptions.lock().unwrap().len(), 3);
+        assert_eq!(state.subscribed_windows(), vec!["main", "settings"]);
+
+        state.remove_window("main");
+        let remaining: Vec<_> = state
+            .subscriptions
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        assert_eq!(remaining, vec![("settings".to_string(), other)]);
+    }
+}
@@ -0,0 +1,62 @@
+//! Debug-build-only views of managed state. Not compiled into release builds.
+
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use tauri::State;
+
+use crate::commands::background_autosave::BackgroundAutosave;
+use crate::commands::cancel::CancelRegistry;
+use crate::commands::debug_bridge::DebugBridgeState;
+use crate::rate_limit::{RateLimitConfig, RateLimiter};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockStatus {
+    pub cancel_registry: bool,
+    pub rate_limiter: bool,
+    pub debug_bridge: bool,
+}
+
+/// Non-sensitive snapshot of backend state
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeStateSnapshot {
+    pub active_request_ids: Vec<String>,
+    pub rate_limit: RateLimitConfig,
+    pub rate_limit_in_window: usize,
+    pub debug_bridge_enabled: bool,
+    pub debug_log_subscribers: Vec<String>,
+    /// Tick interval of the backend autosave timer, `None` when it isn't running
+    pub background_autosave_interval_secs: Option<u64>,
+    /// Which locks were held when the snapshot started
+    pub locks: LockStatus,
+}
+
+/// Dump managed state for diagnosing frontend/backend divergence
+#[tauri::command]
+pub fn dump_runtime_state(
+    registry: State<CancelRegistry>,
+    limiter: State<RateLimiter>,
+    bridge: State<DebugBridgeState>,
+    autosave: State<BackgroundAutosave>,
+) -> Result<RuntimeStateSnapshot, String> {
+    // Check locks first so taking them below doesn't skew the result
+    let locks = LockStatus {
+        cancel_registry: registry.is_locked(),
+        rate_limiter: limiter.is_locked(),
+        debug_bridge: bridge.rotation.try_lock().is_err()
+            || bridge.subscriptions.try_lock().is_err(),
+    };
+
+    let debug_log_subscribers = bridge.subscribed_windows();
+
+    Ok(RuntimeStateSnapshot {
+        active_request_ids: registry.active_ids(),
+        rate_limit: limiter.config(),
+        rate_limit_in_window: limiter.in_window(),
+        debug_bridge_enabled: bridge.enabled.load(Ordering::SeqCst),
+        debug_log_subscribers,
+        background_autosave_interval_secs: autosave.interval_secs(),
+        locks,
+    })
+}
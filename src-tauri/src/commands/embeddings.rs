@@ -0,0 +1,148 @@
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::anthropic;
+use crate::commands::ai::{base_url, require_api_key, timeouts};
+use crate::commands::versions::load_version;
+use crate::rate_limit::{ConcurrencyLimiter, RateLimiter};
+use crate::{db, settings};
+
+/// Store key for the model the last embedding was made with; queries use the same one
+pub(crate) const EMBEDDING_MODEL_KEY: &str = "ai_embedding_model";
+
+/// A version ranked by similarity to a query
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticMatch {
+    pub version_id: i64,
+    pub document_path: String,
+    pub label: Option<String>,
+    pub timestamp: i64,
+    /// Cosine similarity, -1.0 to 1.0
+    pub score: f32,
+}
+
+fn to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn from_blob(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Cosine similarity, or 0.0 when the lengths differ or either vector is zero
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Compute and store an embedding for a version. Does nothing if one already exists
+/// for the same content and model.
+#[tauri::command]
+pub async fn ai_embed_version(app: AppHandle, id: i64, model: String) -> Result<(), String> {
+    let api_key = require_api_key()?;
+    let (content, content_hash) = {
+        let conn = db::open(&app)?;
+        let version = load_version(&conn, id)?;
+        let existing: Option<(String, String)> = conn
+            .query_row(
+                "SELECT model, content_hash FROM version_embeddings WHERE version_id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if existing.is_some_and(|(m, hash)| m == model && hash == version.content_hash) {
+            return Ok(());
+        }
+        (version.content, version.content_hash)
+    };
+
+    let concurrency = app.state::<ConcurrencyLimiter>();
+    let _permit = concurrency.acquire().await?;
+    app.state::<RateLimiter>().acquire().await;
+    let vector =
+        anthropic::embed(&base_url(&app), timeouts(&app), &api_key, &model, &content).await?;
+
+    let conn = db::open(&app)?;
+    conn.execute(
+        "INSERT INTO version_embeddings
+         (version_id, model, content_hash, dimensions, vector, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(version_id) DO UPDATE SET model = excluded.model,
+             content_hash = excluded.content_hash, dimensions = excluded.dimensions,
+             vector = excluded.vector, created_at = excluded.created_at",
+        rusqlite::params![
+            id,
+            model,
+            content_hash,
+            vector.len() as i64,
+            to_blob(&vector),
+            db::now_ms()
+        ],
+    )
+    .map_err(|e| format!("Failed to store embedding: {}", e))?;
+    settings::set(&app, EMBEDDING_MODEL_KEY, &model)
+}
+
+/// The `top_k` embedded versions closest to `query`, best first. The query is
+/// embedded with the model of the last `ai_embed_version` call, and only versions
+/// embedded with that model are compared.
+#[tauri::command]
+pub async fn semantic_search(
+    app: AppHandle,
+    query: String,
+    top_k: usize,
+) -> Result<Vec<SemanticMatch>, String> {
+    if query.trim().is_empty() {
+        return Err("Validation: query is empty".to_string());
+    }
+    let api_key = require_api_key()?;
+    let model: String = settings::get(&app, EMBEDDING_MODEL_KEY).ok_or_else(|| {
+        "NotFound: no versions have been embedded yet; run ai_embed_version first".to_string()
+    })?;
+
+    let concurrency = app.state::<ConcurrencyLimiter>();
+    let _permit = concurrency.acquire().await?;
+    app.state::<RateLimiter>().acquire().await;
+    let target =
+        anthropic::embed(&base_url(&app), timeouts(&app), &api_key, &model, &query).await?;
+
+    let conn = db::open(&app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT v.id, v.document_path, v.checkpoint_name, v.timestamp, e.vector
+             FROM version_embeddings e JOIN versions v ON v.id = e.version_id
+             WHERE e.model = ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut matches = stmt
+        .query_map([&model], |row| {
+            let vector: Vec<u8> = row.get(4)?;
+            Ok(SemanticMatch {
+                version_id: row.get(0)?,
+                document_path: row.get(1)?,
+                label: row.get(2)?,
+                timestamp: row.get(3)?,
+                score: cosine(&target, &from_blob(&vector)),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    matches.truncate(top_k);
+    Ok(matches)
+}
@@ -0,0 +1,880 @@
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::commands::versions::load_version;
+use crate::commands::versions::{
+    content_from_row, create_version, gzip_original_len, insert_version_at, latest_version_id,
+    normalize_line_endings, version_from_row, Version, CONTENT_COLUMNS, VERSION_COLUMNS,
+};
+use crate::commands::workspace::allowed_path;
+use crate::{db, markdown, settings, text};
+
+pub const MANIFEST_NAME: &str = "manifest.json";
+pub const ZIP_FORMAT: &str = "serq-versions";
+pub const ZIP_FORMAT_VERSION: u32 = 1;
+
+/// Longest label fragment kept in an exported filename
+const MAX_FILENAME_LABEL: usize = 50;
+
+/// Store key and default for the largest file `import_large_file` accepts
+pub(crate) const MAX_IMPORT_BYTES_KEY: &str = "max_import_bytes";
+pub(crate) const DEFAULT_MAX_IMPORT_BYTES: u64 = 100 * 1024 * 1024;
+/// Bounds on the read size `import_large_file` uses
+const MIN_IMPORT_CHUNK: usize = 4 * 1024;
+const MAX_IMPORT_CHUNK: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportProgress<'a> {
+    path: &'a str,
+    bytes_read: u64,
+    total_bytes: u64,
+    percent: u8,
+}
+
+/// Root manifest of a versions zip export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZipManifest {
+    pub format: String,
+    pub format_version: u32,
+    pub exported_at: i64,
+    pub versions: Vec<ManifestEntry>,
+}
+
+/// Metadata for one exported version file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub file: String,
+    pub id: i64,
+    pub document_path: String,
+    pub label: Option<String>,
+    pub timestamp: i64,
+    pub is_checkpoint: bool,
+    pub parent_version_id: Option<i64>,
+    pub content_hash: String,
+}
+
+/// Reduce a label to characters safe in filenames on every platform
+pub fn sanitize_filename(label: &str) -> String {
+    let mut out = String::new();
+    for c in label.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            out.push(c);
+        } else if !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    out.trim_matches('-')
+        .chars()
+        .take(MAX_FILENAME_LABEL)
+        .collect::<String>()
+        .trim_end_matches('-')
+        .to_string()
+}
+
+/// `0001-20250101-120000-label.md`: export order, UTC save time, and sanitized label
+fn version_filename(index: usize, timestamp: i64, label: Option<&str>) -> String {
+    let time = Utc
+        .timestamp_millis_opt(timestamp)
+        .single()
+        .map(|t| t.format("%Y%m%d-%H%M%S").to_string())
+        .unwrap_or_else(|| timestamp.to_string());
+    match label.map(sanitize_filename).filter(|l| !l.is_empty()) {
+        Some(label) => format!("{:04}-{}-{}.md", index, time, label),
+        None => format!("{:04}-{}.md", index, time),
+    }
+}
+
+/// Export every version as an individual `.md` file in a zip, plus a root manifest.
+/// Rows are streamed into the archive one at a time rather than loaded up front.
+#[tauri::command]
+pub fn export_versions_zip(app: AppHandle, path: String) -> Result<(), String> {
+    let target = allowed_path(&app, &path)?;
+    let conn = db::open(&app)?;
+    let file = File::create(&target).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM versions ORDER BY id",
+            VERSION_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    let mut entries = Vec::new();
+
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let version = version_from_row(row).map_err(|e| e.to_string())?;
+        let file = version_filename(
+            entries.len() + 1,
+            version.timestamp,
+            version.label.as_deref(),
+        );
+
+        zip.start_file(file.as_str(), options)
+            .map_err(|e| format!("Failed to add {}: {}", file, e))?;
+        zip.write_all(version.content.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", file, e))?;
+
+        entries.push(ManifestEntry {
+            file,
+            id: version.id,
+            document_path: version.document_path,
+            label: version.label,
+            timestamp: version.timestamp,
+            is_checkpoint: version.is_checkpoint,
+            parent_version_id: version.parent_version_id,
+            content_hash: version.content_hash,
+        });
+    }
+
+    let manifest = ZipManifest {
+        format: ZIP_FORMAT.to_string(),
+        format_version: ZIP_FORMAT_VERSION,
+        exported_at: db::now_ms(),
+        versions: entries,
+    };
+    zip.start_file(MANIFEST_NAME, options)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|e| e.to_string())?;
+    zip.finish()
+        .map_err(|e| format!("Failed to finish zip: {}", e))?;
+
+    Ok(())
+}
+
+/// Outcome of a zip import
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub imported: usize,
+    /// Versions deleted first in `replace` mode
+    pub replaced: usize,
+    /// False when the zip had no manifest and metadata was synthesized
+    pub from_manifest: bool,
+}
+
+/// Read an entry's text, rejecting names that would escape the archive root
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<String, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| format!("Validation: manifest lists missing file {}: {}", name, e))?;
+    let mut content = String::new();
+    entry
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    Ok(content)
+}
+
+/// Recreate versions from a zip produced by `export_versions_zip`.
+/// `mode` is `append` (add to existing history) or `replace` (delete existing versions first).
+/// Zips without a manifest are imported as `.md` files in filename order.
+#[tauri::command]
+pub fn import_versions_zip(
+    app: AppHandle,
+    path: String,
+    mode: String,
+) -> Result<ImportSummary, String> {
+    let replace = match mode.as_str() {
+        "append" => false,
+        "replace" => true,
+        other => return Err(format!("Validation: unknown import mode '{}'", other)),
+    };
+
+    let source = allowed_path(&app, &path)?;
+    let file = File::open(&source).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid zip: {}", e))?;
+
+    // Reject path traversal before reading anything
+    let mut names = Vec::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        if entry.enclosed_name().is_none() {
+            return Err(format!("Validation: unsafe entry name '{}'", entry.name()));
+        }
+        if !entry.is_dir() {
+            names.push(entry.name().to_string());
+        }
+    }
+
+    let manifest = if names.iter().any(|n| n == MANIFEST_NAME) {
+        let text = read_entry(&mut archive, MANIFEST_NAME)?;
+        let manifest: ZipManifest = serde_json::from_str(&text)
+            .map_err(|e| format!("Validation: invalid manifest: {}", e))?;
+        if manifest.format != ZIP_FORMAT || manifest.format_version > ZIP_FORMAT_VERSION {
+            return Err(format!(
+                "Validation: unsupported manifest format {} v{}",
+                manifest.format, manifest.format_version
+            ));
+        }
+        Some(manifest)
+    } else {
+        None
+    };
+    let from_manifest = manifest.is_some();
+
+    let entries = match manifest {
+        Some(manifest) => manifest.versions,
+        None => synthesize_entries(&path, names),
+    };
+
+    let mut conn = db::open(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let replaced = if replace {
+        tx.execute("DELETE FROM versions", [])
+            .map_err(|e| format!("Failed to clear versions: {}", e))?
+    } else {
+        0
+    };
+
+    // Map exported ids to new ids so lineage survives the import
+    let mut new_ids = HashMap::new();
+    for entry in &entries {
+        let content = read_entry(&mut archive, &entry.file)?;
+        let parent = entry
+            .parent_version_id
+            .and_then(|old| new_ids.get(&old).copied());
+        let id = insert_version_at(
+            &tx,
+            &entry.document_path,
+            &content,
+            entry.label.as_deref(),
+            parent,
+            entry.timestamp,
+        )?;
+        new_ids.insert(entry.id, id);
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(ImportSummary {
+        imported: entries.len(),
+        replaced,
+        from_manifest,
+    })
+}
+
+/// Metadata for a manifest-less zip: every `.md` file in filename order, as one
+/// linear history of a document named after the zip
+fn synthesize_entries(zip_path: &str, mut names: Vec<String>) -> Vec<ManifestEntry> {
+    names.retain(|n| n.ends_with(".md"));
+    names.sort();
+
+    let document_path = Path::new(zip_path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Imported".to_string());
+    let now = db::now_ms();
+
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(i, file)| ManifestEntry {
+            id: i as i64 + 1,
+            parent_version_id: (i > 0).then_some(i as i64),
+            document_path: document_path.clone(),
+            label: None,
+            timestamp: now + i as i64,
+            is_checkpoint: false,
+            content_hash: String::new(),
+            file,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnonymizedVersion {
+    id: i64,
+    document_path: String,
+    label: Option<String>,
+    timestamp: i64,
+    is_checkpoint: bool,
+    parent_version_id: Option<i64>,
+    content: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnonymizedComment {
+    id: String,
+    version_id: Option<i64>,
+    document_path: String,
+    text: String,
+    created_at: i64,
+    resolved_at: Option<i64>,
+    position_from: i64,
+    position_to: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnonymizedExport {
+    format: &'static str,
+    format_version: u32,
+    exported_at: i64,
+    versions: Vec<AnonymizedVersion>,
+    comments: Vec<AnonymizedComment>,
+}
+
+/// Export all versions and comments as JSON with find/replace pairs applied as
+/// whole-word matches to content, comment text, labels, and document paths.
+/// Comment positions still refer to the original text.
+#[tauri::command]
+pub fn export_anonymized(
+    app: AppHandle,
+    path: String,
+    replacements: Vec<(String, String)>,
+) -> Result<(), String> {
+    let target = allowed_path(&app, &path)?;
+    let conn = db::open(&app)?;
+    let scrub = |text: &str| text::apply_replacements(text, &replacements);
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, document_path, checkpoint_name, timestamp, is_checkpoint,
+                    parent_version_id, {}
+             FROM versions ORDER BY id",
+            CONTENT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let versions = stmt
+        .query_map([], |row| {
+            Ok(AnonymizedVersion {
+                id: row.get(0)?,
+                document_path: scrub(&row.get::<_, String>(1)?),
+                label: row.get::<_, Option<String>>(2)?.map(|l| scrub(&l)),
+                timestamp: row.get(3)?,
+                is_checkpoint: row.get(4)?,
+                parent_version_id: row.get(5)?,
+                content: scrub(&content_from_row(row, 6)?),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, version_id, document_path, text, created_at, resolved_at,
+                    position_from, position_to
+             FROM comments ORDER BY created_at",
+        )
+        .map_err(|e| e.to_string())?;
+    let comments = stmt
+        .query_map([], |row| {
+            Ok(AnonymizedComment {
+                id: row.get(0)?,
+                version_id: row.get(1)?,
+                document_path: scrub(&row.get::<_, String>(2)?),
+                text: scrub(&row.get::<_, String>(3)?),
+                created_at: row.get(4)?,
+                resolved_at: row.get(5)?,
+                position_from: row.get(6)?,
+                position_to: row.get(7)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let export = AnonymizedExport {
+        format: "serq-anonymized",
+        format_version: 1,
+        exported_at: db::now_ms(),
+        versions,
+        comments,
+    };
+    let file = File::create(&target).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    serde_json::to_writer_pretty(file, &export)
+        .map_err(|e| format!("Failed to write export: {}", e))
+}
+
+/// Fixed per-file zip overhead: local header, data descriptor and central
+/// directory record (each carrying the CRC-32), excluding the filename itself
+const ZIP_ENTRY_OVERHEAD: u64 = 30 + 16 + 46;
+/// Zip end-of-central-directory record
+const ZIP_TRAILER: u64 = 22;
+/// Length of an exported filename, less its label: `0001-20250101-120000-.md`
+const ZIP_FILENAME_BASE: u64 = 24;
+/// Pretty-printed JSON keys, punctuation and indentation around one manifest entry,
+/// including its 64-character `contentHash`
+const MANIFEST_ENTRY_OVERHEAD: u64 = 280;
+/// The same for one anonymized version and one anonymized comment
+const JSON_VERSION_OVERHEAD: u64 = 200;
+const JSON_COMMENT_OVERHEAD: u64 = 260;
+/// Top-level JSON wrapper (format, version, export time)
+const JSON_HEADER: u64 = 120;
+
+/// Byte totals the export size estimate is built from
+struct ExportTotals {
+    versions: u64,
+    content: u64,
+    paths: u64,
+    labels: u64,
+    comments: u64,
+    comment_text: u64,
+    comment_paths: u64,
+}
+
+fn export_totals(conn: &rusqlite::Connection) -> Result<ExportTotals, String> {
+    let (versions, plain_content, paths, labels) = conn
+        .query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(CASE WHEN compressed = 0
+                                 THEN LENGTH(CAST(content AS BLOB)) END), 0),
+                    COALESCE(SUM(LENGTH(CAST(document_path AS BLOB))), 0),
+                    COALESCE(SUM(LENGTH(CAST(checkpoint_name AS BLOB))), 0)
+             FROM versions",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    // Compressed versions count at their original size, read from the gzip trailer
+    let mut content = plain_content as u64;
+    let mut stmt = conn
+        .prepare("SELECT compressed_content FROM versions WHERE compressed != 0")
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let compressed: Vec<u8> = row.get(0).map_err(|e| e.to_string())?;
+        content += gzip_original_len(&compressed);
+    }
+    let (comments, comment_text, comment_paths) = conn
+        .query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(LENGTH(CAST(text AS BLOB))), 0),
+                    COALESCE(SUM(LENGTH(CAST(document_path AS BLOB))), 0)
+             FROM comments",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(ExportTotals {
+        versions: versions as u64,
+        content,
+        paths: paths as u64,
+        labels: labels as u64,
+        comments: comments as u64,
+        comment_text: comment_text as u64,
+        comment_paths: comment_paths as u64,
+    })
+}
+
+/// Predict the size in bytes of an export without writing it. `format` is `zip`
+/// (`export_versions_zip`) or `json` (`export_anonymized`). Zip content is counted
+/// uncompressed, so the estimate is an upper bound.
+#[tauri::command]
+pub fn estimate_export_size(app: AppHandle, format: String) -> Result<u64, String> {
+    let conn = db::open(&app)?;
+    let t = export_totals(&conn)?;
+    match format.as_str() {
+        "zip" => {
+            // Filenames appear in the local header, the central directory and the manifest
+            let filenames = t.versions * ZIP_FILENAME_BASE + t.labels;
+            let entries = t.versions + 1;
+            let manifest = JSON_HEADER + t.versions * MANIFEST_ENTRY_OVERHEAD + t.paths + t.labels;
+            Ok(t.content
+                + manifest
+                + 3 * filenames
+                + entries * ZIP_ENTRY_OVERHEAD
+                + 2 * MANIFEST_NAME.len() as u64
+                + ZIP_TRAILER)
+        }
+        "json" => Ok(JSON_HEADER
+            + t.content
+            + t.paths
+            + t.labels
+            + t.versions * JSON_VERSION_OVERHEAD
+            + t.comment_text
+            + t.comment_paths
+            + t.comments * JSON_COMMENT_OVERHEAD),
+        other => Err(format!(
+            "Validation: unknown export format '{}' (expected zip or json)",
+            other
+        )),
+    }
+}
+
+/// A version's markdown rendered to sanitized HTML
+#[tauri::command]
+pub fn render_version_markdown(app: AppHandle, id: i64) -> Result<String, String> {
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, id)?;
+    Ok(markdown::to_safe_html(&version.content))
+}
+
+/// Write a version as a standalone HTML document, ready to print or share. The title
+/// comes from the label (or the document's file name); `css` is added in a `<style>`.
+#[tauri::command]
+pub fn export_version_html(
+    app: AppHandle,
+    id: i64,
+    path: String,
+    css: Option<String>,
+) -> Result<(), String> {
+    let target = allowed_path(&app, &path)?;
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, id)?;
+
+    let title = version
+        .label
+        .clone()
+        .filter(|l| !l.trim().is_empty())
+        .or_else(|| {
+            Path::new(&version.document_path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| format!("Version {}", version.id));
+    let document = html_document(&title, css.as_deref(), &version.content);
+    std::fs::write(&target, document).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// A standalone HTML page rendering `content`, with an escaped title
+fn html_document(title: &str, css: Option<&str>, content: &str) -> String {
+    // Keep custom CSS from closing the style element early
+    let style = css
+        .map(|css| format!("<style>\n{}\n</style>\n", css.replace("</", "<\\/")))
+        .unwrap_or_default();
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n{}</head>\n<body>\n{}</body>\n</html>\n",
+        text::escape_html(title.trim()),
+        style,
+        markdown::to_safe_html(content)
+    )
+}
+
+/// Save a text file from disk as a new version of `document_path`. Files that
+/// don't look like text, or aren't UTF-8, are rejected.
+#[tauri::command]
+pub fn import_file_as_version(
+    app: AppHandle,
+    path: String,
+    document_path: String,
+    label: Option<String>,
+) -> Result<Version, String> {
+    let source = allowed_path(&app, &path)?;
+    let bytes = std::fs::read(&source).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    if !text::looks_like_text(&bytes) {
+        return Err(format!(
+            "Validation: {} does not look like a text file",
+            path
+        ));
+    }
+    let content =
+        String::from_utf8(bytes).map_err(|_| format!("Validation: {} is not valid UTF-8", path))?;
+    create_version(app, document_path, content, label, None, None, None)
+}
+
+/// Import a large text file as a new version of `document_path`, reading it
+/// `chunk_bytes` at a time on a blocking thread and emitting `import-progress`
+/// after each read. Files over the import limit are rejected before reading. If
+/// the document's latest version already has the same content, that version is
+/// returned and nothing is saved.
+#[tauri::command]
+pub async fn import_large_file(
+    app: AppHandle,
+    path: String,
+    document_path: String,
+    label: Option<String>,
+    chunk_bytes: usize,
+) -> Result<Version, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        import_in_chunks(app, &path, document_path, label, chunk_bytes)
+    })
+    .await
+    .map_err(|e| format!("Import failed: {}", e))?
+}
+
+fn import_in_chunks(
+    app: AppHandle,
+    path: &str,
+    document_path: String,
+    label: Option<String>,
+    chunk_bytes: usize,
+) -> Result<Version, String> {
+    let source = allowed_path(&app, path)?;
+    let mut file = File::open(&source).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let total_bytes = file
+        .metadata()
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?
+        .len();
+    let limit = max_import_bytes(&app);
+    if total_bytes > limit {
+        return Err(format!(
+            "TooLarge: {} is {} bytes, import limit is {} bytes",
+            path, total_bytes, limit
+        ));
+    }
+
+    let mut bytes = Vec::with_capacity(total_bytes as usize);
+    let mut chunk = vec![0; chunk_bytes.clamp(MIN_IMPORT_CHUNK, MAX_IMPORT_CHUNK)];
+    loop {
+        let read = file
+            .read(&mut chunk)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if read == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+        // The file may have grown since it was measured
+        if bytes.len() as u64 > limit {
+            return Err(format!(
+                "TooLarge: {} is over the import limit of {} bytes",
+                path, limit
+            ));
+        }
+        let bytes_read = bytes.len() as u64;
+        let percent = (bytes_read * 100 / total_bytes.max(bytes_read).max(1)) as u8;
+        let _ = app.emit(
+            "import-progress",
+            ImportProgress {
+                path,
+                bytes_read,
+                total_bytes,
+                percent,
+            },
+        );
+    }
+
+    if !text::looks_like_text(&bytes) {
+        return Err(format!(
+            "Validation: {} does not look like a text file",
+            path
+        ));
+    }
+    let content =
+        String::from_utf8(bytes).map_err(|_| format!("Validation: {} is not valid UTF-8", path))?;
+
+    {
+        let conn = db::open(&app)?;
+        if let Some(latest_id) = latest_version_id(&conn, &document_path)? {
+            let latest = load_version(&conn, latest_id)?;
+            let same = latest.content_hash == db::content_hash(&content)
+                || (content.contains('\r')
+                    && latest.content_hash == db::content_hash(&normalize_line_endings(&content)));
+            if same {
+                return Ok(latest);
+            }
+        }
+    }
+    create_version(app, document_path, content, label, None, None, None)
+}
+
+fn max_import_bytes(app: &AppHandle) -> u64 {
+    settings::get(app, MAX_IMPORT_BYTES_KEY).unwrap_or(DEFAULT_MAX_IMPORT_BYTES)
+}
+
+/// Set the largest file `import_large_file` will read
+#[tauri::command]
+pub fn set_max_import_bytes(app: AppHandle, n: u64) -> Result<(), String> {
+    if n == 0 {
+        return Err("Validation: limit must be greater than 0".to_string());
+    }
+    settings::set(&app, MAX_IMPORT_BYTES_KEY, n)
+}
+
+/// Quote a string as a DOT ID: backslashes and quotes escaped, newlines as `\n`
+fn dot_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Write the version lineage of every document as a Graphviz DOT file: one cluster
+/// per document, nodes labeled with id, label and UTC save time, and an edge from
+/// each parent to its children. Render with e.g. `dot -Tsvg`.
+#[tauri::command]
+pub fn export_graph_dot(app: AppHandle, path: String) -> Result<(), String> {
+    let target = allowed_path(&app, &path)?;
+    let conn = db::open(&app)?;
+    let mut dot = String::from("digraph versions {\n  rankdir=LR;\n  node [shape=box];\n");
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, document_path, checkpoint_name, timestamp FROM versions
+             ORDER BY document_path, id",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    let mut current_document: Option<String> = None;
+    let mut clusters = 0;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let id: i64 = row.get(0).map_err(|e| e.to_string())?;
+        let document_path: String = row.get(1).map_err(|e| e.to_string())?;
+        let label: Option<String> = row.get(2).map_err(|e| e.to_string())?;
+        let timestamp: i64 = row.get(3).map_err(|e| e.to_string())?;
+
+        if current_document.as_deref() != Some(document_path.as_str()) {
+            if current_document.is_some() {
+                dot.push_str("  }\n");
+            }
+            dot.push_str(&format!(
+                "  subgraph cluster_{} {{\n    label={};\n",
+                clusters,
+                dot_string(&document_path)
+            ));
+            clusters += 1;
+            current_document = Some(document_path);
+        }
+        let time = Utc
+            .timestamp_millis_opt(timestamp)
+            .single()
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| timestamp.to_string());
+        let text = match label {
+            Some(label) => format!("#{}\n{}\n{}", id, label, time),
+            None => format!("#{}\n{}", id, time),
+        };
+        dot.push_str(&format!("    v{} [label={}];\n", id, dot_string(&text)));
+    }
+    if current_document.is_some() {
+        dot.push_str("  }\n");
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT parent_version_id, id FROM versions
+             WHERE parent_version_id IS NOT NULL ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let parent: i64 = row.get(0).map_err(|e| e.to_string())?;
+        let child: i64 = row.get(1).map_err(|e| e.to_string())?;
+        dot.push_str(&format!("  v{} -> v{};\n", parent, child));
+    }
+    dot.push_str("}\n");
+
+    std::fs::write(&target, dot).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// Import a file's history from a local git repository as versions, one per commit
+/// that changed it, oldest first along the first-parent history of `HEAD`. Each
+/// takes the commit's summary as its label and commit time as its timestamp, and
+/// is parented on the one before (the first on the document's latest version).
+/// `file_path` is relative to the repository root and becomes the document path.
+/// Commits where the file is missing, not text, or identical to the version before
+/// are skipped. Returns how many versions were created.
+#[tauri::command]
+pub fn import_from_git(
+    app: AppHandle,
+    repo_path: String,
+    file_path: String,
+) -> Result<usize, String> {
+    let root = allowed_path(&app, &repo_path)?;
+    let repo = git2::Repository::open(&root)
+        .map_err(|e| format!("Validation: {} is not a git repository: {}", repo_path, e))?;
+    let git_error = |e: git2::Error| format!("Failed to read git history: {}", e);
+
+    let mut walk = repo.revwalk().map_err(git_error)?;
+    walk.push_head().map_err(git_error)?;
+    walk.simplify_first_parent().map_err(git_error)?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)
+        .map_err(git_error)?;
+
+    let mut conn = db::open(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut parent = latest_version_id(&tx, &file_path)?;
+    let mut previous_blob: Option<git2::Oid> = None;
+    let mut previous_hash = match parent {
+        Some(id) => Some(load_version(&tx, id)?.content_hash),
+        None => None,
+    };
+    let mut imported = 0;
+    for oid in walk {
+        let commit = repo
+            .find_commit(oid.map_err(git_error)?)
+            .map_err(git_error)?;
+        let tree = commit.tree().map_err(git_error)?;
+        let Ok(entry) = tree.get_path(Path::new(&file_path)) else {
+            continue;
+        };
+        if previous_blob == Some(entry.id()) {
+            continue;
+        }
+        let Ok(blob) = repo.find_blob(entry.id()) else {
+            continue;
+        };
+        previous_blob = Some(entry.id());
+        if !text::looks_like_text(blob.content()) {
+            continue;
+        }
+        let Ok(content) = std::str::from_utf8(blob.content()) else {
+            continue;
+        };
+        let hash = db::content_hash(content);
+        if previous_hash.as_ref() == Some(&hash) {
+            continue;
+        }
+        previous_hash = Some(hash);
+        let label = commit.summary().map(str::trim).filter(|s| !s.is_empty());
+        let id = insert_version_at(
+            &tx,
+            &file_path,
+            content,
+            label,
+            parent,
+            commit.time().seconds() * 1000,
+        )?;
+        parent = Some(id);
+        imported += 1;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_document_escapes_title_and_css() {
+        let document = html_document(
+            " </title><script>alert(1)</script> ",
+            Some("body { color: red } </style><script>x()</script>"),
+            "# Hi",
+        );
+        assert!(
+            document.contains("<title>&lt;/title&gt;&lt;script&gt;alert(1)&lt;/script&gt;</title>"),
+            "{}",
+            document
+        );
+        assert_eq!(document.matches("</style>").count(), 1, "{}", document);
+        assert!(!document.contains("</script>"), "{}", document);
+        assert!(document.contains("<h1>Hi</h1>"), "{}", document);
+    }
+}
@@ -0,0 +1,836 @@
+use chrono::{Local, TimeZone};
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::cancel::CancelRegistry;
+use crate::commands::tags::tagged_version;
+use crate::commands::versions::{
+    ancestor_chain, content_from_row, latest_version_id, load_version, version_from_row, Version,
+    CONTENT_COLUMNS, VERSION_COLUMNS,
+};
+use crate::diff::{self, DiffHunk, DiffSegment, DiffStats, ReplayOp, SideBySideRow};
+use crate::{db, settings};
+
+/// Store key and default for the largest version `diff_versions` diffs synchronously
+pub(crate) const DIFF_SIZE_THRESHOLD_KEY: &str = "diff_size_threshold";
+pub(crate) const DEFAULT_DIFF_SIZE_THRESHOLD: usize = 1024 * 1024;
+
+/// Saves further apart than this start a new writing session
+const SESSION_GAP_MS: i64 = 30 * 60 * 1000;
+/// Sessions reported by `range_change_summary`, most active first
+const TOP_SESSIONS: usize = 3;
+/// Most frames `replay_data` returns
+const MAX_REPLAY_STEPS: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryDiff {
+    request_id: String,
+    index: usize,
+    from_id: i64,
+    to_id: i64,
+    stats: DiffStats,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryDiffsDone {
+    request_id: String,
+    completed: usize,
+    cancelled: bool,
+    error: Option<String>,
+}
+
+/// Compute diff stats for each consecutive pair in `version_ids` on a background thread.
+/// Emits `history-diff` per pair and a final `history-diffs-done`.
+/// Cancel with `cancel_request(request_id)`.
+#[tauri::command]
+pub fn compute_history_diffs(
+    app: AppHandle,
+    registry: State<CancelRegistry>,
+    request_id: String,
+    version_ids: Vec<i64>,
+) -> Result<(), String> {
+    let cancelled = registry.register(&request_id);
+
+    std::thread::spawn(move || {
+        let mut completed = 0;
+        let result = (|| -> Result<(), String> {
+            let conn = db::open(&app)?;
+            let mut previous: Option<(i64, String)> = None;
+
+            for &id in &version_ids {
+                if cancelled.load(Ordering::SeqCst) {
+                    break;
+                }
+                let content = load_version(&conn, id)?.content;
+                if let Some((from_id, old)) = previous.take() {
+                    let event = HistoryDiff {
+                        request_id: request_id.clone(),
+                        index: completed,
+                        from_id,
+                        to_id: id,
+                        stats: diff::diff_stats(&old, &content),
+                    };
+                    let _ = app.emit("history-diff", event);
+                    completed += 1;
+                }
+                previous = Some((id, content));
+            }
+            Ok(())
+        })();
+
+        app.state::<CancelRegistry>().finish(&request_id);
+        let _ = app.emit(
+            "history-diffs-done",
+            HistoryDiffsDone {
+                request_id,
+                completed,
+                cancelled: cancelled.load(Ordering::SeqCst),
+                error: result.err(),
+            },
+        );
+    });
+
+    Ok(())
+}
+
+/// The last `window` versions of a version's document, ending at that version, oldest first
+pub fn recent_history(
+    conn: &rusqlite::Connection,
+    id: i64,
+    window: usize,
+) -> Result<Vec<Version>, String> {
+    let target = load_version(conn, id)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM versions WHERE document_path = ?1 AND id <= ?2
+             ORDER BY id DESC LIMIT ?3",
+            VERSION_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut versions = stmt
+        .query_map(
+            rusqlite::params![target.document_path, id, window as i64],
+            version_from_row,
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    versions.reverse();
+    Ok(versions)
+}
+
+/// A run of lines in the target version that was rewritten across recent history
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChurnRegion {
+    /// 1-based, inclusive line range in the target version
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Most times any line in the region was rewritten
+    pub edits: usize,
+    pub preview: String,
+}
+
+/// Hotspots in a version: line regions rewritten most often over the last
+/// `window` versions, ranked by edit frequency
+#[tauri::command]
+pub fn churn_report(app: AppHandle, id: i64, window: usize) -> Result<Vec<ChurnRegion>, String> {
+    let conn = db::open(&app)?;
+    let history = recent_history(&conn, id, window.max(2))?;
+    let Some(first) = history.first() else {
+        return Ok(Vec::new());
+    };
+
+    let mut counts = vec![0; diff::line_count(&first.content)];
+    for pair in history.windows(2) {
+        counts = diff::carry_line_churn(&counts, &pair[0].content, &pair[1].content);
+    }
+
+    let lines: Vec<&str> = history[history.len() - 1]
+        .content
+        .split_inclusive('\n')
+        .collect();
+    let mut regions: Vec<ChurnRegion> = Vec::new();
+    for (i, &edits) in counts.iter().enumerate() {
+        if edits == 0 {
+            continue;
+        }
+        match regions.last_mut() {
+            Some(region) if region.end_line == i => {
+                region.end_line = i + 1;
+                region.edits = region.edits.max(edits);
+            }
+            _ => regions.push(ChurnRegion {
+                start_line: i + 1,
+                end_line: i + 1,
+                edits,
+                preview: lines
+                    .get(i)
+                    .map(|l| l.trim().to_string())
+                    .unwrap_or_default(),
+            }),
+        }
+    }
+
+    regions.sort_by(|a, b| b.edits.cmp(&a.edits).then(a.start_line.cmp(&b.start_line)));
+    Ok(regions)
+}
+
+/// Diff a version against its recorded parent rather than the previous id, so
+/// branched history shows the right changes. Versions without a parent diff
+/// against empty content.
+#[tauri::command]
+pub fn diff_from_parent(app: AppHandle, id: i64) -> Result<Vec<DiffHunk>, String> {
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, id)?;
+    let parent_content = match version.parent_version_id {
+        Some(parent_id) => load_version(&conn, parent_id)?.content,
+        None => String::new(),
+    };
+    Ok(diff::diff_hunks(&parent_content, &version.content))
+}
+
+/// Result of `diff_versions`: the hunks, or notice that they'll arrive as a
+/// `version-diff-ready` event because the inputs are over the size threshold
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum DiffOutcome {
+    Ready {
+        hunks: Vec<DiffHunk>,
+    },
+    #[serde(rename_all = "camelCase")]
+    TooLargeForSync {
+        request_id: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionDiffReady {
+    request_id: String,
+    from_id: i64,
+    to_id: i64,
+    hunks: Vec<DiffHunk>,
+}
+
+/// Diff two versions. If either is larger than the diff size threshold the diff runs
+/// on a background thread and `TooLargeForSync` is returned; the hunks follow in a
+/// `version-diff-ready` event carrying `request_id`.
+#[tauri::command]
+pub fn diff_versions(
+    app: AppHandle,
+    from_id: i64,
+    to_id: i64,
+    request_id: String,
+) -> Result<DiffOutcome, String> {
+    let conn = db::open(&app)?;
+    let from = load_version(&conn, from_id)?;
+    let to = load_version(&conn, to_id)?;
+
+    let threshold = diff_size_threshold(&app);
+    Ok(diff_or_defer(
+        (from_id, from.content),
+        (to_id, to.content),
+        threshold,
+        request_id,
+        move |ready| {
+            let _ = app.emit("version-diff-ready", ready);
+        },
+    ))
+}
+
+/// Diff inline when both sides are at most `threshold` bytes, otherwise on a
+/// background thread that hands the hunks to `deliver`
+fn diff_or_defer(
+    (from_id, from): (i64, String),
+    (to_id, to): (i64, String),
+    threshold: usize,
+    request_id: String,
+    deliver: impl FnOnce(VersionDiffReady) + Send + 'static,
+) -> DiffOutcome {
+    if from.len() <= threshold && to.len() <= threshold {
+        return DiffOutcome::Ready {
+            hunks: diff::diff_hunks(&from, &to),
+        };
+    }
+
+    let id = request_id.clone();
+    std::thread::spawn(move || {
+        let hunks = diff::diff_hunks(&from, &to);
+        deliver(VersionDiffReady {
+            request_id: id,
+            from_id,
+            to_id,
+            hunks,
+        });
+    });
+    DiffOutcome::TooLargeForSync { request_id }
+}
+
+fn diff_size_threshold(app: &AppHandle) -> usize {
+    settings::get(app, DIFF_SIZE_THRESHOLD_KEY).unwrap_or(DEFAULT_DIFF_SIZE_THRESHOLD)
+}
+
+/// Set the byte size above which `diff_versions` moves off the calling thread
+#[tauri::command]
+pub fn set_diff_size_threshold(app: AppHandle, bytes: usize) -> Result<(), String> {
+    if bytes == 0 {
+        return Err("Validation: threshold must be greater than 0".to_string());
+    }
+    settings::set(&app, DIFF_SIZE_THRESHOLD_KEY, bytes)
+}
+
+/// Two-column diff of two versions as aligned rows. Pass `offset`/`limit` to page
+/// through large files a window of rows at a time.
+#[tauri::command]
+pub fn diff_versions_side_by_side(
+    app: AppHandle,
+    from_id: i64,
+    to_id: i64,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<Vec<SideBySideRow>, String> {
+    let conn = db::open(&app)?;
+    let from = load_version(&conn, from_id)?;
+    let to = load_version(&conn, to_id)?;
+    Ok(diff::side_by_side(&from.content, &to.content)
+        .into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect())
+}
+
+/// A diff at the granularity the caller asked for
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "granularity", rename_all = "camelCase")]
+pub enum GranularDiff {
+    Line { hunks: Vec<DiffHunk> },
+    Word { segments: Vec<DiffSegment> },
+}
+
+/// Diff any stored version against the editor's unsaved buffer, at `line` or
+/// `word` granularity, to show changes since the version the user opened
+#[tauri::command]
+pub fn diff_against_version(
+    app: AppHandle,
+    id: i64,
+    working: String,
+    granularity: String,
+) -> Result<GranularDiff, String> {
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, id)?;
+    match granularity.as_str() {
+        "line" => Ok(GranularDiff::Line {
+            hunks: diff::diff_hunks(&version.content, &working),
+        }),
+        "word" => Ok(GranularDiff::Word {
+            segments: diff::word_diff(&version.content, &working),
+        }),
+        other => Err(format!(
+            "Validation: unknown granularity '{}' (expected line or word)",
+            other
+        )),
+    }
+}
+
+/// How two documents' histories diverge
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocCompare {
+    pub a_versions: usize,
+    pub b_versions: usize,
+    pub a_latest_id: Option<i64>,
+    pub b_latest_id: Option<i64>,
+    /// Diff from `a`'s latest content to `b`'s
+    pub latest_stats: DiffStats,
+    pub latest_hunks: Vec<DiffHunk>,
+    /// Labels and tags present in both histories, sorted
+    pub shared_labels: Vec<String>,
+    pub shared_tags: Vec<String>,
+}
+
+/// Compare two documents, e.g. a fork and its original: version counts, the diff
+/// between their latest versions, and labels/tags they have in common
+#[tauri::command]
+pub fn compare_documents(app: AppHandle, a: String, b: String) -> Result<DocCompare, String> {
+    let conn = db::open(&app)?;
+
+    let count = |document_path: &str| -> Result<usize, String> {
+        conn.query_row(
+            "SELECT COUNT(*) FROM versions WHERE document_path = ?1",
+            [document_path],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|n| n as usize)
+        .map_err(|e| e.to_string())
+    };
+    let strings = |sql: &str| -> Result<Vec<String>, String> {
+        let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+        let values = stmt
+            .query_map([&a, &b], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(values)
+    };
+    let latest_content = |document_path: &str| -> Result<(Option<i64>, String), String> {
+        match latest_version_id(&conn, document_path)? {
+            Some(id) => Ok((Some(id), load_version(&conn, id)?.content)),
+            None => Ok((None, String::new())),
+        }
+    };
+
+    let a_versions = count(&a)?;
+    let b_versions = count(&b)?;
+    if a_versions == 0 && b_versions == 0 {
+        return Err("NotFound: neither document has any versions".to_string());
+    }
+    let (a_latest_id, a_content) = latest_content(&a)?;
+    let (b_latest_id, b_content) = latest_content(&b)?;
+
+    Ok(DocCompare {
+        a_versions,
+        b_versions,
+        a_latest_id,
+        b_latest_id,
+        latest_stats: diff::diff_stats(&a_content, &b_content),
+        latest_hunks: diff::diff_hunks(&a_content, &b_content),
+        shared_labels: strings(
+            "SELECT checkpoint_name FROM versions WHERE document_path = ?1
+               AND checkpoint_name IS NOT NULL
+             INTERSECT
+             SELECT checkpoint_name FROM versions WHERE document_path = ?2
+             ORDER BY 1",
+        )?,
+        shared_tags: strings(
+            "SELECT t.tag FROM version_tags t JOIN versions v ON v.id = t.version_id
+               WHERE v.document_path = ?1
+             INTERSECT
+             SELECT t.tag FROM version_tags t JOIN versions v ON v.id = t.version_id
+               WHERE v.document_path = ?2
+             ORDER BY 1",
+        )?,
+    })
+}
+
+/// A run of saves with no gap longer than 30 minutes
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WritingSession {
+    pub start: i64,
+    pub end: i64,
+    pub saves: usize,
+    /// Words added plus removed across the session's saves
+    pub words_changed: usize,
+}
+
+/// Net versus cumulative change over a span of versions
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeSummary {
+    /// Saves strictly between `from_id` and `to_id`
+    pub intermediate_saves: usize,
+    /// Sum of every consecutive diff in the range, churn included
+    pub cumulative: DiffStats,
+    /// The single diff from `from_id` to `to_id`
+    pub net: DiffStats,
+    pub top_sessions: Vec<WritingSession>,
+}
+
+/// How much a document changed between two of its versions: the net diff, the sum
+/// of every save along the way, and the busiest writing sessions in between
+#[tauri::command]
+pub fn range_change_summary(
+    app: AppHandle,
+    from_id: i64,
+    to_id: i64,
+) -> Result<RangeSummary, String> {
+    let conn = db::open(&app)?;
+    let from = load_version(&conn, from_id)?;
+    let to = load_version(&conn, to_id)?;
+    if from.document_path != to.document_path {
+        return Err("Validation: versions belong to different documents".to_string());
+    }
+    if from_id >= to_id {
+        return Err("Validation: from_id must be older than to_id".to_string());
+    }
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {}, timestamp FROM versions
+             WHERE document_path = ?1 AND id > ?2 AND id <= ?3 ORDER BY id",
+            CONTENT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+        .query(rusqlite::params![from.document_path, from_id, to_id])
+        .map_err(|e| e.to_string())?;
+
+    let mut cumulative = DiffStats::default();
+    let mut sessions: Vec<WritingSession> = Vec::new();
+    let mut saves = 0;
+    let mut previous = from.content.clone();
+    let mut last_timestamp = from.timestamp;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let content = content_from_row(row, 0).map_err(|e| e.to_string())?;
+        let timestamp: i64 = row.get(3).map_err(|e| e.to_string())?;
+        saves += 1;
+
+        let stats = diff::diff_stats(&previous, &content);
+        cumulative.lines_added += stats.lines_added;
+        cumulative.lines_removed += stats.lines_removed;
+        cumulative.words_added += stats.words_added;
+        cumulative.words_removed += stats.words_removed;
+
+        let words_changed = stats.words_added + stats.words_removed;
+        match sessions.last_mut() {
+            Some(session) if timestamp - last_timestamp <= SESSION_GAP_MS => {
+                session.end = timestamp;
+                session.saves += 1;
+                session.words_changed += words_changed;
+            }
+            _ => sessions.push(WritingSession {
+                start: timestamp,
+                end: timestamp,
+                saves: 1,
+                words_changed,
+            }),
+        }
+        previous = content;
+        last_timestamp = timestamp;
+    }
+
+    sessions.sort_by(|a, b| {
+        b.words_changed
+            .cmp(&a.words_changed)
+            .then(a.start.cmp(&b.start))
+    });
+    sessions.truncate(TOP_SESSIONS);
+
+    Ok(RangeSummary {
+        intermediate_saves: saves.saturating_sub(1),
+        cumulative,
+        net: diff::diff_stats(&from.content, &to.content),
+        top_sessions: sessions,
+    })
+}
+
+/// Active editing time over a span of saves
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditingTime {
+    /// Sum of the gaps between consecutive saves, breaks excluded
+    pub active_ms: i64,
+    /// Runs of saves separated by breaks
+    pub sessions: usize,
+    pub saves: usize,
+}
+
+/// Rough editing time between two versions of a document, from save timestamps.
+/// Gaps between consecutive saves count as active time unless longer than
+/// `idle_gap_secs`, which are treated as breaks between sessions.
+#[tauri::command]
+pub fn editing_time(
+    app: AppHandle,
+    from_id: i64,
+    to_id: i64,
+    idle_gap_secs: u64,
+) -> Result<EditingTime, String> {
+    let conn = db::open(&app)?;
+    let from = load_version(&conn, from_id)?;
+    let to = load_version(&conn, to_id)?;
+    if from.document_path != to.document_path {
+        return Err("Validation: versions belong to different documents".to_string());
+    }
+    if from_id > to_id {
+        return Err("Validation: from_id must not be newer than to_id".to_string());
+    }
+    let idle_gap_ms = i64::try_from(idle_gap_secs.saturating_mul(1000)).unwrap_or(i64::MAX);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp FROM versions
+             WHERE document_path = ?1 AND id >= ?2 AND id <= ?3 ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let timestamps = stmt
+        .query_map(
+            rusqlite::params![from.document_path, from_id, to_id],
+            |row| row.get::<_, i64>(0),
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut time = EditingTime {
+        active_ms: 0,
+        sessions: usize::from(!timestamps.is_empty()),
+        saves: timestamps.len(),
+    };
+    for pair in timestamps.windows(2) {
+        let gap = (pair[1] - pair[0]).max(0);
+        if gap > idle_gap_ms {
+            time.sessions += 1;
+        } else {
+            time.active_ms += gap;
+        }
+    }
+    Ok(time)
+}
+
+fn format_local(timestamp: i64) -> String {
+    Local
+        .timestamp_millis_opt(timestamp)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Markdown release notes between the versions tagged `from_tag` and `to_tag`:
+/// overall diff stats, the labeled saves in between, open review comments on
+/// those versions, and the full diff
+#[tauri::command]
+pub fn changelog_between_tags(
+    app: AppHandle,
+    from_tag: String,
+    to_tag: String,
+) -> Result<String, String> {
+    let conn = db::open(&app)?;
+    let from = load_version(&conn, tagged_version(&conn, &from_tag)?)?;
+    let to = load_version(&conn, tagged_version(&conn, &to_tag)?)?;
+    if from.document_path != to.document_path {
+        return Err("Validation: the tagged versions belong to different documents".to_string());
+    }
+    if from.id >= to.id {
+        return Err(format!(
+            "Validation: '{}' must tag an older version than '{}'",
+            from_tag, to_tag
+        ));
+    }
+
+    let stats = diff::diff_stats(&from.content, &to.content);
+    let mut out = format!(
+        "# Changes from {} to {}\n\n",
+        from_tag.trim(),
+        to_tag.trim()
+    );
+    out.push_str(&format!(
+        "{} → {} ({}), +{} / -{} words, +{} / -{} lines\n",
+        format_local(from.timestamp),
+        format_local(to.timestamp),
+        from.document_path,
+        stats.words_added,
+        stats.words_removed,
+        stats.lines_added,
+        stats.lines_removed
+    ));
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, checkpoint_name, timestamp FROM versions
+             WHERE document_path = ?1 AND id > ?2 AND id <= ?3
+               AND checkpoint_name IS NOT NULL AND checkpoint_name != ''
+             ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let milestones = stmt
+        .query_map(
+            rusqlite::params![from.document_path, from.id, to.id],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    if !milestones.is_empty() {
+        out.push_str("\n## Versions\n\n");
+        for (id, label, timestamp) in milestones {
+            out.push_str(&format!(
+                "- {} — {} (#{})\n",
+                format_local(timestamp),
+                label,
+                id
+            ));
+        }
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT c.comment_type, c.text FROM comments c JOIN versions v ON v.id = c.version_id
+             WHERE v.document_path = ?1 AND v.id > ?2 AND v.id <= ?3
+               AND c.parent_id IS NULL AND c.resolved_at IS NULL AND c.deleted_at IS NULL
+             ORDER BY c.created_at, c.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let comments = stmt
+        .query_map(
+            rusqlite::params![from.document_path, from.id, to.id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    if !comments.is_empty() {
+        out.push_str("\n## Open comments\n\n");
+        for (comment_type, text) in comments {
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            out.push_str(&format!("- **{}**: {}\n", comment_type, text));
+        }
+    }
+
+    let unified = diff::unified_diff(&from.content, &to.content);
+    if !unified.is_empty() {
+        out.push_str("\n## Diff\n\n```diff\n");
+        out.push_str(&unified);
+        if !unified.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str("```\n");
+    }
+    Ok(out)
+}
+
+/// The edits that produced one version in a replay
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayFrame {
+    pub version_id: i64,
+    pub timestamp: i64,
+    pub label: Option<String>,
+    pub ops: Vec<ReplayOp>,
+}
+
+/// Frames for replaying how a version was written: the version and its nearest
+/// ancestors, `steps` frames at most, oldest first. `steps` is capped at 500. The
+/// first frame builds its version from empty text; each later one applies the diff
+/// from the frame before.
+#[tauri::command]
+pub fn replay_data(app: AppHandle, id: i64, steps: usize) -> Result<Vec<ReplayFrame>, String> {
+    let conn = db::open(&app)?;
+    let mut chain = ancestor_chain(&conn, id)?;
+    chain.truncate(steps.clamp(1, MAX_REPLAY_STEPS));
+    chain.reverse();
+
+    let mut previous = String::new();
+    let mut frames = Vec::with_capacity(chain.len());
+    for version_id in chain {
+        let version = load_version(&conn, version_id)?;
+        frames.push(ReplayFrame {
+            version_id,
+            timestamp: version.timestamp,
+            label: version.label,
+            ops: diff::replay_ops(&previous, &version.content),
+        });
+        previous = version.content;
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn outcome_at(
+        size: usize,
+        threshold: usize,
+    ) -> (DiffOutcome, mpsc::Receiver<VersionDiffReady>) {
+        let (tx, rx) = mpsc::channel();
+        // One line each, `size` bytes including the newline
+        let from = format!("{}\n", "a".repeat(size - 1));
+        let to = format!("{}\n", "b".repeat(size - 1));
+        let outcome = diff_or_defer(
+            (1, from),
+            (2, to),
+            threshold,
+            "req-1".to_string(),
+            move |ready| {
+                let _ = tx.send(ready);
+            },
+        );
+        (outcome, rx)
+    }
+
+    #[test]
+    fn diff_below_threshold_is_returned_inline() {
+        let (outcome, rx) = outcome_at(63, 64);
+        match outcome {
+            DiffOutcome::Ready { hunks } => assert!(!hunks.is_empty()),
+            other => panic!("expected Ready, got {:?}", other),
+        }
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn diff_at_threshold_is_returned_inline() {
+        let (outcome, rx) = outcome_at(64, 64);
+        assert!(
+            matches!(outcome, DiffOutcome::Ready { .. }),
+            "{:?}",
+            outcome
+        );
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    /// Benchmark at the boundary: the largest diff kept on the calling thread
+    /// has to finish quickly enough not to freeze it
+    #[test]
+    fn diff_at_default_threshold_stays_interactive() {
+        let mut from = String::new();
+        let mut line = 0;
+        while from.len() < DEFAULT_DIFF_SIZE_THRESHOLD {
+            from.push_str(&format!("line {} of a long document\n", line));
+            line += 1;
+        }
+        from.truncate(DEFAULT_DIFF_SIZE_THRESHOLD);
+        let to = ["line 10 ", "line 5000 ", "line 20000 "]
+            .iter()
+            .fold(from.clone(), |text, marker| {
+                text.replacen(marker, &marker.to_uppercase(), 1)
+            });
+        assert_eq!(to.len(), DEFAULT_DIFF_SIZE_THRESHOLD);
+
+        let started = std::time::Instant::now();
+        let outcome = diff_or_defer(
+            (1, from),
+            (2, to),
+            DEFAULT_DIFF_SIZE_THRESHOLD,
+            "bench".to_string(),
+            |_| panic!("a diff at the threshold must not be deferred"),
+        );
+        let elapsed = started.elapsed();
+        match outcome {
+            DiffOutcome::Ready { hunks } => assert_eq!(hunks.len(), 3),
+            other => panic!("expected Ready, got {:?}", other),
+        }
+        assert!(
+            elapsed < Duration::from_secs(2),
+            "diff at the threshold took {:?}",
+            elapsed
+        );
+    }
+
+    #[test]
+    fn diff_over_threshold_arrives_as_event() {
+        let (outcome, rx) = outcome_at(65, 64);
+        match outcome {
+            DiffOutcome::TooLargeForSync { request_id } => assert_eq!(request_id, "req-1"),
+            other => panic!("expected TooLargeForSync, got {:?}", other),
+        }
+        let ready = rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("version-diff-ready payload");
+        assert_eq!(ready.request_id, "req-1");
+        assert_eq!((ready.from_id, ready.to_id), (1, 2));
+        assert!(!ready.hunks.is_empty());
+    }
+}
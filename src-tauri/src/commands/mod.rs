@@ -1,6 +1,79 @@
-// AI commands disabled until keyring API is fixed
-// pub mod ai;
-// pub use ai::*;
+pub mod ai;
+pub use ai::*;
+
+pub mod ai_draft;
+pub use ai_draft::*;
+
+pub mod ai_queue;
+pub use ai_queue::*;
+
+pub mod ai_summary;
+pub use ai_summary::*;
+
+pub mod ai_tags;
+pub use ai_tags::*;
+
+pub mod ai_usage;
+pub use ai_usage::*;
+
+pub mod analysis;
+pub use analysis::*;
 
 pub mod debug_bridge;
 pub use debug_bridge::*;
+
+pub mod embeddings;
+pub use embeddings::*;
+
+pub mod versions;
+pub use versions::*;
+
+pub mod attachments;
+pub use attachments::*;
+
+pub mod backup;
+pub use backup::*;
+
+pub mod background_autosave;
+pub use background_autosave::*;
+
+pub mod comments;
+pub use comments::*;
+
+pub mod config;
+pub use config::*;
+
+pub mod cancel;
+pub use cancel::*;
+
+pub mod history;
+pub use history::*;
+
+pub mod database;
+pub use database::*;
+
+pub mod export;
+pub use export::*;
+
+pub mod version_meta;
+pub use version_meta::*;
+
+pub mod view_state;
+pub use view_state::*;
+
+pub mod search;
+pub use search::*;
+
+pub mod tags;
+pub use tags::*;
+
+pub mod warmup;
+pub use warmup::*;
+
+pub mod workspace;
+pub use workspace::*;
+
+#[cfg(debug_assertions)]
+pub mod diagnostics;
+#[cfg(debug_assertions)]
+pub use diagnostics::*;
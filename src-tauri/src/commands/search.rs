@@ -0,0 +1,119 @@
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::cancel::CancelRegistry;
+use crate::commands::versions::{load_version, version_from_row, VERSION_COLUMNS};
+use crate::secrets::{self, SecretFinding};
+use crate::{db, text};
+
+/// Byte ranges of every case-insensitive, non-overlapping match of `query` in a
+/// version, for highlighting hits in the editor
+#[tauri::command]
+pub fn search_version_positions(
+    app: AppHandle,
+    id: i64,
+    query: String,
+) -> Result<Vec<(usize, usize)>, String> {
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, id)?;
+    Ok(text::find_case_insensitive(&version.content, &query))
+}
+
+/// Likely secrets (API keys, tokens, private keys) in a version, with byte offsets,
+/// so the UI can warn before export. The content isn't modified.
+#[tauri::command]
+pub fn scan_version_for_secrets(app: AppHandle, id: i64) -> Result<Vec<SecretFinding>, String> {
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, id)?;
+    Ok(secrets::find_secrets(&version.content))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchHit {
+    request_id: String,
+    version_id: i64,
+    document_path: String,
+    label: Option<String>,
+    timestamp: i64,
+    positions: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchDone {
+    request_id: String,
+    scanned: usize,
+    hits: usize,
+    cancelled: bool,
+    error: Option<String>,
+}
+
+/// Case-insensitive search of every version, newest first, on a background thread.
+/// Emits `search-hit` per matching version and a final `search-done`.
+/// Cancel with `cancel_request(request_id)`.
+#[tauri::command]
+pub fn search_versions_stream(
+    app: AppHandle,
+    registry: State<CancelRegistry>,
+    query: String,
+    request_id: String,
+) -> Result<(), String> {
+    if query.trim().is_empty() {
+        return Err("Validation: query is empty".to_string());
+    }
+    let cancelled = registry.register(&request_id);
+
+    std::thread::spawn(move || {
+        let mut scanned = 0;
+        let mut hits = 0;
+        let result = (|| -> Result<(), String> {
+            let conn = db::open(&app)?;
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT {} FROM versions ORDER BY id DESC",
+                    VERSION_COLUMNS
+                ))
+                .map_err(|e| e.to_string())?;
+            let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+
+            while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+                if cancelled.load(Ordering::SeqCst) {
+                    break;
+                }
+                let version = version_from_row(row).map_err(|e| e.to_string())?;
+                scanned += 1;
+                let positions = text::find_case_insensitive(&version.content, &query);
+                if positions.is_empty() {
+                    continue;
+                }
+                let event = SearchHit {
+                    request_id: request_id.clone(),
+                    version_id: version.id,
+                    document_path: version.document_path,
+                    label: version.label,
+                    timestamp: version.timestamp,
+                    positions,
+                };
+                let _ = app.emit("search-hit", event);
+                hits += 1;
+            }
+            Ok(())
+        })();
+
+        app.state::<CancelRegistry>().finish(&request_id);
+        let _ = app.emit(
+            "search-done",
+            SearchDone {
+                request_id,
+                scanned,
+                hits,
+                cancelled: cancelled.load(Ordering::SeqCst),
+                error: result.err(),
+            },
+        );
+    });
+
+    Ok(())
+}
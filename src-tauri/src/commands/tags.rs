@@ -0,0 +1,181 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter};
+
+use crate::commands::versions::load_version;
+use crate::db;
+
+/// A tag and how many versions carry it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TagsChanged<'a> {
+    tag: &'a str,
+    version_ids: &'a [i64],
+}
+
+/// Canonical tag form: trimmed, lowercase, inner whitespace collapsed to `-`
+pub fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    if tag.is_empty() {
+        return Err("Validation: tag must not be empty".to_string());
+    }
+    Ok(tag)
+}
+
+/// Sorted tags of every tagged version
+pub fn tags_by_version(conn: &rusqlite::Connection) -> Result<HashMap<i64, Vec<String>>, String> {
+    let mut stmt = conn
+        .prepare("SELECT version_id, tag FROM version_tags ORDER BY version_id, tag")
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let version_id: i64 = row.get(0).map_err(|e| e.to_string())?;
+        let tag: String = row.get(1).map_err(|e| e.to_string())?;
+        tags.entry(version_id).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+/// Tags on one version, sorted
+pub fn version_tags(conn: &rusqlite::Connection, version_id: i64) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT tag FROM version_tags WHERE version_id = ?1 ORDER BY tag")
+        .map_err(|e| e.to_string())?;
+    let tags = stmt
+        .query_map([version_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(tags)
+}
+
+/// The newest version carrying a tag, failing with `NotFound` if none does
+pub fn tagged_version(conn: &rusqlite::Connection, tag: &str) -> Result<i64, String> {
+    let tag = normalize_tag(tag)?;
+    conn.query_row(
+        "SELECT MAX(version_id) FROM version_tags WHERE tag = ?1",
+        [&tag],
+        |row| row.get::<_, Option<i64>>(0),
+    )
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("NotFound: no version is tagged '{}'", tag))
+}
+
+/// Add a tag to a version. Returns false if it already had it.
+#[tauri::command]
+pub fn tag_version(app: AppHandle, version_id: i64, tag: String) -> Result<bool, String> {
+    let tag = normalize_tag(&tag)?;
+    let conn = db::open(&app)?;
+    load_version(&conn, version_id)?;
+    let inserted = conn
+        .execute(
+            "INSERT OR IGNORE INTO version_tags (version_id, tag, created_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![version_id, tag, db::now_ms()],
+        )
+        .map_err(|e| format!("Failed to tag version: {}", e))?;
+    Ok(inserted > 0)
+}
+
+/// Add a tag to many versions in one transaction, skipping those that already have
+/// it. Emits a single `tags-changed` event listing the newly tagged versions.
+/// Returns how many that was.
+#[tauri::command]
+pub fn tag_versions_bulk(
+    app: AppHandle,
+    version_ids: Vec<i64>,
+    tag: String,
+) -> Result<usize, String> {
+    let tag = normalize_tag(&tag)?;
+    let now = db::now_ms();
+    let mut conn = db::open(&app)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut tagged = Vec::new();
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT OR IGNORE INTO version_tags (version_id, tag, created_at) VALUES (?1, ?2, ?3)",
+            )
+            .map_err(|e| e.to_string())?;
+        for &version_id in &version_ids {
+            load_version(&tx, version_id)?;
+            let inserted = stmt
+                .execute(rusqlite::params![version_id, tag, now])
+                .map_err(|e| format!("Failed to tag version: {}", e))?;
+            if inserted > 0 {
+                tagged.push(version_id);
+            }
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    if !tagged.is_empty() {
+        let _ = app.emit(
+            "tags-changed",
+            TagsChanged {
+                tag: &tag,
+                version_ids: &tagged,
+            },
+        );
+    }
+    Ok(tagged.len())
+}
+
+/// Remove a tag from a version. Returns false if it wasn't there.
+#[tauri::command]
+pub fn untag_version(app: AppHandle, version_id: i64, tag: String) -> Result<bool, String> {
+    let tag = normalize_tag(&tag)?;
+    let conn = db::open(&app)?;
+    let removed = conn
+        .execute(
+            "DELETE FROM version_tags WHERE version_id = ?1 AND tag = ?2",
+            rusqlite::params![version_id, tag],
+        )
+        .map_err(|e| format!("Failed to untag version: {}", e))?;
+    Ok(removed > 0)
+}
+
+/// Tags on a version, sorted
+#[tauri::command]
+pub fn get_version_tags(app: AppHandle, version_id: i64) -> Result<Vec<String>, String> {
+    let conn = db::open(&app)?;
+    load_version(&conn, version_id)?;
+    version_tags(&conn, version_id)
+}
+
+/// Every tag in use with its version count, most used first, ties alphabetical.
+/// With `document_path`, only that document's versions are counted.
+#[tauri::command]
+pub fn list_tags(app: AppHandle, document_path: Option<String>) -> Result<Vec<TagCount>, String> {
+    let conn = db::open(&app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.tag, COUNT(*) FROM version_tags t
+             JOIN versions v ON v.id = t.version_id
+             WHERE ?1 IS NULL OR v.document_path = ?1
+             GROUP BY t.tag ORDER BY COUNT(*) DESC, t.tag",
+        )
+        .map_err(|e| e.to_string())?;
+    let tags = stmt
+        .query_map([&document_path], |row| {
+            Ok(TagCount {
+                tag: row.get(0)?,
+                count: row.get::<_, i64>(1)? as usize,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(tags)
+}
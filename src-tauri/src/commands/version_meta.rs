@@ -0,0 +1,26 @@
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db;
+
+/// Read a metadata value for a version
+pub fn get_meta(conn: &Connection, version_id: i64, key: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT value FROM version_meta WHERE version_id = ?1 AND key = ?2",
+        rusqlite::params![version_id, key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read version metadata: {}", e))
+}
+
+/// Insert or replace a metadata value for a version
+pub fn set_meta(conn: &Connection, version_id: i64, key: &str, value: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO version_meta (version_id, key, value, updated_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(version_id, key) DO UPDATE SET value = excluded.value,
+                                                     updated_at = excluded.updated_at",
+        rusqlite::params![version_id, key, value, db::now_ms()],
+    )
+    .map_err(|e| format!("Failed to write version metadata: {}", e))?;
+    Ok(())
+}
@@ -0,0 +1,1233 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rusqlite::{OptionalExtension, Row};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::tags::tags_by_version;
+use crate::commands::version_meta::set_meta;
+use crate::stats::{self, LatencyStats};
+use crate::{db, diff, secrets, settings, text};
+
+/// Recent `create_version` timings kept for `save_latency_stats`
+const SAVE_LATENCY_SAMPLES: usize = 200;
+
+/// Store key and default for the largest content a save may store
+pub(crate) const MAX_VERSION_BYTES_KEY: &str = "max_version_bytes";
+pub(crate) const DEFAULT_MAX_VERSION_BYTES: usize = 10 * 1024 * 1024;
+
+/// Store key: convert CRLF/CR to LF before saving (default on)
+pub(crate) const NORMALIZE_LINE_ENDINGS_KEY: &str = "normalize_line_endings";
+
+/// Store key and default for the change ratio past which an autosave is labeled
+pub(crate) const AUTO_LABEL_THRESHOLD_KEY: &str = "auto_label_threshold";
+pub(crate) const DEFAULT_AUTO_LABEL_THRESHOLD: f64 = 0.5;
+const MAJOR_EDIT_LABEL: &str = "major edit";
+
+/// Store key for the database size limit in bytes; unset means no limit
+pub(crate) const STORAGE_QUOTA_KEY: &str = "storage_quota_bytes";
+
+/// Store key: refuse saves whose content contains likely secrets (default off)
+pub(crate) const BLOCK_SECRETS_KEY: &str = "block_secrets_on_save";
+
+/// Store key for the durability of saves that don't ask for one
+pub(crate) const DEFAULT_DURABILITY_KEY: &str = "default_durability";
+
+/// Store key for the content new documents start from
+pub(crate) const DOCUMENT_TEMPLATE_KEY: &str = "document_template";
+
+/// `version_meta` key set on versions whose line endings were rewritten on save
+const LINE_ENDINGS_NORMALIZED_META: &str = "line_endings_normalized";
+
+/// Columns selected for a full `Version`, in the order `version_from_row` expects
+pub(crate) const VERSION_COLUMNS: &str = "id, document_path, content, compressed, \
+     compressed_content, checkpoint_name, timestamp, is_checkpoint, parent_version_id";
+
+/// What `content_from_row` reads, starting at the index it is given
+pub(crate) const CONTENT_COLUMNS: &str = "content, compressed, compressed_content";
+
+/// A single stored version with its full content
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Version {
+    pub id: i64,
+    pub document_path: String,
+    pub content: String,
+    pub label: Option<String>,
+    pub timestamp: i64,
+    pub is_checkpoint: bool,
+    pub parent_version_id: Option<i64>,
+    pub content_hash: String,
+    /// Byte length of `content`, so the UI can warn before loading something huge
+    pub content_length: usize,
+    /// How the save was written and what that costs; only set by `create_version`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub durability: Option<DurabilityInfo>,
+}
+
+/// A version without its content, for listings
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionSummary {
+    pub id: i64,
+    pub document_path: String,
+    pub label: Option<String>,
+    pub timestamp: i64,
+    pub is_checkpoint: bool,
+    pub parent_version_id: Option<i64>,
+    pub content_hash: String,
+    pub content_length: usize,
+}
+
+impl From<Version> for VersionSummary {
+    fn from(version: Version) -> Self {
+        Self {
+            id: version.id,
+            document_path: version.document_path,
+            label: version.label,
+            timestamp: version.timestamp,
+            is_checkpoint: version.is_checkpoint,
+            parent_version_id: version.parent_version_id,
+            content_hash: version.content_hash,
+            content_length: version.content_length,
+        }
+    }
+}
+
+/// A version's text from `CONTENT_COLUMNS` selected at `index`, decompressing
+/// versions `compress_old_versions` has compressed
+pub(crate) fn content_from_row(row: &Row, index: usize) -> rusqlite::Result<String> {
+    if !row.get::<_, bool>(index + 1)? {
+        return row.get(index);
+    }
+    let compressed: Vec<u8> = row.get(index + 2)?;
+    gunzip(&compressed).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index + 2, rusqlite::types::Type::Blob, e.into())
+    })
+}
+
+pub(crate) fn version_from_row(row: &Row) -> rusqlite::Result<Version> {
+    let content = content_from_row(row, 2)?;
+    Ok(Version {
+        id: row.get(0)?,
+        document_path: row.get(1)?,
+        content_hash: db::content_hash(&content),
+        content_length: content.len(),
+        content,
+        label: row.get(5)?,
+        timestamp: row.get(6)?,
+        is_checkpoint: row.get(7)?,
+        parent_version_id: row.get(8)?,
+        durability: None,
+    })
+}
+
+/// Load a version by id, returning `None` if it doesn't exist
+pub fn find_version(conn: &rusqlite::Connection, id: i64) -> Result<Option<Version>, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM versions WHERE id = ?1", VERSION_COLUMNS),
+        [id],
+        version_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load version: {}", e))
+}
+
+/// Load a version by id, failing with `NotFound` if it doesn't exist
+pub fn load_version(conn: &rusqlite::Connection, id: i64) -> Result<Version, String> {
+    find_version(conn, id)?.ok_or_else(|| format!("NotFound: version {} does not exist", id))
+}
+
+/// Fetch a single version's full content by id
+#[tauri::command]
+pub fn get_version(app: AppHandle, id: i64) -> Result<Version, String> {
+    let conn = db::open(&app)?;
+    load_version(&conn, id)
+}
+
+/// Versions fetched by `get_versions`, in the requested order, and the ids that
+/// don't exist
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionBatch {
+    pub versions: Vec<Version>,
+    pub missing: Vec<i64>,
+}
+
+/// Fetch several versions' full content in one query
+#[tauri::command]
+pub fn get_versions(app: AppHandle, ids: Vec<i64>) -> Result<VersionBatch, String> {
+    if ids.is_empty() {
+        return Ok(VersionBatch {
+            versions: Vec::new(),
+            missing: Vec::new(),
+        });
+    }
+    let conn = db::open(&app)?;
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM versions WHERE id IN ({})",
+            VERSION_COLUMNS, placeholders
+        ))
+        .map_err(|e| e.to_string())?;
+    let found: HashMap<i64, Version> = stmt
+        .query_map(rusqlite::params_from_iter(&ids), version_from_row)
+        .map_err(|e| e.to_string())?
+        .map(|row| row.map(|v| (v.id, v)))
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to load versions: {}", e))?;
+
+    let mut batch = VersionBatch {
+        versions: Vec::new(),
+        missing: Vec::new(),
+    };
+    for id in ids {
+        match found.get(&id) {
+            Some(version) => batch.versions.push(version.clone()),
+            None => batch.missing.push(id),
+        }
+    }
+    Ok(batch)
+}
+
+/// Optimistic-concurrency check before saving: true if the document has a version
+/// newer than the one the editor started from (e.g. saved from another window)
+#[tauri::command]
+pub fn check_save_conflict(app: AppHandle, base_version_id: i64) -> Result<bool, String> {
+    let conn = db::open(&app)?;
+    let latest: Option<i64> = conn
+        .query_row(
+            "SELECT (SELECT MAX(id) FROM versions WHERE document_path = base.document_path)
+             FROM versions base WHERE base.id = ?1",
+            [base_version_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to check for newer versions: {}", e))?;
+
+    match latest {
+        Some(latest) => Ok(latest > base_version_id),
+        None => Err(format!(
+            "NotFound: version {} does not exist",
+            base_version_id
+        )),
+    }
+}
+
+/// Insert a version row and return its id. Labeled versions are stored as checkpoints.
+pub fn insert_version(
+    conn: &rusqlite::Connection,
+    document_path: &str,
+    content: &str,
+    label: Option<&str>,
+    parent_version_id: Option<i64>,
+) -> Result<i64, String> {
+    insert_version_at(
+        conn,
+        document_path,
+        content,
+        label,
+        parent_version_id,
+        db::now_ms(),
+    )
+}
+
+/// `insert_version` with an explicit timestamp, for imports that preserve save times
+pub fn insert_version_at(
+    conn: &rusqlite::Connection,
+    document_path: &str,
+    content: &str,
+    label: Option<&str>,
+    parent_version_id: Option<i64>,
+    timestamp: i64,
+) -> Result<i64, String> {
+    conn.execute(
+        "INSERT INTO versions
+           (document_path, content, timestamp, is_checkpoint, checkpoint_name,
+            word_count, char_count, parent_version_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![
+            document_path,
+            content,
+            timestamp,
+            label.is_some(),
+            label,
+            content.split_whitespace().count() as i64,
+            content.chars().count() as i64,
+            parent_version_id,
+        ],
+    )
+    .map_err(|e| format!("Failed to save version: {}", e))?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Latest version id of a document, if it has any
+pub fn latest_version_id(
+    conn: &rusqlite::Connection,
+    document_path: &str,
+) -> Result<Option<i64>, String> {
+    conn.query_row(
+        "SELECT MAX(id) FROM versions WHERE document_path = ?1",
+        [document_path],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Save a new version of a document.
+/// The parent is `base_version_id` when the editor knows where it started from,
+/// otherwise the document's latest version. `override_secrets` saves even when
+/// secret blocking would refuse the content. `durability` defaults to
+/// `set_default_durability`; user-triggered saves should pass `"full"`. The
+/// returned version's `durability` describes the mode used and its tradeoff.
+#[tauri::command]
+pub fn create_version(
+    app: AppHandle,
+    document_path: String,
+    content: String,
+    label: Option<String>,
+    base_version_id: Option<i64>,
+    override_secrets: Option<bool>,
+    durability: Option<db::Durability>,
+) -> Result<Version, String> {
+    let started = Instant::now();
+    let conn = db::open(&app)?;
+    let content = seed_from_template(&app, &conn, &document_path, base_version_id, content)?;
+    let prepared = prepare_content(&app, content)?;
+    check_storage_quota(&app, &conn, prepared.content.len())?;
+    check_secrets(&app, &prepared.content, override_secrets.unwrap_or(false))?;
+
+    let parent = match base_version_id {
+        Some(id) => Some(load_version(&conn, id)?.id),
+        None => latest_version_id(&conn, &document_path)?,
+    };
+    let durability = durability.unwrap_or_else(|| default_durability(&app));
+    let id = db::with_durability(&conn, durability, || {
+        let id = insert_version(
+            &conn,
+            &document_path,
+            &prepared.content,
+            label.as_deref(),
+            parent,
+        )?;
+        record_save_meta(&conn, id, &prepared)?;
+        Ok(id)
+    })?;
+    if let Some(latency) = app.try_state::<SaveLatency>() {
+        latency.record(started.elapsed().as_millis() as u64);
+    }
+    Ok(Version {
+        durability: Some(DurabilityInfo::of(durability)),
+        ..load_version(&conn, id)?
+    })
+}
+
+/// The document template with `{{date}}` (today, local) and `{{title}}` (file name
+/// without extension) filled in, when this is an empty first save of a document
+/// and a template is set; otherwise `content` unchanged
+fn seed_from_template(
+    app: &AppHandle,
+    conn: &rusqlite::Connection,
+    document_path: &str,
+    base_version_id: Option<i64>,
+    content: String,
+) -> Result<String, String> {
+    if !content.trim().is_empty() || base_version_id.is_some() {
+        return Ok(content);
+    }
+    let Some(template) = settings::get::<String>(app, DOCUMENT_TEMPLATE_KEY) else {
+        return Ok(content);
+    };
+    if latest_version_id(conn, document_path)?.is_some() {
+        return Ok(content);
+    }
+    let title = std::path::Path::new(document_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+    Ok(text::fill_placeholders(
+        &template,
+        &[("date", &date), ("title", &title)],
+    ))
+}
+
+/// Set the content a new document's first save starts from when it is empty.
+/// `{{date}}` and `{{title}}` are filled in at that point. An empty template
+/// turns seeding off.
+#[tauri::command]
+pub fn set_document_template(app: AppHandle, content: String) -> Result<(), String> {
+    if content.is_empty() {
+        return settings::remove(&app, DOCUMENT_TEMPLATE_KEY);
+    }
+    validate_content(&app, &content)?;
+    settings::set(&app, DOCUMENT_TEMPLATE_KEY, content)
+}
+
+/// The document template, if one is set
+#[tauri::command]
+pub fn get_document_template(app: AppHandle) -> Option<String> {
+    settings::get(&app, DOCUMENT_TEMPLATE_KEY)
+}
+
+/// Label for an autosave that rewrote more than `threshold` of the previous version
+fn auto_label_significant(old: &str, new: &str, threshold: f64) -> Option<&'static str> {
+    (diff::change_ratio(old, new) > threshold).then_some(MAJOR_EDIT_LABEL)
+}
+
+/// Label autosaves "major edit" when more than `ratio` (0 to 1) of the previous
+/// version's words changed
+#[tauri::command]
+pub fn set_auto_label_threshold(app: AppHandle, ratio: f64) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&ratio) {
+        return Err("Validation: ratio must be between 0 and 1".to_string());
+    }
+    settings::set(&app, AUTO_LABEL_THRESHOLD_KEY, ratio)
+}
+
+/// Durations of the most recent successful saves, oldest first, bounded in size
+#[derive(Default)]
+pub struct SaveLatency(Mutex<VecDeque<u64>>);
+
+impl SaveLatency {
+    fn record(&self, ms: u64) {
+        let mut samples = self.0.lock().unwrap();
+        if samples.len() == SAVE_LATENCY_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(ms);
+    }
+}
+
+/// Time from `create_version` being called to its row being committed, over the
+/// last 200 saves this session
+#[tauri::command]
+pub fn save_latency_stats(latency: State<SaveLatency>) -> Result<LatencyStats, String> {
+    let mut samples: Vec<u64> = latency.0.lock().unwrap().iter().copied().collect();
+    Ok(stats::latency_stats(&mut samples))
+}
+
+/// Save a version if the content differs from the document's latest. It is left
+/// unlabeled unless the change passes the auto-label threshold ("major edit").
+/// Returns `None` when nothing changed. `override_secrets` is as for `create_version`.
+#[tauri::command]
+pub fn autosave(
+    app: AppHandle,
+    document_path: String,
+    content: String,
+    override_secrets: Option<bool>,
+) -> Result<Option<Version>, String> {
+    let prepared = prepare_content(&app, content)?;
+
+    let conn = db::open(&app)?;
+    let latest = match latest_version_id(&conn, &document_path)? {
+        Some(id) => Some(load_version(&conn, id)?),
+        None => None,
+    };
+    if latest
+        .as_ref()
+        .is_some_and(|v| v.content == prepared.content)
+    {
+        return Ok(None);
+    }
+    check_storage_quota(&app, &conn, prepared.content.len())?;
+    check_secrets(&app, &prepared.content, override_secrets.unwrap_or(false))?;
+    let threshold =
+        settings::get(&app, AUTO_LABEL_THRESHOLD_KEY).unwrap_or(DEFAULT_AUTO_LABEL_THRESHOLD);
+    let label = latest
+        .as_ref()
+        .and_then(|v| auto_label_significant(&v.content, &prepared.content, threshold));
+    let parent = latest.map(|v| v.id);
+    let id = db::with_durability(&conn, default_durability(&app), || {
+        let id = insert_version(&conn, &document_path, &prepared.content, label, parent)?;
+        record_save_meta(&conn, id, &prepared)?;
+        Ok(id)
+    })?;
+    load_version(&conn, id).map(Some)
+}
+
+fn default_durability(app: &AppHandle) -> db::Durability {
+    settings::get(app, DEFAULT_DURABILITY_KEY).unwrap_or(db::Durability::Relaxed)
+}
+
+/// A durability mode and what it costs
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DurabilityInfo {
+    pub mode: db::Durability,
+    /// The `PRAGMA synchronous` value saves run with
+    pub synchronous: &'static str,
+    pub tradeoff: &'static str,
+}
+
+impl DurabilityInfo {
+    fn of(mode: db::Durability) -> Self {
+        Self {
+            mode,
+            synchronous: mode.synchronous(),
+            tradeoff: mode.tradeoff(),
+        }
+    }
+}
+
+/// Set the durability of autosaves and of `create_version` calls that don't pass
+/// one. `"relaxed"` (the default) is fast; `"full"` fsyncs every save.
+#[tauri::command]
+pub fn set_default_durability(
+    app: AppHandle,
+    mode: db::Durability,
+) -> Result<DurabilityInfo, String> {
+    settings::set(&app, DEFAULT_DURABILITY_KEY, mode)?;
+    Ok(DurabilityInfo::of(mode))
+}
+
+/// Content after the save pipeline, plus what the pipeline changed
+struct PreparedContent {
+    content: String,
+    line_endings_normalized: bool,
+}
+
+/// Shared save pipeline for `create_version` and `autosave`: normalize, then validate
+fn prepare_content(app: &AppHandle, content: String) -> Result<PreparedContent, String> {
+    let line_endings_normalized = normalize_line_endings_enabled(app) && content.contains('\r');
+    let content = if line_endings_normalized {
+        normalize_line_endings(&content)
+    } else {
+        content
+    };
+    validate_content(app, &content)?;
+    Ok(PreparedContent {
+        content,
+        line_endings_normalized,
+    })
+}
+
+/// Note in `version_meta` what the save pipeline did to a version
+fn record_save_meta(
+    conn: &rusqlite::Connection,
+    id: i64,
+    prepared: &PreparedContent,
+) -> Result<(), String> {
+    if prepared.line_endings_normalized {
+        set_meta(conn, id, LINE_ENDINGS_NORMALIZED_META, "true")?;
+    }
+    Ok(())
+}
+
+/// Fail with `SecretsDetected`, followed by the findings as a JSON array, when
+/// secret blocking is on and `content` has likely secrets, unless overridden
+fn check_secrets(app: &AppHandle, content: &str, override_secrets: bool) -> Result<(), String> {
+    if override_secrets || !settings::get(app, BLOCK_SECRETS_KEY).unwrap_or(false) {
+        return Ok(());
+    }
+    let findings = secrets::find_secrets(content);
+    if findings.is_empty() {
+        return Ok(());
+    }
+    let json = serde_json::to_string(&findings).map_err(|e| e.to_string())?;
+    Err(format!("SecretsDetected: {}", json))
+}
+
+/// Refuse `create_version` and `autosave` saves whose content has likely secrets
+/// (as `scan_version_for_secrets` finds them), unless the save passes
+/// `override_secrets`. Off by default.
+#[tauri::command]
+pub fn set_block_secrets_on_save(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::set(&app, BLOCK_SECRETS_KEY, enabled)
+}
+
+/// Convert CRLF and lone CR line endings to LF
+pub fn normalize_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+fn normalize_line_endings_enabled(app: &AppHandle) -> bool {
+    settings::get(app, NORMALIZE_LINE_ENDINGS_KEY).unwrap_or(true)
+}
+
+/// Toggle line-ending normalization on save
+#[tauri::command]
+pub fn set_normalize_line_endings(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::set(&app, NORMALIZE_LINE_ENDINGS_KEY, enabled)
+}
+
+/// Reject content over the configured size limit with a `TooLarge` error
+fn validate_content(app: &AppHandle, content: &str) -> Result<(), String> {
+    let limit = max_version_bytes(app);
+    if content.len() > limit {
+        return Err(format!(
+            "TooLarge: content is {} bytes, limit is {} bytes",
+            content.len(),
+            limit
+        ));
+    }
+    Ok(())
+}
+
+fn max_version_bytes(app: &AppHandle) -> usize {
+    settings::get(app, MAX_VERSION_BYTES_KEY).unwrap_or(DEFAULT_MAX_VERSION_BYTES)
+}
+
+/// Set the largest content size `create_version` and `autosave` will store
+#[tauri::command]
+pub fn set_max_version_bytes(app: AppHandle, n: usize) -> Result<(), String> {
+    if n == 0 {
+        return Err("Validation: limit must be greater than 0".to_string());
+    }
+    settings::set(&app, MAX_VERSION_BYTES_KEY, n)
+}
+
+/// Current content size limit in bytes
+#[tauri::command]
+pub fn get_max_version_bytes(app: AppHandle) -> usize {
+    max_version_bytes(&app)
+}
+
+/// Bytes of the database in use: allocated pages minus free ones
+fn database_bytes(conn: &rusqlite::Connection) -> Result<u64, String> {
+    conn.query_row(
+        "SELECT (p.page_count - f.freelist_count) * s.page_size
+         FROM pragma_page_count() p, pragma_freelist_count() f, pragma_page_size() s",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|bytes| bytes.max(0) as u64)
+    .map_err(|e| format!("Failed to measure database: {}", e))
+}
+
+fn storage_quota(app: &AppHandle) -> Option<u64> {
+    settings::get(app, STORAGE_QUOTA_KEY)
+}
+
+/// Fail with `QuotaExceeded` if saving `incoming` more bytes would take the
+/// database past the storage quota
+fn check_storage_quota(
+    app: &AppHandle,
+    conn: &rusqlite::Connection,
+    incoming: usize,
+) -> Result<(), String> {
+    let Some(quota) = storage_quota(app) else {
+        return Ok(());
+    };
+    let used = database_bytes(conn)?;
+    if used + incoming as u64 > quota {
+        return Err(format!(
+            "QuotaExceeded: the database uses {} bytes and the quota is {} bytes. \
+             Prune or squash old versions, or compact the database, to free space.",
+            used, quota
+        ));
+    }
+    Ok(())
+}
+
+/// Database usage against the storage quota
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageQuotaStatus {
+    pub used_bytes: u64,
+    pub quota_bytes: Option<u64>,
+    /// `used_bytes` as a percentage of the quota, when there is one
+    pub percent_used: Option<f64>,
+}
+
+/// Cap how large the database may grow; saves that would pass it fail with
+/// `QuotaExceeded`. 0 removes the quota.
+#[tauri::command]
+pub fn set_storage_quota(app: AppHandle, max_bytes: u64) -> Result<(), String> {
+    if max_bytes == 0 {
+        return settings::remove(&app, STORAGE_QUOTA_KEY);
+    }
+    settings::set(&app, STORAGE_QUOTA_KEY, max_bytes)
+}
+
+/// How much of the storage quota the database uses
+#[tauri::command]
+pub fn storage_quota_status(app: AppHandle) -> Result<StorageQuotaStatus, String> {
+    let conn = db::open(&app)?;
+    let used_bytes = database_bytes(&conn)?;
+    let quota_bytes = storage_quota(&app);
+    Ok(StorageQuotaStatus {
+        used_bytes,
+        quota_bytes,
+        percent_used: quota_bytes.map(|quota| used_bytes as f64 * 100.0 / quota as f64),
+    })
+}
+
+/// Restore an old version by saving its content as a new version branched from it
+#[tauri::command]
+pub fn restore_version(app: AppHandle, id: i64) -> Result<Version, String> {
+    let conn = db::open(&app)?;
+    let source = load_version(&conn, id)?;
+    let label = format!("Restored from #{}", source.id);
+    let new_id = insert_version(
+        &conn,
+        &source.document_path,
+        &source.content,
+        Some(&label),
+        Some(source.id),
+    )?;
+    load_version(&conn, new_id)
+}
+
+/// Start a new document whose first version branches from an existing version
+#[tauri::command]
+pub fn fork_document(
+    app: AppHandle,
+    id: i64,
+    new_document_path: String,
+) -> Result<Version, String> {
+    let conn = db::open(&app)?;
+    let source = load_version(&conn, id)?;
+    if latest_version_id(&conn, &new_document_path)?.is_some() {
+        return Err(format!(
+            "Conflict: document {} already has history",
+            new_document_path
+        ));
+    }
+    let label = format!("Forked from #{}", source.id);
+    let new_id = insert_version(
+        &conn,
+        &new_document_path,
+        &source.content,
+        Some(&label),
+        Some(source.id),
+    )?;
+    load_version(&conn, new_id)
+}
+
+/// A parent→child edge in the version lineage
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEdge {
+    pub parent_id: i64,
+    pub child_id: i64,
+}
+
+/// Every parent→child edge across all documents, ordered by child id.
+/// Linear history comes back as a simple chain.
+#[tauri::command]
+pub fn version_graph(app: AppHandle) -> Result<Vec<GraphEdge>, String> {
+    let conn = db::open(&app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT parent_version_id, id FROM versions
+             WHERE parent_version_id IS NOT NULL ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let edges = stmt
+        .query_map([], |row| {
+            Ok(GraphEdge {
+                parent_id: row.get(0)?,
+                child_id: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(edges)
+}
+
+/// A version followed by its ancestors, nearest first. Fails on a lineage cycle.
+pub fn ancestor_chain(conn: &rusqlite::Connection, id: i64) -> Result<Vec<i64>, String> {
+    let mut chain = vec![load_version(conn, id)?.id];
+    let mut seen = std::collections::HashSet::from([id]);
+    let mut current = id;
+    loop {
+        let parent: Option<i64> = conn
+            .query_row(
+                "SELECT parent_version_id FROM versions WHERE id = ?1",
+                [current],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .flatten();
+        let Some(parent) = parent else {
+            return Ok(chain);
+        };
+        if !seen.insert(parent) {
+            return Err(format!(
+                "Conflict: version lineage has a cycle at version {}",
+                parent
+            ));
+        }
+        chain.push(parent);
+        current = parent;
+    }
+}
+
+/// Version ids along the lineage from `from_id` to `to_id`, inclusive, going up to
+/// their nearest common ancestor and back down. Fails if they share no ancestor.
+#[tauri::command]
+pub fn version_path(app: AppHandle, from_id: i64, to_id: i64) -> Result<Vec<i64>, String> {
+    let conn = db::open(&app)?;
+    let from_chain = ancestor_chain(&conn, from_id)?;
+    let to_chain = ancestor_chain(&conn, to_id)?;
+
+    let to_positions: std::collections::HashMap<i64, usize> = to_chain
+        .iter()
+        .enumerate()
+        .map(|(i, &id)| (id, i))
+        .collect();
+    let (up, down) = from_chain
+        .iter()
+        .enumerate()
+        .find_map(|(i, id)| to_positions.get(id).map(|&j| (i, j)))
+        .ok_or_else(|| {
+            format!(
+                "NotFound: versions {} and {} are on unrelated branches",
+                from_id, to_id
+            )
+        })?;
+
+    // Up from `from` to the common ancestor, then down to `to`
+    let mut path = from_chain[..=up].to_vec();
+    path.extend(to_chain[..down].iter().rev());
+    Ok(path)
+}
+
+/// Id and save time of an adjacent version
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NeighborRef {
+    pub id: i64,
+    pub timestamp: i64,
+}
+
+/// The versions immediately before and after one version in its document's history
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Neighbors {
+    pub older: Option<NeighborRef>,
+    pub newer: Option<NeighborRef>,
+}
+
+/// Prev/next lookup for history navigation without loading the whole list.
+/// Both queries are rowid range scans on the document's index.
+#[tauri::command]
+pub fn version_neighbors(app: AppHandle, id: i64) -> Result<Neighbors, String> {
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, id)?;
+
+    let neighbor = |sql: &str| {
+        conn.query_row(sql, rusqlite::params![version.document_path, id], |row| {
+            Ok(NeighborRef {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+            })
+        })
+        .optional()
+        .map_err(|e| e.to_string())
+    };
+
+    Ok(Neighbors {
+        older: neighbor(
+            "SELECT id, timestamp FROM versions
+             WHERE document_path = ?1 AND id < ?2 ORDER BY id DESC LIMIT 1",
+        )?,
+        newer: neighbor(
+            "SELECT id, timestamp FROM versions
+             WHERE document_path = ?1 AND id > ?2 ORDER BY id ASC LIMIT 1",
+        )?,
+    })
+}
+
+/// What `deduplicate_versions` removed, or would remove in a dry run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupReport {
+    pub dry_run: bool,
+    pub removed_versions: usize,
+    pub remapped_comments: usize,
+    pub remapped_attachments: usize,
+    pub removed_ids: Vec<i64>,
+}
+
+/// Collapse runs of consecutive versions with identical content within a document,
+/// keeping the earliest of each run. Comments, attachments and child lineage are
+/// re-pointed to the kept version. Versions with a different label or tag set are never collapsed.
+#[tauri::command]
+pub fn deduplicate_versions(app: AppHandle, dry_run: bool) -> Result<DedupReport, String> {
+    deduplicate(&mut db::open(&app)?, dry_run)
+}
+
+fn deduplicate(conn: &mut rusqlite::Connection, dry_run: bool) -> Result<DedupReport, String> {
+    let tags = tags_by_version(conn)?;
+
+    // (duplicate id, kept id) pairs
+    let mut duplicates: Vec<(i64, i64)> = Vec::new();
+    {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT id, document_path, checkpoint_name, {} FROM versions
+                 ORDER BY document_path, id",
+                CONTENT_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+        let no_tags = Vec::new();
+        // Kept version of the current run: (id, document, hash, label)
+        let mut kept: Option<(i64, String, String, Option<String>)> = None;
+
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let id: i64 = row.get(0).map_err(|e| e.to_string())?;
+            let document_path: String = row.get(1).map_err(|e| e.to_string())?;
+            let label: Option<String> = row.get(2).map_err(|e| e.to_string())?;
+            // Compressed rows have an empty `content` column, so always decode
+            let content = content_from_row(row, 3).map_err(|e| e.to_string())?;
+            let hash = db::content_hash(&content);
+
+            let is_duplicate =
+                kept.as_ref()
+                    .is_some_and(|(kept_id, doc, kept_hash, kept_label)| {
+                        *doc == document_path
+                            && *kept_hash == hash
+                            && *kept_label == label
+                            && tags.get(kept_id).unwrap_or(&no_tags)
+                                == tags.get(&id).unwrap_or(&no_tags)
+                    });
+            match (&kept, is_duplicate) {
+                (Some((kept_id, ..)), true) => duplicates.push((id, *kept_id)),
+                _ => kept = Some((id, document_path, hash, label)),
+            }
+        }
+    }
+
+    let mut remapped_comments = 0;
+    let mut remapped_attachments = 0;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for &(duplicate, keep) in &duplicates {
+        if dry_run {
+            let count = |table: &str| {
+                tx.query_row(
+                    &format!("SELECT COUNT(*) FROM {} WHERE version_id = ?1", table),
+                    [duplicate],
+                    |row| row.get::<_, i64>(0),
+                )
+                .map(|n| n as usize)
+                .map_err(|e| e.to_string())
+            };
+            remapped_comments += count("comments")?;
+            remapped_attachments += count("version_attachments")?;
+            continue;
+        }
+        remapped_comments += tx
+            .execute(
+                "UPDATE comments SET version_id = ?1 WHERE version_id = ?2",
+                [keep, duplicate],
+            )
+            .map_err(|e| format!("Failed to re-point comments: {}", e))?;
+        // Attachments cascade on delete, so they have to move first
+        remapped_attachments += tx
+            .execute(
+                "UPDATE version_attachments SET version_id = ?1 WHERE version_id = ?2",
+                [keep, duplicate],
+            )
+            .map_err(|e| format!("Failed to re-point attachments: {}", e))?;
+        tx.execute(
+            "UPDATE versions SET parent_version_id = ?1 WHERE parent_version_id = ?2",
+            [keep, duplicate],
+        )
+        .map_err(|e| format!("Failed to re-point lineage: {}", e))?;
+        tx.execute("DELETE FROM versions WHERE id = ?1", [duplicate])
+            .map_err(|e| format!("Failed to remove duplicate: {}", e))?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(DedupReport {
+        dry_run,
+        removed_versions: duplicates.len(),
+        remapped_comments,
+        remapped_attachments,
+        removed_ids: duplicates.iter().map(|(id, _)| *id).collect(),
+    })
+}
+
+/// Collapse the autosaves from `from_id` to `to_id` (inclusive) into `to_id`, which
+/// is labeled `label` and takes `from_id`'s parent. Keeping `to_id`'s row keeps its
+/// id and timestamp, so id and time order still hold around the squashed range.
+/// Comments, attachments and child versions of the others move to `to_id` and the
+/// others are deleted, in one transaction. Fails with `Conflict` listing any
+/// labeled, checkpoint or tagged versions in the range, which are never squashed.
+/// Returns `to_id`.
+#[tauri::command]
+pub fn squash_versions(
+    app: AppHandle,
+    from_id: i64,
+    to_id: i64,
+    label: String,
+) -> Result<i64, String> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err("Validation: label must not be empty".to_string());
+    }
+    let mut conn = db::open(&app)?;
+    let from = load_version(&conn, from_id)?;
+    let to = load_version(&conn, to_id)?;
+    if from.document_path != to.document_path {
+        return Err("Validation: versions belong to different documents".to_string());
+    }
+    if from_id > to_id {
+        return Err("Validation: from_id must not be newer than to_id".to_string());
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let (squashed, protected) = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT v.id, v.checkpoint_name IS NOT NULL OR v.is_checkpoint != 0
+                     OR EXISTS (SELECT 1 FROM version_tags t WHERE t.version_id = v.id)
+                 FROM versions v
+                 WHERE v.document_path = ?1 AND v.id >= ?2 AND v.id <= ?3 ORDER BY v.id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(
+                rusqlite::params![from.document_path, from_id, to_id],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, bool>(1)?)),
+            )
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let protected: Vec<i64> = rows.iter().filter(|(_, p)| *p).map(|(id, _)| *id).collect();
+        (rows, protected)
+    };
+    if !protected.is_empty() {
+        let ids: Vec<String> = protected.iter().map(|id| id.to_string()).collect();
+        return Err(format!(
+            "Conflict: labeled, checkpoint or tagged versions can't be squashed: {}",
+            ids.join(", ")
+        ));
+    }
+
+    for (id, _) in squashed.iter().filter(|(id, _)| *id != to_id) {
+        for sql in [
+            "UPDATE comments SET version_id = ?1 WHERE version_id = ?2",
+            "UPDATE version_attachments SET version_id = ?1 WHERE version_id = ?2",
+            "UPDATE versions SET parent_version_id = ?1 WHERE parent_version_id = ?2",
+        ] {
+            tx.execute(sql, [to_id, *id])
+                .map_err(|e| format!("Failed to re-point version {}: {}", id, e))?;
+        }
+        tx.execute("DELETE FROM versions WHERE id = ?1", [*id])
+            .map_err(|e| format!("Failed to remove version {}: {}", id, e))?;
+    }
+    tx.execute(
+        "UPDATE versions SET checkpoint_name = ?1, is_checkpoint = 1, parent_version_id = ?2
+         WHERE id = ?3",
+        rusqlite::params![label, from.parent_version_id, to_id],
+    )
+    .map_err(|e| format!("Failed to label version {}: {}", to_id, e))?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(to_id)
+}
+
+/// Versions saved strictly after an ISO 8601 time, oldest first, for polling sync tools
+#[tauri::command]
+pub fn versions_since(app: AppHandle, timestamp: String) -> Result<Vec<VersionSummary>, String> {
+    let since = db::parse_timestamp(&timestamp)?;
+    let conn = db::open(&app)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM versions WHERE timestamp > ?1 ORDER BY timestamp, id",
+            VERSION_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let versions = stmt
+        .query_map([since], version_from_row)
+        .map_err(|e| e.to_string())?
+        .map(|row| row.map(VersionSummary::from))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(versions)
+}
+
+/// A stable fingerprint of a document's history, for telling whether two databases
+/// hold the same document. It is the SHA-256 of each version's content hash, one per
+/// line, ordered by timestamp and then hash. Only content counts: row ids, labels,
+/// parents, comments, tags and other metadata do not. With no `document_id`, every
+/// document is included, ordered by path, with each path on its own line before its
+/// hashes.
+#[tauri::command]
+pub fn document_fingerprint(app: AppHandle, document_id: Option<String>) -> Result<String, String> {
+    let conn = db::open(&app)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM versions WHERE ?1 IS NULL OR document_path = ?1",
+            VERSION_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    // Hashes aren't stored, so the final order is settled here
+    let mut rows = stmt
+        .query_map([&document_id], |row| {
+            let version = version_from_row(row)?;
+            Ok((
+                version.document_path,
+                version.timestamp,
+                version.content_hash,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    rows.sort();
+    if let (Some(path), true) = (&document_id, rows.is_empty()) {
+        return Err(format!("NotFound: no versions for {}", path));
+    }
+
+    let mut input = String::new();
+    let mut current: Option<&str> = None;
+    for (path, _, hash) in &rows {
+        if document_id.is_none() && current != Some(path.as_str()) {
+            input.push_str(path);
+            input.push('\n');
+            current = Some(path);
+        }
+        input.push_str(hash);
+        input.push('\n');
+    }
+    Ok(db::content_hash(&input))
+}
+
+fn gzip(text: &str) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(text.as_bytes())?;
+    encoder.finish()
+}
+
+/// Uncompressed length of gzip data, from its trailer, without decompressing.
+/// Exact below 4 GiB, which is far past the version size limit.
+pub(crate) fn gzip_original_len(bytes: &[u8]) -> u64 {
+    match bytes.len().checked_sub(4) {
+        Some(start) => {
+            let trailer: [u8; 4] = bytes[start..].try_into().unwrap_or_default();
+            u32::from_le_bytes(trailer) as u64
+        }
+        None => 0,
+    }
+}
+
+fn gunzip(bytes: &[u8]) -> std::io::Result<String> {
+    let mut text = String::new();
+    GzDecoder::new(bytes).read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Gzip the content of versions older than `older_than_days`, in place. A
+/// document's latest version and labeled, checkpoint or tagged versions are left
+/// alone, as are versions that wouldn't shrink. Reads through `get_version` and
+/// the other version commands decompress transparently, but compressed text drops
+/// out of full-text search. Returns the bytes saved.
+#[tauri::command]
+pub fn compress_old_versions(app: AppHandle, older_than_days: i64) -> Result<usize, String> {
+    if older_than_days < 0 {
+        return Err("Validation: older_than_days must not be negative".to_string());
+    }
+    let cutoff = db::now_ms() - older_than_days.saturating_mul(24 * 60 * 60 * 1000);
+    compress_versions_before(&mut db::open(&app)?, cutoff)
+}
+
+fn compress_versions_before(conn: &mut rusqlite::Connection, cutoff: i64) -> Result<usize, String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let candidates: Vec<(i64, String)> = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT v.id, v.content FROM versions v
+                 WHERE v.compressed = 0 AND v.timestamp < ?1
+                   AND v.checkpoint_name IS NULL AND v.is_checkpoint = 0
+                   AND NOT EXISTS (SELECT 1 FROM version_tags t WHERE t.version_id = v.id)
+                   AND v.id != (SELECT MAX(id) FROM versions l
+                                WHERE l.document_path = v.document_path)",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([cutoff], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        rows
+    };
+
+    let mut saved = 0;
+    for (id, content) in candidates {
+        let compressed = gzip(&content).map_err(|e| format!("Failed to compress: {}", e))?;
+        if compressed.len() >= content.len() {
+            continue;
+        }
+        tx.execute(
+            "UPDATE versions SET content = '', compressed = 1, compressed_content = ?1
+             WHERE id = ?2",
+            rusqlite::params![compressed, id],
+        )
+        .map_err(|e| format!("Failed to compress version {}: {}", id, e))?;
+        saved += content.len() - compressed.len();
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrated() -> rusqlite::Connection {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        db::apply_migrations(&mut conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn dedup_keeps_distinct_compressed_versions() {
+        let mut conn = migrated();
+        let first = "first draft ".repeat(100);
+        let second = "second draft ".repeat(100);
+        let a = insert_version(&conn, "doc.md", &first, None, None).unwrap();
+        let b = insert_version(&conn, "doc.md", &second, None, Some(a)).unwrap();
+        insert_version(&conn, "doc.md", "latest", None, Some(b)).unwrap();
+
+        assert!(compress_versions_before(&mut conn, i64::MAX).unwrap() > 0);
+        let report = deduplicate(&mut conn, false).unwrap();
+
+        assert!(report.removed_ids.is_empty());
+        assert_eq!(load_version(&conn, a).unwrap().content, first);
+        assert_eq!(load_version(&conn, b).unwrap().content, second);
+    }
+
+    #[test]
+    fn dedup_moves_attachments_to_the_kept_version() {
+        let mut conn = migrated();
+        let kept = insert_version(&conn, "doc.md", "same", None, None).unwrap();
+        let duplicate = insert_version(&conn, "doc.md", "same", None, Some(kept)).unwrap();
+        conn.execute(
+            "INSERT INTO version_attachments (version_id, name, path, mime, created_at)
+             VALUES (?1, 'a.png', '/tmp/a.png', 'image/png', 0)",
+            [duplicate],
+        )
+        .unwrap();
+
+        let preview = deduplicate(&mut conn, true).unwrap();
+        assert_eq!(preview.remapped_attachments, 1);
+        let report = deduplicate(&mut conn, false).unwrap();
+        assert_eq!(report.removed_ids, vec![duplicate]);
+        assert_eq!(report.remapped_attachments, 1);
+        let owner: i64 = conn
+            .query_row("SELECT version_id FROM version_attachments", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(owner, kept);
+    }
+
+    #[test]
+    fn gzip_trailer_gives_original_length() {
+        let text = "compressible ".repeat(500);
+        assert_eq!(gzip_original_len(&gzip(&text).unwrap()), text.len() as u64);
+        assert_eq!(gzip_original_len(&[1, 2]), 0);
+    }
+}
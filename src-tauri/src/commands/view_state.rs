@@ -0,0 +1,73 @@
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::commands::versions::load_version;
+use crate::db;
+
+/// Editor position saved for a version
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewState {
+    /// Character offset of the cursor
+    pub cursor_offset: i64,
+    /// Scroll position from 0.0 (top) to 1.0 (bottom)
+    pub scroll_ratio: f64,
+    pub updated_at: i64,
+}
+
+/// Remember the cursor and scroll position for a version
+#[tauri::command]
+pub fn save_view_state(
+    app: AppHandle,
+    version_id: i64,
+    cursor_offset: i64,
+    scroll_ratio: f64,
+) -> Result<(), String> {
+    if cursor_offset < 0 {
+        return Err("Validation: cursor_offset must not be negative".to_string());
+    }
+    if !(0.0..=1.0).contains(&scroll_ratio) {
+        return Err("Validation: scroll_ratio must be between 0 and 1".to_string());
+    }
+    let conn = db::open(&app)?;
+    load_version(&conn, version_id)?;
+    conn.execute(
+        "INSERT INTO version_view_state (version_id, cursor_offset, scroll_ratio, updated_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(version_id) DO UPDATE SET cursor_offset = excluded.cursor_offset,
+             scroll_ratio = excluded.scroll_ratio, updated_at = excluded.updated_at",
+        rusqlite::params![version_id, cursor_offset, scroll_ratio, db::now_ms()],
+    )
+    .map_err(|e| format!("Failed to save view state: {}", e))?;
+    Ok(())
+}
+
+/// The saved position for a version, with the cursor clamped to its current length
+#[tauri::command]
+pub fn get_view_state(app: AppHandle, version_id: i64) -> Result<Option<ViewState>, String> {
+    let conn = db::open(&app)?;
+    let version = load_version(&conn, version_id)?;
+    let state = conn
+        .query_row(
+            "SELECT cursor_offset, scroll_ratio, updated_at
+             FROM version_view_state WHERE version_id = ?1",
+            [version_id],
+            |row| {
+                Ok(ViewState {
+                    cursor_offset: row.get(0)?,
+                    scroll_ratio: row.get(1)?,
+                    updated_at: row.get(2)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read view state: {}", e))?;
+
+    let length = version.content.chars().count() as i64;
+    Ok(state.map(|state| ViewState {
+        cursor_offset: state.cursor_offset.clamp(0, length),
+        scroll_ratio: state.scroll_ratio.clamp(0.0, 1.0),
+        ..state
+    }))
+}
@@ -0,0 +1,63 @@
+use serde::Serialize;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+
+use crate::commands::ai::refresh_models;
+use crate::{db, secrets};
+
+/// How one warmup step went
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupStep {
+    pub name: &'static str,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WarmupDone {
+    steps: Vec<WarmupStep>,
+}
+
+fn step(name: &'static str, started: Instant, result: Result<(), String>) -> WarmupStep {
+    WarmupStep {
+        name,
+        ok: result.is_ok(),
+        error: result.err(),
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+/// Initialize lazily-loaded pieces in the background so first interactions don't
+/// stall: the database connection and search index, the secret patterns, and the
+/// model list. Each step runs even if an earlier one fails. Emits `warmup-done`.
+/// Token counts use a character heuristic, so there is no tokenizer to load.
+#[tauri::command]
+pub fn warmup(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut steps = Vec::new();
+
+        let started = Instant::now();
+        let result = db::open(&app).and_then(|conn| {
+            conn.query_row(
+                "SELECT COUNT(*) FROM versions_fts WHERE versions_fts MATCH 'serq'",
+                [],
+                |_| Ok(()),
+            )
+            .map_err(|e| e.to_string())
+        });
+        steps.push(step("search_index", started, result));
+
+        let started = Instant::now();
+        secrets::find_secrets("");
+        steps.push(step("secret_patterns", started, Ok(())));
+
+        let started = Instant::now();
+        let result = refresh_models(&app).await.map(|_| ());
+        steps.push(step("model_list", started, result));
+
+        let _ = app.emit("warmup-done", WarmupDone { steps });
+    });
+}
@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::settings;
+
+/// Store key: canonical directory import/export paths must stay inside
+pub(crate) const WORKING_DIRECTORY_KEY: &str = "working_directory";
+
+/// Only allow import/export inside `path`. The directory must exist.
+#[tauri::command]
+pub fn set_working_directory(app: AppHandle, path: String) -> Result<(), String> {
+    let canonical = std::fs::canonicalize(&path)
+        .map_err(|e| format!("NotFound: directory {} is not accessible: {}", path, e))?;
+    if !canonical.is_dir() {
+        return Err(format!("Validation: {} is not a directory", path));
+    }
+    settings::set(&app, WORKING_DIRECTORY_KEY, canonical.to_string_lossy())
+}
+
+/// The directory import/export is limited to, if one is set
+#[tauri::command]
+pub fn get_working_directory(app: AppHandle) -> Option<String> {
+    settings::get(&app, WORKING_DIRECTORY_KEY)
+}
+
+/// Canonicalize a file path for import/export and check it's inside the working
+/// directory. Without a working directory every path is allowed. Files that don't
+/// exist yet (export targets) are resolved through their parent directory.
+pub(crate) fn allowed_path(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    let Some(base) = get_working_directory(app) else {
+        return Ok(PathBuf::from(path));
+    };
+
+    let requested = Path::new(path);
+    let canonical = if requested.exists() {
+        std::fs::canonicalize(requested)
+    } else {
+        let parent = match requested.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let name = requested
+            .file_name()
+            .ok_or_else(|| format!("Validation: {} is not a file path", path))?;
+        std::fs::canonicalize(parent).map(|dir| dir.join(name))
+    }
+    .map_err(|e| format!("NotFound: {} is not accessible: {}", path, e))?;
+
+    if !canonical.starts_with(&base) {
+        return Err(format!(
+            "Forbidden: {} is outside the working directory {}",
+            path, base
+        ));
+    }
+    Ok(canonical)
+}
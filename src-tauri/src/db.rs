@@ -0,0 +1,435 @@
+//! Direct SQLite access for Rust-side commands.
+//!
+//! The sql plugin owns migrations and the frontend's connection pool. Rust commands
+//! open their own short-lived connection to the same `serq.db` file, which lives in
+//! the app config directory (the location the plugin resolves `sqlite:serq.db` to).
+
+use rusqlite::types::Value;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_sql::{Migration, MigrationKind};
+
+pub const DB_FILE: &str = "serq.db";
+pub const CONNECTION_URL: &str = "sqlite:serq.db";
+
+/// PRAGMAs reported by `db_get_pragmas`
+pub const REPORTED_PRAGMAS: &[&str] = &[
+    "journal_mode",
+    "synchronous",
+    "foreign_keys",
+    "busy_timeout",
+    "cache_size",
+];
+
+/// sqlx's tracking table, as the sql plugin creates it
+const MIGRATIONS_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS _sqlx_migrations (
+    version BIGINT PRIMARY KEY,
+    description TEXT NOT NULL,
+    installed_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    success BOOLEAN NOT NULL,
+    checksum BLOB NOT NULL,
+    execution_time BIGINT NOT NULL
+)";
+
+/// Database file chosen with `switch_database`, replacing serq.db for Rust commands
+#[derive(Default)]
+pub struct ActiveDatabase(pub Mutex<Option<PathBuf>>);
+
+/// Absolute path of the active database file: serq.db (shared with the sql plugin)
+/// unless `switch_database` pointed Rust commands elsewhere
+pub fn db_path(app: &AppHandle) -> Result<PathBuf, String> {
+    if let Some(path) = app
+        .try_state::<ActiveDatabase>()
+        .and_then(|active| active.0.lock().unwrap().clone())
+    {
+        return Ok(path);
+    }
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve app config dir: {}", e))?;
+    Ok(dir.join(DB_FILE))
+}
+
+/// Open a connection to serq.db with the connection-level PRAGMAs applied.
+/// Every Rust command goes through here, so the settings hold no matter when
+/// the sql plugin opens its own pool.
+pub fn open(app: &AppHandle) -> Result<Connection, String> {
+    let path = db_path(app)?;
+    let conn = Connection::open(&path).map_err(|e| format!("Failed to open database: {}", e))?;
+    apply_connection_pragmas(&conn)?;
+    Ok(conn)
+}
+
+/// Open the active database read-only; any write through it fails in SQLite itself
+pub fn open_readonly(app: &AppHandle) -> Result<Connection, String> {
+    let path = db_path(app)?;
+    let conn = Connection::open_with_flags(
+        &path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("Failed to open database: {}", e))?;
+    apply_connection_pragmas(&conn)?;
+    Ok(conn)
+}
+
+/// Per-connection settings. These reset on every new connection.
+pub fn apply_connection_pragmas(conn: &Connection) -> Result<(), String> {
+    conn.busy_timeout(Duration::from_secs(5))
+        .map_err(|e| e.to_string())?;
+    conn.execute_batch(
+        "PRAGMA foreign_keys = ON;
+         PRAGMA synchronous = NORMAL;
+         PRAGMA cache_size = -8000;",
+    )
+    .map_err(|e| format!("Failed to apply PRAGMAs: {}", e))
+}
+
+/// How hard a write pushes to disk before returning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Durability {
+    /// `synchronous = NORMAL`, the connection default. In WAL mode a power loss
+    /// can drop the last few commits, but the database stays consistent.
+    Relaxed,
+    /// `synchronous = FULL`: the write is on disk when the call returns, at the
+    /// cost of an fsync per commit
+    Full,
+}
+
+impl Durability {
+    pub fn synchronous(self) -> &'static str {
+        match self {
+            Durability::Relaxed => "NORMAL",
+            Durability::Full => "FULL",
+        }
+    }
+
+    pub fn tradeoff(self) -> &'static str {
+        match self {
+            Durability::Relaxed => {
+                "Fast: no fsync per save. A crash or power loss may lose the most \
+                 recent saves, but never corrupts history."
+            }
+            Durability::Full => {
+                "Safe: each save is flushed to disk before returning. Slower, \
+                 noticeably so on frequent saves or slow disks."
+            }
+        }
+    }
+}
+
+/// Run `write` with `synchronous` set for `durability`, then put the connection
+/// default back
+pub fn with_durability<T>(
+    conn: &Connection,
+    durability: Durability,
+    write: impl FnOnce() -> Result<T, String>,
+) -> Result<T, String> {
+    if durability == Durability::Relaxed {
+        return write();
+    }
+    conn.execute_batch(&format!(
+        "PRAGMA synchronous = {};",
+        durability.synchronous()
+    ))
+    .map_err(|e| format!("Failed to set durability: {}", e))?;
+    let result = write();
+    let restored = conn
+        .execute_batch(&format!(
+            "PRAGMA synchronous = {};",
+            Durability::Relaxed.synchronous()
+        ))
+        .map_err(|e| format!("Failed to restore durability: {}", e));
+    let value = result?;
+    restored?;
+    Ok(value)
+}
+
+/// Startup initialization: make sure the config dir exists and switch the
+/// database to WAL, which is persisted in the file itself.
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let path = db_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let conn = open(app)?;
+    conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))
+        .map_err(|e| format!("Failed to enable WAL: {}", e))
+}
+
+/// Split a migration script into statements, keeping trigger bodies
+/// (`BEGIN ... END;`) whole. Comment-only lines are dropped.
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut in_trigger = false;
+
+    for line in sql.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || (current.is_empty() && trimmed.starts_with("--")) {
+            continue;
+        }
+        current.push_str(line);
+        current.push('\n');
+        if trimmed.to_uppercase().ends_with("BEGIN") {
+            in_trigger = true;
+        }
+        let ends = if in_trigger {
+            trimmed.eq_ignore_ascii_case("END;")
+        } else {
+            trimmed.ends_with(';')
+        };
+        if ends {
+            statements.push(current.trim().to_string());
+            current.clear();
+            in_trigger = false;
+        }
+    }
+    if !current.trim().is_empty() {
+        statements.push(current.trim().to_string());
+    }
+    statements
+}
+
+/// Apply bundled migrations missing from `conn`, recording them in sqlx's tracking
+/// table with sqlx's checksums so the sql plugin accepts the file later.
+/// Returns the versions applied.
+pub fn apply_migrations(conn: &mut Connection) -> Result<Vec<i64>, String> {
+    conn.execute_batch(MIGRATIONS_TABLE_DDL)
+        .map_err(|e| format!("Failed to create migrations table: {}", e))?;
+    let mut applied = Vec::new();
+
+    for migration in migrations() {
+        let done: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM _sqlx_migrations WHERE version = ?1 AND success = 1)",
+                [migration.version],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if done {
+            continue;
+        }
+
+        let started = Instant::now();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute_batch(migration.sql)
+            .map_err(|e| format!("Migration {} failed: {}", migration.version, e))?;
+        tx.execute(
+            "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+             VALUES (?1, ?2, 1, ?3, ?4)",
+            rusqlite::params![
+                migration.version,
+                migration.description,
+                Sha384::digest(migration.sql.as_bytes()).to_vec(),
+                started.elapsed().as_nanos() as i64
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        applied.push(migration.version);
+    }
+    Ok(applied)
+}
+
+/// Whether a file starts with the SQLite header
+pub fn is_sqlite_file(path: &Path) -> Result<bool, String> {
+    use std::io::Read;
+    let mut header = [0u8; 16];
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header == b"SQLite format 3\0"),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Render any SQLite value as display text
+pub fn value_to_string(value: Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        Value::Text(s) => s,
+        Value::Blob(b) => format!("<{} bytes>", b.len()),
+    }
+}
+
+/// Hex-encoded SHA-256 of a version's content
+pub fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Current time in epoch milliseconds, matching the frontend's `Date.now()` timestamps
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Parse an RFC 3339 / ISO 8601 time (e.g. `2024-05-01T12:00:00Z`) to epoch milliseconds
+pub fn parse_timestamp(iso: &str) -> Result<i64, String> {
+    chrono::DateTime::parse_from_rfc3339(iso.trim())
+        .map(|t| t.timestamp_millis())
+        .map_err(|e| {
+            format!(
+                "Validation: invalid timestamp '{}': {} (expected ISO 8601, e.g. 2024-05-01T12:00:00Z)",
+                iso, e
+            )
+        })
+}
+
+/// Every schema migration, in order. Registered with the sql plugin in `run()`.
+pub fn migrations() -> Vec<Migration> {
+    vec![
+        Migration {
+            version: 1,
+            description: "create_versions_table",
+            sql: include_str!("../migrations/001_versions.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 2,
+            description: "create_comments_table",
+            sql: include_str!("../migrations/002_comments.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 3,
+            description: "add_comment_version_id",
+            sql: include_str!("../migrations/003_comment_versions.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 4,
+            description: "add_version_parents",
+            sql: include_str!("../migrations/004_version_parents.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 5,
+            description: "create_ai_usage_table",
+            sql: include_str!("../migrations/005_ai_usage.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 6,
+            description: "create_version_meta_table",
+            sql: include_str!("../migrations/006_version_meta.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 7,
+            description: "create_version_tags_table",
+            sql: include_str!("../migrations/007_version_tags.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 8,
+            description: "add_comment_parent_id",
+            sql: include_str!("../migrations/008_comment_threads.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 9,
+            description: "create_versions_fts",
+            sql: include_str!("../migrations/009_versions_fts.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 10,
+            description: "create_version_attachments_table",
+            sql: include_str!("../migrations/010_version_attachments.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 11,
+            description: "create_comment_revisions_table",
+            sql: include_str!("../migrations/011_comment_revisions.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 12,
+            description: "add_comment_type",
+            sql: include_str!("../migrations/012_comment_types.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 13,
+            description: "create_version_embeddings_table",
+            sql: include_str!("../migrations/013_version_embeddings.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 14,
+            description: "create_version_view_state_table",
+            sql: include_str!("../migrations/014_version_view_state.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 15,
+            description: "create_ai_jobs_table",
+            sql: include_str!("../migrations/015_ai_jobs.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 16,
+            description: "add_comment_deleted_at",
+            sql: include_str!("../migrations/016_comment_trash.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 17,
+            description: "add_version_compression",
+            sql: include_str!("../migrations/017_version_compression.sql"),
+            kind: MigrationKind::Up,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_semicolons_and_skips_comments() {
+        let sql =
+            "-- header\nCREATE TABLE a (id INTEGER);\n\nINSERT INTO a\n  VALUES (1);\nSELECT 1";
+        assert_eq!(
+            split_statements(sql),
+            vec![
+                "CREATE TABLE a (id INTEGER);",
+                "INSERT INTO a\n  VALUES (1);",
+                "SELECT 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_trigger_bodies_together() {
+        let sql = "CREATE TRIGGER t AFTER INSERT ON a\nBEGIN\n  UPDATE a SET id = 1;\n  DELETE FROM a;\nEND;\nSELECT 2;";
+        let statements = split_statements(sql);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].starts_with("CREATE TRIGGER") && statements[0].ends_with("END;"));
+        assert_eq!(statements[1], "SELECT 2;");
+    }
+
+    #[test]
+    fn bundled_migrations_apply_to_an_empty_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let applied = apply_migrations(&mut conn).unwrap();
+        assert_eq!(applied.len(), migrations().len());
+        assert!(apply_migrations(&mut conn).unwrap().is_empty());
+    }
+}
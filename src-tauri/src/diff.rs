@@ -0,0 +1,359 @@
+//! Shared diff core used by every command that compares version content.
+
+use serde::Serialize;
+use similar::{ChangeTag, DiffTag, TextDiff};
+
+/// Summary counts for a diff between two texts
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffStats {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub words_added: usize,
+    pub words_removed: usize,
+}
+
+/// Fraction of the text that changed between two versions, from 0.0 (identical)
+/// to 1.0 (nothing in common), measured over words
+pub fn change_ratio(old: &str, new: &str) -> f64 {
+    1.0 - TextDiff::from_words(old, new).ratio() as f64
+}
+
+/// Line-level diff stats. Word counts are taken from the added/removed lines.
+pub fn diff_stats(old: &str, new: &str) -> DiffStats {
+    let diff = TextDiff::from_lines(old, new);
+    let mut stats = DiffStats::default();
+
+    for change in diff.iter_all_changes() {
+        let words = change.value().split_whitespace().count();
+        match change.tag() {
+            ChangeTag::Insert => {
+                stats.lines_added += 1;
+                stats.words_added += words;
+            }
+            ChangeTag::Delete => {
+                stats.lines_removed += 1;
+                stats.words_removed += words;
+            }
+            ChangeTag::Equal => {}
+        }
+    }
+
+    stats
+}
+
+/// Unified diff text with three lines of context. Empty when the texts are equal.
+pub fn unified_diff(old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header("before", "after")
+        .to_string()
+}
+
+/// Number of lines as the line diff tokenizes them
+pub fn line_count(text: &str) -> usize {
+    text.split_inclusive('\n').count()
+}
+
+/// Carry per-line edit counts from `old` to `new`. Unchanged lines keep their
+/// count; rewritten lines get one more than the lines they replaced; inserted
+/// lines start at one.
+pub fn carry_line_churn(old_counts: &[usize], old: &str, new: &str) -> Vec<usize> {
+    let count_at = |i: usize| old_counts.get(i).copied().unwrap_or(0);
+    let mut counts = Vec::with_capacity(line_count(new));
+
+    for op in TextDiff::from_lines(old, new).ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        match tag {
+            DiffTag::Equal => counts.extend(old_range.map(count_at)),
+            DiffTag::Delete => {}
+            DiffTag::Insert => counts.extend(std::iter::repeat_n(1, new_range.len())),
+            DiffTag::Replace => {
+                let prior = old_range.map(count_at).max().unwrap_or(0);
+                counts.extend(std::iter::repeat_n(prior + 1, new_range.len()));
+            }
+        }
+    }
+
+    counts
+}
+
+/// One line of a hunk, tagged `equal`, `insert` or `delete`
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub kind: &'static str,
+    pub text: String,
+}
+
+/// A contiguous group of changes with three lines of context, as in a unified diff.
+/// Starts are 1-based line numbers.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Line diff grouped into hunks. Empty when the texts are equal.
+pub fn diff_hunks(old: &str, new: &str) -> Vec<DiffHunk> {
+    let diff = TextDiff::from_lines(old, new);
+    diff.grouped_ops(3)
+        .iter()
+        .map(|group| {
+            let (first, last) = (&group[0], &group[group.len() - 1]);
+            let old_range = first.old_range().start..last.old_range().end;
+            let new_range = first.new_range().start..last.new_range().end;
+            let lines = group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffLine {
+                    kind: match change.tag() {
+                        ChangeTag::Equal => "equal",
+                        ChangeTag::Insert => "insert",
+                        ChangeTag::Delete => "delete",
+                    },
+                    text: change.value().to_string(),
+                })
+                .collect();
+            DiffHunk {
+                old_start: old_range.start + 1,
+                old_lines: old_range.len(),
+                new_start: new_range.start + 1,
+                new_lines: new_range.len(),
+                lines,
+            }
+        })
+        .collect()
+}
+
+/// One side of a side-by-side row: a 1-based line number and its text
+#[derive(Debug, Clone, Serialize)]
+pub struct SideLine {
+    pub number: usize,
+    pub text: String,
+}
+
+/// Aligned left/right lines for a two-column diff. A missing side is a blank cell.
+/// `kind` is `equal`, `insert`, `delete` or `replace`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SideBySideRow {
+    pub kind: &'static str,
+    pub left: Option<SideLine>,
+    pub right: Option<SideLine>,
+}
+
+/// Full-file line diff aligned into rows. Replaced runs are paired line by line,
+/// padding the shorter side with blanks.
+pub fn side_by_side(old: &str, new: &str) -> Vec<SideBySideRow> {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let side = |lines: &[&str], i: usize| SideLine {
+        number: i + 1,
+        text: lines[i].trim_end_matches(['\n', '\r']).to_string(),
+    };
+
+    let mut rows = Vec::new();
+    for op in TextDiff::from_lines(old, new).ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let kind = match tag {
+            DiffTag::Equal => "equal",
+            DiffTag::Delete => "delete",
+            DiffTag::Insert => "insert",
+            DiffTag::Replace => "replace",
+        };
+        for offset in 0..old_range.len().max(new_range.len()) {
+            let left =
+                (offset < old_range.len()).then(|| side(&old_lines, old_range.start + offset));
+            let right =
+                (offset < new_range.len()).then(|| side(&new_lines, new_range.start + offset));
+            rows.push(SideBySideRow { kind, left, right });
+        }
+    }
+    rows
+}
+
+/// A run of text with one change kind: `equal`, `insert` or `delete`
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffSegment {
+    pub kind: &'static str,
+    pub text: String,
+}
+
+/// Word-level diff of the whole text, with adjacent changes of the same kind merged
+pub fn word_diff(old: &str, new: &str) -> Vec<DiffSegment> {
+    let mut segments: Vec<DiffSegment> = Vec::new();
+    for change in TextDiff::from_words(old, new).iter_all_changes() {
+        let kind = match change.tag() {
+            ChangeTag::Equal => "equal",
+            ChangeTag::Insert => "insert",
+            ChangeTag::Delete => "delete",
+        };
+        match segments.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(change.value()),
+            _ => segments.push(DiffSegment {
+                kind,
+                text: change.value().to_string(),
+            }),
+        }
+    }
+    segments
+}
+
+/// One edit in a replay: `insert` or `delete` `text` at `position`, a character
+/// offset into the text as it stands after the previous ops have been applied
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayOp {
+    pub kind: &'static str,
+    pub position: usize,
+    pub text: String,
+}
+
+/// Word-level edits that turn `old` into `new` when applied in order
+pub fn replay_ops(old: &str, new: &str) -> Vec<ReplayOp> {
+    let mut ops: Vec<ReplayOp> = Vec::new();
+    let mut position = 0;
+    for change in TextDiff::from_words(old, new).iter_all_changes() {
+        let len = change.value().chars().count();
+        let kind = match change.tag() {
+            ChangeTag::Equal => {
+                position += len;
+                continue;
+            }
+            ChangeTag::Insert => "insert",
+            ChangeTag::Delete => "delete",
+        };
+        // Only merge runs with no equal text between them
+        let contiguous = |last: &ReplayOp| match kind {
+            "insert" => last.position + last.text.chars().count() == position,
+            _ => last.position == position,
+        };
+        match ops.last_mut() {
+            Some(last) if last.kind == kind && contiguous(last) => {
+                last.text.push_str(change.value())
+            }
+            _ => ops.push(ReplayOp {
+                kind,
+                position,
+                text: change.value().to_string(),
+            }),
+        }
+        if kind == "insert" {
+            position += len;
+        }
+    }
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_count_changed_lines_and_words() {
+        let stats = diff_stats("one two\nthree\n", "one two\nfour five\nsix\n");
+        assert_eq!((stats.lines_added, stats.lines_removed), (2, 1));
+        assert_eq!((stats.words_added, stats.words_removed), (3, 1));
+    }
+
+    #[test]
+    fn change_ratio_spans_identical_to_disjoint() {
+        assert_eq!(change_ratio("same text", "same text"), 0.0);
+        assert_eq!(change_ratio("alpha", "omega"), 1.0);
+    }
+
+    #[test]
+    fn unified_diff_is_empty_for_equal_texts() {
+        assert_eq!(unified_diff("a\n", "a\n"), "");
+        let diff = unified_diff("a\n", "b\n");
+        assert!(diff.contains("-a") && diff.contains("+b"));
+    }
+
+    #[test]
+    fn line_count_includes_unterminated_last_line() {
+        assert_eq!(line_count(""), 0);
+        assert_eq!(line_count("a\nb"), 2);
+        assert_eq!(line_count("a\nb\n"), 2);
+    }
+
+    #[test]
+    fn churn_carries_equal_lines_and_bumps_rewrites() {
+        let counts = carry_line_churn(&[3, 1], "keep\nold\n", "keep\nnew\nadded\n");
+        assert_eq!(counts, vec![3, 2, 2]);
+        let counts = carry_line_churn(&[2], "keep\n", "keep\nadded\n");
+        assert_eq!(counts, vec![2, 1]);
+    }
+
+    #[test]
+    fn hunks_use_one_based_starts() {
+        let hunks = diff_hunks("a\nb\nc\n", "a\nB\nc\n");
+        assert_eq!(hunks.len(), 1);
+        let hunk = &hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines), (1, 3));
+        assert_eq!((hunk.new_start, hunk.new_lines), (1, 3));
+        let kinds: Vec<_> = hunk.lines.iter().map(|l| l.kind).collect();
+        assert_eq!(kinds, vec!["equal", "delete", "insert", "equal"]);
+        assert!(diff_hunks("same\n", "same\n").is_empty());
+    }
+
+    #[test]
+    fn side_by_side_pads_shorter_replacement() {
+        let rows = side_by_side("a\nb\n", "a\nx\ny\n");
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].kind, "equal");
+        assert_eq!(rows[1].left.as_ref().map(|l| l.text.as_str()), Some("b"));
+        assert_eq!(rows[1].right.as_ref().map(|l| l.number), Some(2));
+        assert!(rows[2].left.is_none());
+        assert_eq!(rows[2].right.as_ref().map(|l| l.text.as_str()), Some("y"));
+    }
+
+    #[test]
+    fn word_diff_merges_adjacent_segments() {
+        let segments = word_diff("the quick fox", "the slow fox");
+        let parts: Vec<_> = segments.iter().map(|s| (s.kind, s.text.as_str())).collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("equal", "the "),
+                ("delete", "quick"),
+                ("insert", "slow"),
+                ("equal", " fox"),
+            ]
+        );
+    }
+
+    /// Apply replay ops the way the frontend does
+    fn apply(text: &str, ops: &[ReplayOp]) -> String {
+        let mut chars: Vec<char> = text.chars().collect();
+        for op in ops {
+            let len = op.text.chars().count();
+            match op.kind {
+                "insert" => {
+                    chars.splice(op.position..op.position, op.text.chars());
+                }
+                _ => {
+                    chars.drain(op.position..op.position + len);
+                }
+            }
+        }
+        chars.into_iter().collect()
+    }
+
+    #[test]
+    fn replay_ops_rebuild_the_new_text() {
+        for (old, new) in [
+            ("the quick fox", "the slow brown fox"),
+            ("héllo wörld", "wörld héllo again"),
+            ("", "from nothing"),
+            ("all gone", ""),
+        ] {
+            assert_eq!(apply(old, &replay_ops(old, new)), new);
+        }
+    }
+}
@@ -1,8 +1,19 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+mod anthropic;
 mod commands;
-// Note: AI commands disabled until keyring API is fixed
+mod db;
+mod diff;
+mod markdown;
+mod rate_limit;
+mod readability;
+mod review_markdown;
+mod secrets;
+mod settings;
+mod stats;
+mod text;
 
-use tauri_plugin_sql::{Builder as SqlBuilder, Migration, MigrationKind};
+use tauri::Manager;
+use tauri_plugin_sql::Builder as SqlBuilder;
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -11,43 +22,234 @@ fn greet(name: &str) -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    commands::install_panic_hook();
     tauri::Builder::default()
-        // .plugin(tauri_plugin_keyring::init())  // TODO: Re-enable with commands
         .plugin(
             SqlBuilder::default()
-                .add_migrations(
-                    "sqlite:serq.db",
-                    vec![
-                        Migration {
-                            version: 1,
-                            description: "create_versions_table",
-                            sql: include_str!("../migrations/001_versions.sql"),
-                            kind: MigrationKind::Up,
-                        },
-                        Migration {
-                            version: 2,
-                            description: "create_comments_table",
-                            sql: include_str!("../migrations/002_comments.sql"),
-                            kind: MigrationKind::Up,
-                        },
-                    ],
-                )
+                .add_migrations(db::CONNECTION_URL, db::migrations())
                 .build(),
         )
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
+        .manage(db::ActiveDatabase::default())
+        .manage(commands::CancelRegistry::default())
+        .manage(commands::DebugBridgeState::default())
+        .manage(rate_limit::RateLimiter::default())
+        .manage(rate_limit::ConcurrencyLimiter::default())
+        .manage(commands::BackgroundAutosave::default())
+        .manage(commands::AutoBackup::default())
+        .manage(commands::ModelCache::default())
+        .manage(commands::AiQueue::default())
+        .manage(commands::SaveLatency::default())
+        .setup(|app| {
+            // Non-fatal: commands will surface DB errors themselves
+            if let Err(e) = db::init(app.handle()) {
+                eprintln!("Database init failed: {}", e);
+            }
+            commands::spawn_flusher(app.handle().clone());
+            commands::spawn_webhook_sender(app.handle().clone());
+            commands::resume_auto_backup(app.handle());
+            commands::spawn_ai_worker(app.handle().clone());
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            // Closed windows can't unsubscribe themselves
+            if let tauri::WindowEvent::Destroyed = event {
+                window
+                    .state::<commands::DebugBridgeState>()
+                    .remove_window(window.label());
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             // Debug bridge - always active in dev
             commands::debug_bridge_log,
             commands::debug_bridge_clear,
-            // TODO: Re-enable after fixing keyring API
-            // commands::set_api_key,
-            // commands::get_api_key,
-            // commands::has_api_key,
+            commands::debug_bridge_compact,
+            commands::debug_bridge_rotation_preview,
+            commands::debug_bridge_set_rotation,
+            commands::debug_bridge_set_webhook,
+            commands::debug_bridge_subscribe,
+            commands::debug_bridge_subscribe_filtered,
+            commands::debug_bridge_unsubscribe,
+            commands::debug_bridge_subscription_status,
+            commands::debug_bridge_set_enabled,
+            commands::debug_bridge_is_enabled,
+            commands::debug_bridge_set_flush_interval,
+            // Version history
+            commands::get_version,
+            commands::get_versions,
+            commands::check_save_conflict,
+            commands::create_version,
+            commands::autosave,
+            commands::update_working_content,
+            commands::start_background_autosave,
+            commands::stop_background_autosave,
+            commands::set_max_version_bytes,
+            commands::get_max_version_bytes,
+            commands::set_storage_quota,
+            commands::storage_quota_status,
+            commands::set_normalize_line_endings,
+            commands::set_block_secrets_on_save,
+            commands::set_default_durability,
+            commands::restore_version,
+            commands::fork_document,
+            commands::version_graph,
+            commands::version_neighbors,
+            commands::version_path,
+            commands::save_latency_stats,
+            commands::set_auto_label_threshold,
+            commands::set_document_template,
+            commands::get_document_template,
+            commands::save_view_state,
+            commands::get_view_state,
+            commands::deduplicate_versions,
+            commands::versions_since,
+            commands::document_fingerprint,
+            commands::squash_versions,
+            commands::compress_old_versions,
+            // Tags
+            commands::tag_version,
+            commands::tag_versions_bulk,
+            commands::untag_version,
+            commands::get_version_tags,
+            commands::list_tags,
+            commands::compute_history_diffs,
+            commands::churn_report,
+            commands::diff_from_parent,
+            commands::diff_versions,
+            commands::diff_versions_side_by_side,
+            commands::diff_against_version,
+            commands::compare_documents,
+            commands::range_change_summary,
+            commands::editing_time,
+            commands::changelog_between_tags,
+            commands::replay_data,
+            commands::set_diff_size_threshold,
+            commands::readability,
+            commands::writing_stats_by_day,
+            commands::version_toc,
+            commands::word_frequency,
+            commands::get_stopwords,
+            commands::set_stopwords,
+            commands::is_text_version,
+            // Search
+            commands::search_version_positions,
+            commands::scan_version_for_secrets,
+            commands::search_versions_stream,
+            // Attachments
+            commands::add_attachment,
+            commands::list_attachments,
+            commands::remove_attachment,
+            commands::validate_attachments,
+            // Background work
+            commands::warmup,
+            commands::cancel_request,
+            // Database
+            commands::db_get_pragmas,
+            commands::list_migrations,
+            commands::migration_dry_run,
+            commands::verify_migrations_from_scratch,
+            commands::switch_database,
+            commands::run_readonly_query,
+            commands::explain_query,
+            commands::schema_drift_report,
+            commands::normalize_timestamps,
+            commands::export_repro_db,
+            commands::export_resequenced,
+            commands::enable_auto_backup,
+            commands::disable_auto_backup,
+            commands::fts_check,
+            commands::fts_rebuild,
+            commands::fts_status,
+            #[cfg(debug_assertions)]
+            commands::dump_runtime_state,
+            commands::effective_config,
+            // Import / export
+            commands::export_versions_zip,
+            commands::export_graph_dot,
+            commands::import_versions_zip,
+            commands::export_anonymized,
+            commands::estimate_export_size,
+            commands::render_version_markdown,
+            commands::export_version_html,
+            commands::import_file_as_version,
+            commands::import_large_file,
+            commands::import_from_git,
+            commands::set_max_import_bytes,
+            commands::set_working_directory,
+            commands::get_working_directory,
+            // Comments
+            commands::create_comment,
+            commands::set_max_comment_length,
+            commands::set_event_batching,
+            commands::set_comment_type,
+            commands::list_comments_by_type,
+            commands::list_comments_flat,
+            commands::resolve_all_comments,
+            commands::create_comments_bulk,
+            commands::find_orphaned_comments,
+            commands::purge_orphaned_comments,
+            commands::reparent_orphans,
+            commands::find_duplicate_comments,
+            commands::merge_duplicate_comments,
+            commands::diff_comment_trees,
+            commands::trash_comment,
+            commands::restore_comment,
+            commands::list_trashed_comments,
+            commands::comment_counts_for_versions,
+            commands::edit_comment,
+            commands::get_comment_history,
+            commands::export_comments_markdown,
+            commands::export_comments_markdown_stream,
+            commands::import_comments_markdown,
+            // AI
+            commands::set_api_key,
+            commands::get_api_key,
+            commands::has_api_key,
+            commands::list_all_keys,
+            commands::revoke_all_keys,
+            commands::keyring_available,
+            commands::ai_suggest_label,
+            commands::ai_complete_stream,
+            commands::ai_complete_with_context,
+            commands::set_document_system_prompt,
+            commands::get_document_system_prompt,
+            commands::set_default_system_prompt,
+            commands::ai_complete,
+            commands::set_ai_streaming,
+            commands::get_ai_streaming,
+            commands::ai_set_rate_limit,
+            commands::ai_set_max_concurrency,
+            commands::ai_set_request_timeout,
+            commands::ai_set_usage_tick_interval,
+            commands::ai_set_base_url,
+            commands::ai_list_models,
+            commands::ai_summarize_version,
+            commands::ai_summarize_missing,
+            commands::ai_suggest_tags,
+            commands::ai_apply_suggested_tags,
+            commands::ai_generate_version,
+            commands::get_version_provenance,
+            commands::ai_enqueue,
+            commands::ai_queue_status,
+            commands::ai_cancel_job,
+            commands::ai_embed_version,
+            commands::semantic_search,
+            commands::ai_latency_stats,
+            commands::ai_throughput_stats,
+            commands::set_usage_alert,
+            commands::get_usage_alert,
+            commands::export_ai_usage_csv,
             greet
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // Don't lose buffered debug log lines on quit
+            if let tauri::RunEvent::Exit = event {
+                let _ = app.state::<commands::DebugBridgeState>().flush();
+            }
+        });
 }
@@ -0,0 +1,185 @@
+//! Markdown helpers built on pulldown-cmark.
+
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag, TagEnd};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// One heading of a document outline
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TocEntry {
+    pub level: u8,
+    pub text: String,
+    /// Byte offset of the heading in the source
+    pub offset: usize,
+    /// Anchor unique within the document, e.g. `intro`, `intro-1`
+    pub slug: String,
+}
+
+/// GitHub-style anchor: lowercase, punctuation dropped, whitespace to `-`
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '_' {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Headings in document order. Repeated slugs get `-1`, `-2`, ... suffixes.
+pub fn toc(content: &str) -> Vec<TocEntry> {
+    let mut entries = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut current: Option<(u8, usize, String)> = None;
+
+    for (event, range) in Parser::new(content).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                current = Some((level as u8, range.start, String::new()));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, _, heading)) = current.as_mut() {
+                    heading.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((level, offset, text)) = current.take() {
+                    let base = slugify(&text);
+                    let count = seen.entry(base.clone()).or_insert(0);
+                    let slug = match *count {
+                        0 => base.clone(),
+                        n => format!("{}-{}", base, n),
+                    };
+                    *count += 1;
+                    entries.push(TocEntry {
+                        level,
+                        text: text.trim().to_string(),
+                        offset,
+                        slug,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+/// Link schemes kept when rendering; anything else (e.g. `javascript:`) becomes `#`
+const SAFE_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    match url.split_once(':') {
+        // A colon after a `/`, `?` or `#` is part of a relative URL, not a scheme
+        Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => {
+            if SAFE_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()) {
+                url
+            } else {
+                CowStr::Borrowed("#")
+            }
+        }
+        _ => url,
+    }
+}
+
+/// Render markdown to HTML that is safe to display: raw HTML in the source is
+/// shown as text and links or images with unsafe schemes are neutralized
+pub fn to_safe_html(content: &str) -> String {
+    let events = Parser::new(content).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    });
+    let mut out = String::with_capacity(content.len() * 3 / 2);
+    html::push_html(&mut out, events);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_drops_punctuation() {
+        assert_eq!(slugify("  Hello, World!  "), "hello-world");
+        assert_eq!(slugify("a -- b"), "a-b");
+        assert_eq!(slugify("!!!"), "section");
+    }
+
+    #[test]
+    fn repeated_headings_get_numbered_slugs() {
+        let entries = toc("# A\n\ntext\n\n## A\n");
+        let slugs: Vec<_> = entries.iter().map(|e| e.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["a", "a-1"]);
+        assert_eq!((entries[0].level, entries[1].level), (1, 2));
+        assert_eq!(entries[1].offset, "# A\n\ntext\n\n".len());
+    }
+
+    #[test]
+    fn no_headings_gives_an_empty_toc() {
+        assert!(toc("Just a paragraph.\n\n- and a list\n").is_empty());
+        assert!(toc("").is_empty());
+    }
+
+    #[test]
+    fn raw_html_is_rendered_as_text() {
+        let html =
+            to_safe_html("<script>alert(1)</script>\n\nSee <img src=x onerror=alert(1)> here");
+        assert!(
+            !html.contains("<script") && !html.contains("<img"),
+            "{}",
+            html
+        );
+        assert!(html.contains("&lt;script&gt;"), "{}", html);
+        assert!(html.contains("&lt;img"), "{}", html);
+    }
+
+    #[test]
+    fn unsafe_link_and_image_urls_become_hash() {
+        let html = to_safe_html(
+            "[a](javascript:alert(1)) [b](JavaScript:alert(1)) ![c](data:image/png;base64,AAAA)",
+        );
+        assert!(!html.to_lowercase().contains("javascript:"), "{}", html);
+        assert!(!html.contains("data:"), "{}", html);
+        assert_eq!(html.matches("href=\"#\"").count(), 2, "{}", html);
+        assert!(html.contains("src=\"#\""), "{}", html);
+    }
+
+    #[test]
+    fn safe_and_relative_urls_are_kept() {
+        let html =
+            to_safe_html("[a](https://example.com) [b](mailto:me@example.com) [c](docs/a:b)");
+        assert!(html.contains("href=\"https://example.com\""), "{}", html);
+        assert!(html.contains("href=\"mailto:me@example.com\""), "{}", html);
+        assert!(html.contains("href=\"docs/a:b\""), "{}", html);
+    }
+}
@@ -0,0 +1,160 @@
+//! Sliding-window and concurrency limiters shared by every AI request.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+const WINDOW: Duration = Duration::from_secs(60);
+const DEFAULT_REQUESTS_PER_MINUTE: u32 = 30;
+const DEFAULT_MAX_CONCURRENT: u32 = 4;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitConfig {
+    pub requests_per_minute: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: DEFAULT_REQUESTS_PER_MINUTE,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+    config: Mutex<RateLimitConfig>,
+    recent: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn config(&self) -> RateLimitConfig {
+        *self.config.lock().unwrap()
+    }
+
+    pub fn set_config(&self, config: RateLimitConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    /// Whether either limiter lock is currently held (or poisoned)
+    #[cfg(debug_assertions)]
+    pub fn is_locked(&self) -> bool {
+        self.config.try_lock().is_err() || self.recent.try_lock().is_err()
+    }
+
+    /// Requests started within the current window
+    #[cfg(debug_assertions)]
+    pub fn in_window(&self) -> usize {
+        let now = Instant::now();
+        self.recent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|t| now.duration_since(**t) < WINDOW)
+            .count()
+    }
+
+    /// Wait until a request slot is free in the current window, then take it
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let limit = self.config().requests_per_minute.max(1) as usize;
+                let mut recent = self.recent.lock().unwrap();
+                let now = Instant::now();
+                while recent
+                    .front()
+                    .is_some_and(|t| now.duration_since(*t) >= WINDOW)
+                {
+                    recent.pop_front();
+                }
+                if recent.len() < limit {
+                    recent.push_back(now);
+                    return;
+                }
+                WINDOW - now.duration_since(recent[0])
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcurrencyConfig {
+    pub max_concurrent: u32,
+    /// Wait for a free slot when at capacity, instead of failing with `Busy`
+    pub queue_when_full: bool,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+            queue_when_full: true,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ConcurrencyState {
+    config: ConcurrencyConfig,
+    in_flight: u32,
+}
+
+/// Caps how many AI requests are in flight at once
+#[derive(Default)]
+pub struct ConcurrencyLimiter {
+    state: Mutex<ConcurrencyState>,
+    released: Notify,
+}
+
+/// A held request slot, released on drop (completion, cancellation or error)
+pub struct ConcurrencyPermit<'a> {
+    limiter: &'a ConcurrencyLimiter,
+}
+
+impl Drop for ConcurrencyPermit<'_> {
+    fn drop(&mut self) {
+        self.limiter.state.lock().unwrap().in_flight -= 1;
+        self.limiter.released.notify_waiters();
+    }
+}
+
+impl ConcurrencyLimiter {
+    pub fn config(&self) -> ConcurrencyConfig {
+        self.state.lock().unwrap().config
+    }
+
+    /// Takes effect for the next acquire; requests already in flight keep their slots
+    pub fn set_config(&self, config: ConcurrencyConfig) {
+        self.state.lock().unwrap().config = config;
+        self.released.notify_waiters();
+    }
+
+    /// Take a slot, waiting for one to free up or failing with `Busy` when at
+    /// capacity, depending on `queue_when_full`
+    pub async fn acquire(&self) -> Result<ConcurrencyPermit<'_>, String> {
+        loop {
+            // Created before checking so a release in between isn't missed
+            let released = self.released.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                let limit = state.config.max_concurrent.max(1);
+                if state.in_flight < limit {
+                    state.in_flight += 1;
+                    return Ok(ConcurrencyPermit { limiter: self });
+                }
+                if !state.config.queue_when_full {
+                    return Err(format!(
+                        "Busy: {} AI requests are already in flight",
+                        state.in_flight
+                    ));
+                }
+            }
+            released.await;
+        }
+    }
+}
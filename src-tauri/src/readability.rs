@@ -0,0 +1,124 @@
+//! Readability scores for prose.
+//!
+//! Syllables are counted heuristically (vowel groups, minus a silent trailing `e`),
+//! which is usually within one syllable per word for English but miscounts some words
+//! ("create", "poem") and ignores names, numbers and abbreviations. Sentences end at
+//! `.`, `!` or `?`, so abbreviations like "e.g." split sentences. Scores are
+//! approximate and only meaningful for English.
+
+use serde::{Deserialize, Serialize};
+
+/// Below this many words the formulas are too noisy to report
+pub const MIN_WORDS: usize = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadabilityScores {
+    pub flesch_reading_ease: f64,
+    pub flesch_kincaid_grade: f64,
+    /// Words per sentence
+    pub average_sentence_length: f64,
+    /// Letters per word
+    pub average_word_length: f64,
+    pub words: usize,
+    pub sentences: usize,
+    pub syllables: usize,
+}
+
+/// Heuristic syllable count for one word (letters only), at least 1
+pub fn count_syllables(word: &str) -> usize {
+    let word: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if word.is_empty() {
+        return 0;
+    }
+
+    let is_vowel = |c: char| "aeiouy".contains(c);
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for &c in &word {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    // Silent trailing "e", but not "-le" as in "table"
+    let n = word.len();
+    if n > 2 && word[n - 1] == 'e' && !(word[n - 2] == 'l' && !is_vowel(word[n - 3])) {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+/// Score a text, or `None` if it has fewer than `MIN_WORDS` words
+pub fn score(text: &str) -> Option<ReadabilityScores> {
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .filter(|w| w.chars().any(char::is_alphabetic))
+        .collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+
+    let sentences = text
+        .split(['.', '!', '?'])
+        .filter(|s| s.chars().any(char::is_alphabetic))
+        .count()
+        .max(1);
+    let syllables: usize = words.iter().map(|w| count_syllables(w)).sum();
+    let letters: usize = words
+        .iter()
+        .map(|w| w.chars().filter(|c| c.is_alphabetic()).count())
+        .sum();
+
+    let word_count = words.len() as f64;
+    let words_per_sentence = word_count / sentences as f64;
+    let syllables_per_word = syllables as f64 / word_count;
+
+    Some(ReadabilityScores {
+        flesch_reading_ease: 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word,
+        flesch_kincaid_grade: 0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59,
+        average_sentence_length: words_per_sentence,
+        average_word_length: letters as f64 / word_count,
+        words: words.len(),
+        sentences,
+        syllables,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_vowel_groups_minus_silent_e() {
+        assert_eq!(count_syllables("cat"), 1);
+        assert_eq!(count_syllables("Reading!"), 2);
+        assert_eq!(count_syllables("make"), 1);
+        assert_eq!(count_syllables("table"), 2);
+        assert_eq!(count_syllables("the"), 1);
+        assert_eq!(count_syllables("42"), 0);
+    }
+
+    #[test]
+    fn short_texts_are_not_scored() {
+        assert!(score("Too few words to score.").is_none());
+    }
+
+    #[test]
+    fn scores_simple_prose() {
+        let text = "The cat sat on the mat. ".repeat(6);
+        let scores = score(&text).expect("enough words");
+        assert_eq!(scores.words, 36);
+        assert_eq!(scores.sentences, 6);
+        assert_eq!(scores.syllables, 36);
+        assert_eq!(scores.average_sentence_length, 6.0);
+        assert!((scores.flesch_reading_ease - 116.145).abs() < 1e-9);
+        assert!(scores.flesch_kincaid_grade < 0.0);
+    }
+}
@@ -0,0 +1,267 @@
+//! Markdown format for comment reviews, shared by export and import.
+//!
+//! ```text
+//! # Review of notes.md (version 12)
+//!
+//! ## suggestion 10..24
+//! Top-level comment body, any number of lines.
+//!
+//! - question: A reply
+//!   continuing on an indented line
+//!   - note: A reply to the reply
+//! ```
+//!
+//! Each `##` heading starts a thread anchored to a character range. Replies are
+//! bullets nested two spaces per level and share the thread's range. Body lines
+//! that would read back as a heading or a reply (`#`, `- `), or that start with a
+//! backslash, are written with a leading `\`, which parsing removes.
+
+use std::io::{self, Write};
+
+/// Whether a body line needs a leading backslash to read back as text
+fn needs_escape(line: &str) -> bool {
+    let content = line.trim_start_matches(' ');
+    content.starts_with("- ") || content.starts_with('#') || line.starts_with('\\')
+}
+
+fn escape(line: &str) -> String {
+    if needs_escape(line) {
+        format!("\\{}", line)
+    } else {
+        line.to_string()
+    }
+}
+
+fn unescape(line: &str) -> &str {
+    line.strip_prefix('\\').unwrap_or(line)
+}
+
+/// One comment in a review, flattened in document order. `depth` 0 is a thread root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewEntry {
+    pub depth: usize,
+    pub comment_type: String,
+    pub position_from: i64,
+    pub position_to: i64,
+    pub text: String,
+}
+
+/// Parsed entries plus how many malformed ones were skipped
+#[derive(Debug, Clone, Default)]
+pub struct ParsedReview {
+    pub entries: Vec<ReviewEntry>,
+    pub skipped: usize,
+}
+
+/// Render entries (in depth-first order) as a review document
+pub fn render(title: &str, entries: &[ReviewEntry]) -> String {
+    let mut writer = ReviewWriter::new(Vec::new(), title).expect("writing to a Vec can't fail");
+    for entry in entries {
+        writer.write(entry).expect("writing to a Vec can't fail");
+    }
+    String::from_utf8(writer.into_inner()).expect("review markdown is UTF-8")
+}
+
+/// Writes a review document one entry at a time, for output too large to build
+/// in memory. Produces exactly what `render` does.
+pub struct ReviewWriter<W: Write> {
+    out: W,
+    previous_depth: usize,
+}
+
+impl<W: Write> ReviewWriter<W> {
+    /// Start a document, writing its title
+    pub fn new(mut out: W, title: &str) -> io::Result<Self> {
+        writeln!(out, "# {}", title)?;
+        Ok(Self {
+            out,
+            previous_depth: 0,
+        })
+    }
+
+    /// Append the next entry in depth-first order
+    pub fn write(&mut self, entry: &ReviewEntry) -> io::Result<()> {
+        let after_root = self.previous_depth == 0;
+        self.previous_depth = entry.depth;
+        if entry.depth == 0 {
+            let body: Vec<String> = entry.text.lines().map(escape).collect();
+            return write!(
+                self.out,
+                "\n## {} {}..{}\n{}\n",
+                entry.comment_type,
+                entry.position_from,
+                entry.position_to,
+                body.join("\n")
+            );
+        }
+        let indent = "  ".repeat(entry.depth - 1);
+        if after_root {
+            // Blank line between a thread body and its first reply
+            writeln!(self.out)?;
+        }
+        let mut lines = entry.text.lines();
+        writeln!(
+            self.out,
+            "{}- {}: {}",
+            indent,
+            entry.comment_type,
+            lines.next().unwrap_or("")
+        )?;
+        for line in lines {
+            writeln!(self.out, "{}  {}", indent, escape(line))?;
+        }
+        Ok(())
+    }
+
+    /// The underlying writer
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Parse a review document. Threads with a malformed heading are skipped along
+/// with their replies; malformed replies are skipped individually.
+pub fn parse(markdown: &str, allowed_types: &[&str]) -> ParsedReview {
+    let mut review = ParsedReview::default();
+    // Whether lines currently belong to a skipped thread
+    let mut skipping = false;
+
+    for line in markdown.lines() {
+        if let Some(heading) = line.strip_prefix("## ") {
+            finish_root(&mut review.entries);
+            match parse_heading(heading, allowed_types) {
+                Some(entry) => {
+                    review.entries.push(entry);
+                    skipping = false;
+                }
+                None => {
+                    review.skipped += 1;
+                    skipping = true;
+                }
+            }
+            continue;
+        }
+        if line.starts_with("# ") || skipping {
+            continue;
+        }
+        let Some(last) = review.entries.last() else {
+            continue;
+        };
+
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let content = &line[indent..];
+        if let Some(reply) = content.strip_prefix("- ") {
+            finish_root(&mut review.entries);
+            let depth = indent / 2 + 1;
+            let last_depth = review.entries.last().map_or(0, |e| e.depth);
+            let root = review.entries.iter().rev().find(|e| e.depth == 0);
+            let parsed = reply
+                .split_once(": ")
+                .filter(|(kind, _)| allowed_types.contains(kind));
+            match (root, parsed) {
+                (Some(root), Some((kind, text))) if indent % 2 == 0 && depth <= last_depth + 1 => {
+                    let entry = ReviewEntry {
+                        depth,
+                        comment_type: kind.to_string(),
+                        position_from: root.position_from,
+                        position_to: root.position_to,
+                        text: text.to_string(),
+                    };
+                    review.entries.push(entry);
+                }
+                _ => review.skipped += 1,
+            }
+            continue;
+        }
+
+        if last.depth == 0 {
+            let root = review.entries.last_mut().expect("checked above");
+            if !root.text.is_empty() || !line.trim().is_empty() {
+                root.text.push_str(unescape(line));
+                root.text.push('\n');
+            }
+        } else if line
+            .get(..2 * last.depth)
+            .is_some_and(|prefix| prefix.bytes().all(|b| b == b' '))
+        {
+            // Blank lines inside a reply keep their indentation, unlike the one
+            // separating it from the next thread
+            let reply = review.entries.last_mut().expect("checked above");
+            reply.text.push('\n');
+            reply.text.push_str(unescape(&line[2 * reply.depth..]));
+        }
+    }
+    finish_root(&mut review.entries);
+    review
+}
+
+/// `type from..to` from a thread heading
+fn parse_heading(heading: &str, allowed_types: &[&str]) -> Option<ReviewEntry> {
+    let (kind, range) = heading.trim().split_once(' ')?;
+    if !allowed_types.contains(&kind) {
+        return None;
+    }
+    let (from, to) = range.trim().split_once("..")?;
+    let position_from = from.parse().ok()?;
+    let position_to = to.parse().ok()?;
+    Some(ReviewEntry {
+        depth: 0,
+        comment_type: kind.to_string(),
+        position_from,
+        position_to,
+        text: String::new(),
+    })
+}
+
+/// Trim the trailing blank lines collected into the last thread root's body
+fn finish_root(entries: &mut [ReviewEntry]) {
+    if let Some(root) = entries.last_mut().filter(|e| e.depth == 0) {
+        root.text = root.text.trim_end().to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPES: &[&str] = &["note", "question"];
+
+    fn entry(depth: usize, text: &str) -> ReviewEntry {
+        ReviewEntry {
+            depth,
+            comment_type: "note".to_string(),
+            position_from: 3,
+            position_to: 9,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn markdown_bodies_round_trip() {
+        let entries = vec![
+            entry(
+                0,
+                "Intro\n\n- a list item\n## not a heading\n# nor this\n\\escaped",
+            ),
+            entry(
+                1,
+                "Reply with a list:\n- one\n  - nested\n\nsecond paragraph",
+            ),
+            entry(2, "Deeper\n\\ backslash"),
+            entry(1, "Plain reply"),
+            entry(0, "Second thread"),
+        ];
+        let parsed = parse(&render("Review", &entries), TYPES);
+        assert_eq!(parsed.skipped, 0);
+        assert_eq!(parsed.entries, entries);
+    }
+
+    #[test]
+    fn unknown_types_are_skipped_with_their_replies() {
+        let markdown = "# Review\n\n## bogus 1..2\nbody\n\n- note: reply\n\n## note 4..5\nkept\n";
+        let parsed = parse(markdown, TYPES);
+        assert_eq!(parsed.skipped, 1);
+        assert_eq!(parsed.entries.len(), 1);
+        assert_eq!(parsed.entries[0].text, "kept");
+    }
+}
@@ -0,0 +1,39 @@
+//! Backend access to the persisted preferences store shared with the frontend.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+/// Same file the frontend's preference helpers use
+pub const STORE_FILE: &str = "preferences.json";
+
+/// Read a setting, returning `None` if it's unset or has the wrong shape
+pub fn get<T: DeserializeOwned>(app: &AppHandle, key: &str) -> Option<T> {
+    let store = app.store(STORE_FILE).ok()?;
+    store
+        .get(key)
+        .and_then(|value| serde_json::from_value(value).ok())
+}
+
+/// Write a setting and persist the store
+pub fn set<T: Serialize>(app: &AppHandle, key: &str, value: T) -> Result<(), String> {
+    let store = app
+        .store(STORE_FILE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.set(key, serde_json::to_value(value).map_err(|e| e.to_string())?);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+/// Remove a setting so readers fall back to their default
+pub fn remove(app: &AppHandle, key: &str) -> Result<(), String> {
+    let store = app
+        .store(STORE_FILE)
+        .map_err(|e| format!("Failed to open settings: {}", e))?;
+    store.delete(key);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
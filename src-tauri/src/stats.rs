@@ -0,0 +1,38 @@
+//! Small statistics helpers for latency and throughput reporting.
+
+use serde::Serialize;
+
+/// Distribution summary over a set of millisecond samples
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub average_ms: f64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+/// Nearest-rank percentile of an ascending-sorted slice. `pct` is in 0..=100.
+pub fn percentile<T: Copy + Default>(sorted: &[T], pct: f64) -> T {
+    if sorted.is_empty() {
+        return T::default();
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Summarize latency samples. Sorts the input in place.
+pub fn latency_stats(samples: &mut [u64]) -> LatencyStats {
+    if samples.is_empty() {
+        return LatencyStats::default();
+    }
+    samples.sort_unstable();
+    LatencyStats {
+        samples: samples.len(),
+        average_ms: samples.iter().sum::<u64>() as f64 / samples.len() as f64,
+        p50_ms: percentile(samples, 50.0),
+        p95_ms: percentile(samples, 95.0),
+        max_ms: samples[samples.len() - 1],
+    }
+}
@@ -0,0 +1,304 @@
+//! Plain-text helpers shared by export and analysis commands.
+
+use std::collections::{HashMap, HashSet};
+
+/// Common English function words left out of `word_frequency` when asked
+pub const DEFAULT_STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "am", "an", "and", "any", "are", "as", "at", "be",
+    "because", "been", "before", "being", "but", "by", "can", "could", "did", "do", "does",
+    "doing", "for", "from", "had", "has", "have", "having", "he", "her", "here", "hers", "him",
+    "his", "how", "i", "if", "in", "into", "is", "it", "its", "just", "me", "more", "most", "my",
+    "no", "not", "now", "of", "on", "once", "only", "or", "other", "our", "out", "over", "own",
+    "same", "she", "should", "so", "some", "such", "than", "that", "the", "their", "them", "then",
+    "there", "these", "they", "this", "those", "through", "to", "too", "under", "up", "very",
+    "was", "we", "were", "what", "when", "where", "which", "while", "who", "why", "will", "with",
+    "would", "you", "your",
+];
+
+/// Word characters for whole-word matching
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Replace every whole-word occurrence of `from` with `to`.
+/// A match only counts when it isn't bordered by other word characters,
+/// so replacing "Ann" leaves "Annual" alone.
+pub fn replace_whole_words(text: &str, from: &str, to: &str) -> String {
+    if from.is_empty() {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, _) in text.match_indices(from) {
+        let end = start + from.len();
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+        // A pattern edge that isn't a word character is its own boundary
+        let bounded_start = !before.is_some_and(is_word_char) || !from.starts_with(is_word_char);
+        let bounded_end = !after.is_some_and(is_word_char) || !from.ends_with(is_word_char);
+        if bounded_start && bounded_end {
+            out.push_str(&text[last..start]);
+            out.push_str(to);
+            last = end;
+        }
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// Apply find/replace pairs in order, each as a whole-word replacement
+pub fn apply_replacements(text: &str, replacements: &[(String, String)]) -> String {
+    replacements
+        .iter()
+        .fold(text.to_string(), |acc, (from, to)| {
+            replace_whole_words(&acc, from, to)
+        })
+}
+
+/// Escape `& < > " '` so text can be embedded in HTML
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Remove HTML tags (`<` followed by a letter, `/`, `!` or `?`, through the next
+/// `>`), keeping their inner text. A lone `<` as in "a < b" is left alone. Repeats
+/// until nothing changes, so tags split around other tags don't survive and
+/// stripping twice gives the same result.
+pub fn strip_tags(text: &str) -> String {
+    let mut current = text.to_string();
+    loop {
+        let mut out = String::with_capacity(current.len());
+        let mut rest = current.as_str();
+        while let Some(start) = rest.find('<') {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let opens_tag = after
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?'));
+            match after.find('>').filter(|_| opens_tag) {
+                Some(end) => rest = &after[end + 1..],
+                None => {
+                    out.push('<');
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        if out == current {
+            return out;
+        }
+        current = out;
+    }
+}
+
+/// Byte ranges of case-insensitive matches of `query` in `text`, scanning left to
+/// right without overlaps (after a match, the search resumes at its end). Ranges
+/// always fall on char boundaries, even where lowercasing changes byte lengths.
+pub fn find_case_insensitive(text: &str, query: &str) -> Vec<(usize, usize)> {
+    let needle: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while let Some(c) = text[start..].chars().next() {
+        match match_end(text, start, &needle) {
+            Some(end) => {
+                matches.push((start, end));
+                start = end;
+            }
+            None => start += c.len_utf8(),
+        }
+    }
+    matches
+}
+
+/// End of a case-insensitive match of `needle` starting at byte `start`
+fn match_end(text: &str, start: usize, needle: &[char]) -> Option<usize> {
+    let mut matched = 0;
+    for (offset, c) in text[start..].char_indices() {
+        for lower in c.to_lowercase() {
+            if needle.get(matched) != Some(&lower) {
+                return None;
+            }
+            matched += 1;
+        }
+        if matched == needle.len() {
+            return Some(start + offset + c.len_utf8());
+        }
+    }
+    None
+}
+
+/// The `top_n` most frequent words, lowercased with surrounding punctuation removed.
+/// Apostrophes inside words are kept ("don't"). Words in `stopwords` (lowercase)
+/// are skipped. Ties are ordered alphabetically.
+pub fn word_frequency(
+    text: &str,
+    top_n: usize,
+    stopwords: &HashSet<String>,
+) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let lowered = text.to_lowercase();
+    for raw in lowered.split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '’')) {
+        let word = raw.trim_matches(|c: char| c == '\'' || c == '’');
+        if word.is_empty() || !word.chars().any(char::is_alphabetic) {
+            continue;
+        }
+        if stopwords.contains(word) {
+            continue;
+        }
+        *counts.entry(word.to_string()).or_insert(0) += 1;
+    }
+
+    let mut words: Vec<(String, usize)> = counts.into_iter().collect();
+    words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    words.truncate(top_n);
+    words
+}
+
+/// How much of the content `looks_like_text` inspects
+const TEXT_SAMPLE_BYTES: usize = 8192;
+/// Largest share of control or undecodable characters text may contain
+const MAX_BINARY_RATIO: f64 = 0.1;
+
+/// Whether content is plausibly text rather than an accidental binary save: no NUL
+/// bytes, and few control characters or invalid UTF-8 sequences in the first 8KB
+pub fn looks_like_text(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(TEXT_SAMPLE_BYTES)];
+    if sample.contains(&0) {
+        return false;
+    }
+    let decoded = String::from_utf8_lossy(sample);
+    let mut total = 0;
+    let mut suspicious = 0;
+    for c in decoded.chars() {
+        total += 1;
+        let control = c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\u{c}');
+        if control || c == char::REPLACEMENT_CHARACTER {
+            suspicious += 1;
+        }
+    }
+    total == 0 || (suspicious as f64 / total as f64) <= MAX_BINARY_RATIO
+}
+
+/// Replace each `{{name}}` in `template` with its value. Unknown placeholders are
+/// left as they are.
+pub fn fill_placeholders(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{{{}}}}}", name), value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_word_replacement_respects_boundaries() {
+        assert_eq!(
+            replace_whole_words("Ann met Annual Ann.", "Ann", "Bea"),
+            "Bea met Annual Bea."
+        );
+        assert_eq!(replace_whole_words("cab ab", "ab", "x"), "cab x");
+        assert_eq!(replace_whole_words("text", "", "x"), "text");
+    }
+
+    #[test]
+    fn non_word_pattern_edges_are_boundaries() {
+        assert_eq!(replace_whole_words("x.y x.yz", ".y", "!"), "x! x.yz");
+    }
+
+    #[test]
+    fn replacements_apply_in_order() {
+        let pairs = vec![
+            ("cat".to_string(), "dog".to_string()),
+            ("dog".to_string(), "bird".to_string()),
+        ];
+        assert_eq!(apply_replacements("cat dog", &pairs), "bird bird");
+    }
+
+    #[test]
+    fn escapes_html_specials() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn strips_tags_but_not_comparisons() {
+        assert_eq!(
+            strip_tags("<b>bold</b> and <img src=x onerror=alert(1)>"),
+            "bold and "
+        );
+        assert_eq!(strip_tags("a < b && c > d"), "a < b && c > d");
+        assert_eq!(strip_tags("<<b>script>alert(1)<</b>/script>"), "alert(1)");
+        assert_eq!(strip_tags("unclosed <em"), "unclosed <em");
+    }
+
+    #[test]
+    fn case_insensitive_matches_do_not_overlap() {
+        assert_eq!(find_case_insensitive("Aaa aA", "aa"), vec![(0, 2), (4, 6)]);
+        assert!(find_case_insensitive("abc", "").is_empty());
+    }
+
+    #[test]
+    fn case_insensitive_ranges_stay_on_char_boundaries() {
+        // "İ" lowercases to two chars, so byte offsets can't be copied across
+        let text = "an İstanbul trip";
+        let matches = find_case_insensitive(text, "İSTANBUL");
+        assert_eq!(matches.len(), 1);
+        let (start, end) = matches[0];
+        assert_eq!(&text[start..end], "İstanbul");
+        assert_eq!(find_case_insensitive("ÉCOLE école", "école").len(), 2);
+    }
+
+    #[test]
+    fn word_frequency_skips_stopwords_and_breaks_ties_alphabetically() {
+        let stopwords: HashSet<String> = ["the".to_string()].into_iter().collect();
+        let words = word_frequency("The cat, the dog. Don't 'cat' 42 dog! bee", 3, &stopwords);
+        assert_eq!(
+            words,
+            vec![
+                ("cat".to_string(), 2),
+                ("dog".to_string(), 2),
+                ("bee".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn binary_content_does_not_look_like_text() {
+        assert!(looks_like_text(b"plain text\twith tabs\r\n"));
+        assert!(looks_like_text(b""));
+        assert!(!looks_like_text(b"abc\0def"));
+        assert!(!looks_like_text(&[0xff, 0xfe, 0x01, 0x02, b'a']));
+    }
+
+    #[test]
+    fn fills_known_placeholders_only() {
+        assert_eq!(
+            fill_placeholders(
+                "{{title}} by {{author}} {{unknown}}",
+                &[("title", "Notes"), ("author", "Sam")]
+            ),
+            "Notes by Sam {{unknown}}"
+        );
+    }
+}